@@ -0,0 +1,63 @@
+use criterion::{Criterion, Throughput, criterion_group, criterion_main};
+use std::hint::black_box;
+use sui_sdk::rpc_types::{SuiEvent, SuiTransactionBlockResponse};
+use sui_txs::{
+    Dex,
+    config::default_dexes,
+    events::{DecodeMode, decode_swap},
+};
+
+// Swap events of the decode fixtures, or of the `*.json` transactions in the
+// directory named by `DECODE_CORPUS`, e.g. one loaded by `RecordedSource`.
+fn corpus() -> Vec<(Dex, SuiEvent)> {
+    let dir = std::env::var("DECODE_CORPUS")
+        .unwrap_or_else(|_| concat!(env!("CARGO_MANIFEST_DIR"), "/fixtures/decode").to_string());
+    let patterns = default_dexes()
+        .into_iter()
+        .map(|d| (d.kind, d.swap_event_pattern().unwrap()))
+        .collect::<Vec<_>>();
+
+    let mut events = vec![];
+    for entry in std::fs::read_dir(&dir).unwrap() {
+        let path = entry.unwrap().path();
+        let name = path.to_string_lossy();
+        if !name.ends_with(".json") || name.ends_with(".expected.json") {
+            continue;
+        }
+        let Ok(tx) = serde_json::from_str::<SuiTransactionBlockResponse>(
+            &std::fs::read_to_string(&path).unwrap(),
+        ) else {
+            continue;
+        };
+        for event in tx.events.into_iter().flat_map(|e| e.data) {
+            if let Some((dex, _)) = patterns
+                .iter()
+                .find(|(_, pattern)| pattern.matches(&event.type_).is_some())
+            {
+                events.push((*dex, event));
+            }
+        }
+    }
+    events
+}
+
+fn bench_swap_events(c: &mut Criterion) {
+    let events = corpus();
+    assert!(!events.is_empty(), "no swap events in the corpus");
+
+    let mut group = c.benchmark_group("swap_events");
+    group.throughput(Throughput::Elements(events.len() as u64));
+    for mode in [DecodeMode::Json, DecodeMode::Bcs] {
+        group.bench_function(format!("{mode:?}"), |b| {
+            b.iter(|| {
+                for (dex, event) in &events {
+                    black_box(decode_swap(*dex, black_box(event), mode).unwrap());
+                }
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_swap_events);
+criterion_main!(benches);
@@ -0,0 +1,348 @@
+use anyhow::{Context, Result};
+use bigdecimal::BigDecimal;
+use mini_macro::here as h;
+use move_core_types::language_storage::StructTag;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, HashMap},
+    str::FromStr,
+};
+use sui_sdk::rpc_types::SuiObjectDataOptions;
+use sui_types::base_types::ObjectID;
+
+use crate::{
+    Swap, TransactionKind, TxType,
+    pricing::{PriceSource, normalize_coin_type},
+    source::TransactionSource,
+};
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum TradeKind {
+    // swaps going round back to the coin they started from
+    Arbitrage,
+    // a swap undone on the same pool by another transaction of the wallet
+    // shortly after, as the front and back run of a sandwich are. Whether
+    // anyone else swapped on the pool in between is not checked.
+    RoundTrip,
+    // one swap, or a route from one coin to another
+    Route,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Trade {
+    pub tx_hash: String,
+    pub timestamp_ms: u64,
+    pub kind: TradeKind,
+    // coins in the order they were swapped through, e.g. A, B, C, A for a
+    // cycle. Coins that could not be resolved are empty.
+    pub path: Vec<String>,
+    pub swaps: usize,
+    // set for cycles, and for the second swap of a round trip
+    pub start_token: Option<String>,
+    // net change in `start_token`, flash loan fees taken off
+    pub profit: Option<i128>,
+    pub profit_usd: Option<BigDecimal>,
+}
+
+// Classifies the swaps of a wallet's transactions. Swap events don't always
+// name their coins, those are read off the pools, which can be registered or
+// looked up with `resolve_pools`. Coins need their decimals registered for
+// profits to be valued.
+pub struct ArbitrageAnalyzer<'a> {
+    prices: &'a dyn PriceSource,
+    pools: HashMap<String, (String, String)>,
+    decimals: HashMap<String, u32>,
+    round_trip_window_ms: u64,
+}
+
+// DeepBook fills are order book trades rather than `Swap`s and are left out,
+// a cycle through a DeepBook pool is not recognised.
+fn swaps(tx_type: &TxType) -> Vec<&Swap> {
+    match tx_type {
+        TxType::Swap(swap) => vec![swap],
+        TxType::FlashLoan(flash) => flash.swaps.iter().collect(),
+        TxType::Batch(tx_types) => tx_types.iter().flat_map(swaps).collect(),
+        _ => vec![],
+    }
+}
+
+impl<'a> ArbitrageAnalyzer<'a> {
+    pub fn new(prices: &'a dyn PriceSource) -> Self {
+        Self {
+            prices,
+            pools: HashMap::new(),
+            decimals: HashMap::new(),
+            // the two swaps of a sandwich usually land in the same or the
+            // next checkpoint
+            round_trip_window_ms: 2_000,
+        }
+    }
+
+    pub fn pool(mut self, pool: &str, coin_a: &str, coin_b: &str) -> Self {
+        self.pools.insert(
+            pool.to_string(),
+            (normalize_coin_type(coin_a), normalize_coin_type(coin_b)),
+        );
+        self
+    }
+
+    pub fn decimals(mut self, coin_type: &str, decimals: u32) -> Self {
+        self.decimals
+            .insert(normalize_coin_type(coin_type), decimals);
+        self
+    }
+
+    pub fn round_trip_window_ms(mut self, window_ms: u64) -> Self {
+        self.round_trip_window_ms = window_ms;
+        self
+    }
+
+    // Looks up the coins of the pools swapped on in `txs` whose events don't
+    // name them, from the pool object's type parameters.
+    pub async fn resolve_pools(
+        &mut self,
+        source: &dyn TransactionSource,
+        txs: &[TransactionKind],
+    ) -> Result<()> {
+        let mut unknown = txs
+            .iter()
+            .flat_map(|tx| swaps(&tx.tx_type))
+            .filter(|swap| swap.in_token.is_empty() && !self.pools.contains_key(&swap.pool))
+            .map(|swap| swap.pool.clone())
+            .collect::<Vec<_>>();
+        unknown.sort();
+        unknown.dedup();
+
+        let options = SuiObjectDataOptions::new().with_type();
+        for pool in unknown {
+            let object_id = ObjectID::from_str(&pool).context(h!())?;
+            let object = source.get_object(object_id, &options).await.context(h!())?;
+            let tag = object
+                .data
+                .as_ref()
+                .and_then(|data| data.object_type().ok())
+                .and_then(|object_type| StructTag::try_from(object_type).ok());
+            match tag.as_ref().map(|tag| tag.type_params.as_slice()) {
+                Some([coin_a, coin_b]) => {
+                    let coins = (
+                        normalize_coin_type(&coin_a.to_string()),
+                        normalize_coin_type(&coin_b.to_string()),
+                    );
+                    self.pools.insert(pool, coins);
+                }
+                _ => tracing::warn!("coins of pool {pool} are unknown"),
+            }
+        }
+        Ok(())
+    }
+
+    // The coins a swap gave and got, empty when unknown.
+    fn tokens(&self, swap: &Swap) -> (String, String) {
+        if !swap.in_token.is_empty() {
+            return (
+                normalize_coin_type(&swap.in_token),
+                normalize_coin_type(&swap.out_token),
+            );
+        }
+        match self.pools.get(&swap.pool) {
+            Some((coin_a, coin_b)) if swap.a2b => (coin_a.clone(), coin_b.clone()),
+            Some((coin_a, coin_b)) => (coin_b.clone(), coin_a.clone()),
+            None => (String::new(), String::new()),
+        }
+    }
+
+    fn usd(&self, coin_type: &str, amount: i128, timestamp_ms: u64) -> Option<BigDecimal> {
+        let decimals = *self.decimals.get(coin_type)?;
+        let price = self.prices.usd_price(coin_type, timestamp_ms)?;
+        Some(BigDecimal::from(amount) / BigDecimal::from(10u128.pow(decimals)) * price)
+    }
+
+    fn trade(&self, tx: &TransactionKind) -> Option<Trade> {
+        let swaps = swaps(&tx.tx_type);
+        if swaps.is_empty() {
+            return None;
+        }
+        let tokens = swaps
+            .iter()
+            .map(|swap| self.tokens(swap))
+            .collect::<Vec<_>>();
+
+        let mut path = vec![tokens[0].0.clone()];
+        path.extend(tokens.iter().map(|(_, out_token)| out_token.clone()));
+
+        let start = &tokens[0].0;
+        let is_cycle = swaps.len() > 1 && !start.is_empty() && *start == tokens[tokens.len() - 1].1;
+        let mut trade = Trade {
+            tx_hash: tx.tx_hash.to_string(),
+            timestamp_ms: tx.event_timestamp_ms,
+            kind: TradeKind::Route,
+            path,
+            swaps: swaps.len(),
+            start_token: None,
+            profit: None,
+            profit_usd: None,
+        };
+        if !is_cycle {
+            return Some(trade);
+        }
+
+        // split routes may go through the start coin more than once
+        let mut net: BTreeMap<&str, i128> = BTreeMap::new();
+        for (swap, (in_token, out_token)) in swaps.iter().zip(&tokens) {
+            *net.entry(in_token).or_default() -= swap.in_amount;
+            *net.entry(out_token).or_default() += swap.out_amount;
+        }
+        let mut profit = net.get(start.as_str()).copied().unwrap_or_default();
+        if let TxType::FlashLoan(flash) = &tx.tx_type {
+            profit -= flash
+                .loans
+                .iter()
+                .filter(|loan| loan.asset.as_deref() == Some(start.as_str()))
+                .filter_map(|loan| loan.fee)
+                .sum::<u64>() as i128;
+        }
+
+        trade.kind = TradeKind::Arbitrage;
+        trade.profit_usd = self.usd(start, profit, tx.event_timestamp_ms);
+        trade.start_token = Some(start.clone());
+        trade.profit = Some(profit);
+        Some(trade)
+    }
+
+    // One trade per transaction that swapped, in the order of `txs`.
+    pub fn analyze(&self, txs: &[TransactionKind]) -> Vec<Trade> {
+        let mut trades = vec![];
+        // single swaps, by index into `trades`, for pairing up round trips
+        let mut singles: Vec<(usize, &Swap)> = vec![];
+        for tx in txs {
+            let Some(trade) = self.trade(tx) else {
+                continue;
+            };
+            if let [swap] = swaps(&tx.tx_type).as_slice() {
+                singles.push((trades.len(), swap));
+            }
+            trades.push(trade);
+        }
+
+        singles.sort_by_key(|(i, _)| trades[*i].timestamp_ms);
+        let mut paired = vec![false; singles.len()];
+        for first in 0..singles.len() {
+            if paired[first] {
+                continue;
+            }
+            let (i, first_swap) = singles[first];
+            let second = (first + 1..singles.len()).find(|&second| {
+                let (j, second_swap) = singles[second];
+                !paired[second]
+                    && second_swap.pool == first_swap.pool
+                    && second_swap.a2b != first_swap.a2b
+                    && trades[j].timestamp_ms - trades[i].timestamp_ms <= self.round_trip_window_ms
+            });
+            let Some(second) = second else {
+                continue;
+            };
+            paired[first] = true;
+            paired[second] = true;
+            let (j, second_swap) = singles[second];
+            trades[i].kind = TradeKind::RoundTrip;
+            trades[j].kind = TradeKind::RoundTrip;
+
+            // what the second swap got back for what the first paid
+            let start = self.tokens(first_swap).0;
+            if !start.is_empty() {
+                let profit = second_swap.out_amount - first_swap.in_amount;
+                trades[j].profit_usd = self.usd(&start, profit, trades[j].timestamp_ms);
+                trades[j].start_token = Some(start);
+                trades[j].profit = Some(profit);
+            }
+        }
+        trades
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bigdecimal::BigDecimal;
+    use sui_types::digests::TransactionDigest;
+
+    use super::{ArbitrageAnalyzer, TradeKind};
+    use crate::{Dex, Swap, TransactionKind, TxType, pricing::StaticPrices};
+
+    const SUI: &str = "0x2::sui::SUI";
+    const USDC: &str =
+        "0xdba34672e30cb065b1f93e3ab55318768fd6fef66c15942c9f7cb846e2f900e7::usdc::USDC";
+    const CETUS: &str =
+        "0x06864a6f921804860930db6ddbe2e16acdf8504495ea7481637a1c8b9a8fe54b::cetus::CETUS";
+
+    fn swap(pool: &str, a2b: bool, in_amount: i128, out_amount: i128) -> Swap {
+        Swap {
+            pool: pool.to_string(),
+            dex: Dex::Cetus,
+            a2b,
+            in_amount,
+            out_amount,
+            in_token: String::new(),
+            out_token: String::new(),
+            before_sqrt_price: "0".to_string(),
+            after_sqrt_price: "0".to_string(),
+        }
+    }
+
+    fn tx(timestamp_ms: u64, tx_type: TxType) -> TransactionKind {
+        TransactionKind {
+            tx_type,
+            tx_hash: TransactionDigest::random(),
+            event_timestamp_ms: timestamp_ms,
+        }
+    }
+
+    fn prices() -> StaticPrices {
+        StaticPrices::new([(USDC.to_string(), BigDecimal::from(1))].into())
+    }
+
+    fn analyzer(prices: &StaticPrices) -> ArbitrageAnalyzer<'_> {
+        ArbitrageAnalyzer::new(prices)
+            .pool("0xa", SUI, USDC)
+            .pool("0xb", CETUS, SUI)
+            .pool("0xc", CETUS, USDC)
+            .decimals(USDC, 6)
+    }
+
+    #[test]
+    fn test_round_trip_and_route() {
+        let prices = prices();
+        let txs = [
+            tx(
+                1_000,
+                TxType::Swap(swap("0xa", false, 50_000_000, 14_000_000_000)),
+            ),
+            tx(
+                1_500,
+                TxType::Swap(swap("0xc", false, 1_000_000, 9_000_000_000)),
+            ),
+            tx(
+                1_800,
+                TxType::Swap(swap("0xa", true, 14_000_000_000, 50_400_000)),
+            ),
+            // too late to undo the second
+            tx(
+                9_000,
+                TxType::Swap(swap("0xc", true, 9_000_000_000, 990_000)),
+            ),
+        ];
+        let trades = analyzer(&prices).analyze(&txs);
+        let kinds = trades.iter().map(|t| t.kind).collect::<Vec<_>>();
+        assert_eq!(
+            kinds,
+            [
+                TradeKind::RoundTrip,
+                TradeKind::Route,
+                TradeKind::RoundTrip,
+                TradeKind::Route
+            ]
+        );
+        assert_eq!(trades[0].profit, None);
+        assert_eq!(trades[2].start_token.as_deref(), Some(USDC));
+        assert_eq!(trades[2].profit, Some(400_000));
+    }
+}
@@ -0,0 +1,319 @@
+use alloy_primitives::{Address, B256, U256};
+use std::{collections::HashMap, str::FromStr};
+use sui_sdk::rpc_types::SuiTransactionBlockResponse;
+use sui_types::{base_types::SuiAddress, gas_coin::GAS};
+
+use crate::{
+    Bridge, BridgeTransfer, ForeignAddress, TxType,
+    config::{CoinConfig, coin_decimals, default_coins},
+    errors::EventDecodeError,
+    event_match::EventPattern,
+    events::{
+        DecodeMode, SuiBridgeTokenDeposited, SuiBridgeTransferClaimed, WormholeMessage,
+        WormholeTransferRedeemed, parse,
+    },
+    pricing::normalize_coin_type,
+};
+
+const SUI_BRIDGE: &str = "0xb";
+const WORMHOLE: &str = "0x5306f64e312b581766351c07af79c72fcb1cd25147157fdc2f8ad76de9a3fb6a";
+const TOKEN_BRIDGE: &str = "0x26efee2b51c911237888e5dc6702868abca3c7ac12c53f76ef8eba0697695e3d";
+// the token bridge's `EmitterCap`, the sender of its Wormhole messages
+const TOKEN_BRIDGE_EMITTER: &str =
+    "0xccceeb29348f71bdd22ffef43a2a19c1f5b5e17c5cca5411529120182672ade5";
+
+const SUI_BRIDGE_SUI_MAINNET: u16 = 0;
+const WORMHOLE_SUI: u16 = 21;
+
+// The name of a chain from its id in the bridge's numbering.
+pub fn chain_name(bridge: Bridge, chain: u16) -> Option<&'static str> {
+    let name = match (bridge, chain) {
+        (Bridge::SuiBridge, 0) => "Sui",
+        (Bridge::SuiBridge, 1) => "Sui Testnet",
+        (Bridge::SuiBridge, 10) => "Ethereum",
+        (Bridge::SuiBridge, 11) => "Ethereum Sepolia",
+        (Bridge::Wormhole, 1) => "Solana",
+        (Bridge::Wormhole, 2) => "Ethereum",
+        (Bridge::Wormhole, 4) => "BSC",
+        (Bridge::Wormhole, 5) => "Polygon",
+        (Bridge::Wormhole, 6) => "Avalanche",
+        (Bridge::Wormhole, 21) => "Sui",
+        (Bridge::Wormhole, 23) => "Arbitrum",
+        (Bridge::Wormhole, 24) => "Optimism",
+        (Bridge::Wormhole, 30) => "Base",
+        _ => return None,
+    };
+    Some(name)
+}
+
+// What identifies a transfer on both chains: the bridge, where it was sent
+// from and the sequence number the source side gave it.
+pub fn transfer_id(transfer: &BridgeTransfer) -> (Bridge, u16, u64) {
+    (transfer.bridge, transfer.source_chain, transfer.sequence)
+}
+
+// An address of another chain, an EVM one when it fits in 20 bytes. Wormhole
+// left pads those to 32.
+pub fn foreign_address(bytes: &[u8]) -> Option<ForeignAddress> {
+    match bytes.len() {
+        20 => Some(ForeignAddress::Evm(Address::from_slice(bytes))),
+        32 if bytes[..12].iter().all(|b| *b == 0) => {
+            Some(ForeignAddress::Evm(Address::from_slice(&bytes[12..])))
+        }
+        32 => Some(ForeignAddress::Other(B256::from_slice(bytes))),
+        _ => None,
+    }
+}
+
+// A Wormhole token bridge transfer, payload 1, or 3 when it carries a payload
+// of its own. Amounts are normalized to 8 decimals.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WormholeTransfer {
+    pub amount: U256,
+    pub token_address: B256,
+    pub token_chain: u16,
+    pub to: B256,
+    pub to_chain: u16,
+}
+
+// A Wormhole amount in the units of a coin with `decimals`. Coins with more
+// than 8 decimals lose the rest when normalized.
+pub fn denormalize(amount: U256, decimals: u32) -> U256 {
+    match decimals.checked_sub(8) {
+        Some(shift) => amount * U256::from(10).pow(U256::from(shift)),
+        None => amount,
+    }
+}
+
+pub fn wormhole_transfer(payload: &[u8]) -> Option<WormholeTransfer> {
+    if !matches!(payload.first(), Some(1 | 3)) || payload.len() < 101 {
+        return None;
+    }
+    let chain = |at: usize| u16::from_be_bytes([payload[at], payload[at + 1]]);
+    Some(WormholeTransfer {
+        amount: U256::from_be_slice(&payload[1..33]),
+        token_address: B256::from_slice(&payload[33..65]),
+        token_chain: chain(65),
+        to: B256::from_slice(&payload[67..99]),
+        to_chain: chain(99),
+    })
+}
+
+// The coin `who` sent or received, and how much of it. SUI only counts when
+// nothing else moved, it pays for gas.
+fn moved_coin(
+    tx_resp: &SuiTransactionBlockResponse,
+    who: Option<SuiAddress>,
+    received: bool,
+) -> Option<(SuiAddress, String, u128)> {
+    let balance_changes = tx_resp.balance_changes.as_ref()?;
+    let sui = GAS::type_tag();
+    let mut moved = balance_changes.iter().filter_map(|c| {
+        let owner = c.owner.get_owner_address().ok()?;
+        if who.is_some_and(|who| who != owner) || (c.amount > 0) != received {
+            return None;
+        }
+        Some((owner, c))
+    });
+    let (owner, change) = moved
+        .clone()
+        .find(|(_, c)| c.coin_type != sui)
+        .or_else(|| moved.next())?;
+    Some((
+        owner,
+        change.coin_type.to_string(),
+        change.amount.unsigned_abs(),
+    ))
+}
+
+// Coins leaving Sui through the Sui Bridge or the Wormhole token bridge, and
+// coins arriving through them.
+pub struct BridgeDecoder {
+    deposited: EventPattern,
+    claimed: EventPattern,
+    message: EventPattern,
+    redeemed: EventPattern,
+    decimals: HashMap<String, u32>,
+}
+
+impl Default for BridgeDecoder {
+    fn default() -> Self {
+        let pattern = |s: String| EventPattern::from_str(&s).expect("valid bridge event");
+        Self {
+            deposited: pattern(format!("{SUI_BRIDGE}::bridge::TokenDepositedEvent")),
+            claimed: pattern(format!("{SUI_BRIDGE}::bridge::TokenTransferClaimed")),
+            message: pattern(format!("{WORMHOLE}::publish_message::WormholeMessage")),
+            redeemed: pattern(format!(
+                "{TOKEN_BRIDGE}::complete_transfer::TransferRedeemed"
+            )),
+            decimals: coin_decimals(&default_coins()),
+        }
+    }
+}
+
+impl BridgeDecoder {
+    // The coins whose Wormhole amounts can be told in their own units, in
+    // place of `default_coins`.
+    pub fn with_coins(mut self, coins: &[CoinConfig]) -> Self {
+        self.decimals = coin_decimals(coins);
+        self
+    }
+
+    pub fn decode(
+        &self,
+        tx_resp: &SuiTransactionBlockResponse,
+        mode: DecodeMode,
+    ) -> Result<Option<TxType>, EventDecodeError> {
+        let Some(events) = &tx_resp.events else {
+            return Ok(None);
+        };
+
+        let mut tx_types = vec![];
+        for event in &events.data {
+            if self.deposited.matches(&event.type_).is_some() {
+                let e: SuiBridgeTokenDeposited = parse("SuiBridge", event, mode)?;
+                let sender = SuiAddress::from_bytes(&e.sender_address).unwrap_or(event.sender);
+                let coin = moved_coin(tx_resp, Some(sender), false);
+                tx_types.push(TxType::BridgeOut(BridgeTransfer {
+                    bridge: Bridge::SuiBridge,
+                    source_chain: e.source_chain.into(),
+                    destination_chain: e.target_chain.into(),
+                    sui_address: sender.to_string(),
+                    foreign_address: foreign_address(&e.target_address),
+                    coin_type: coin.map(|(_, coin_type, _)| coin_type),
+                    amount: Some(U256::from(e.amount)),
+                    sequence: e.seq_num,
+                    nonce: None,
+                }));
+            } else if self.claimed.matches(&event.type_).is_some() {
+                let e: SuiBridgeTransferClaimed = parse("SuiBridge", event, mode)?;
+                // anyone may claim for the recipient, it is whoever got the coins
+                let coin = moved_coin(tx_resp, None, true);
+                tx_types.push(TxType::BridgeIn(BridgeTransfer {
+                    bridge: Bridge::SuiBridge,
+                    source_chain: e.message_key.source_chain.into(),
+                    destination_chain: SUI_BRIDGE_SUI_MAINNET,
+                    sui_address: coin
+                        .as_ref()
+                        .map_or(event.sender, |(owner, _, _)| *owner)
+                        .to_string(),
+                    foreign_address: None,
+                    coin_type: coin.as_ref().map(|(_, coin_type, _)| coin_type.clone()),
+                    amount: coin.map(|(_, _, amount)| U256::from(amount)),
+                    sequence: e.message_key.bridge_seq_num,
+                    nonce: None,
+                }));
+            } else if self.message.matches(&event.type_).is_some() {
+                let e: WormholeMessage = parse("Wormhole", event, mode)?;
+                // other apps publish through Wormhole core as well
+                if e.sender.to_string() != TOKEN_BRIDGE_EMITTER {
+                    continue;
+                }
+                let Some(transfer) = wormhole_transfer(&e.payload) else {
+                    continue;
+                };
+                let coin = moved_coin(tx_resp, Some(event.sender), false);
+                let coin_type = coin.map(|(_, coin_type, _)| coin_type);
+                // without the coin's decimals the normalized amount can't be
+                // told in its units
+                let amount = coin_type
+                    .as_deref()
+                    .and_then(|coin_type| self.decimals.get(&normalize_coin_type(coin_type)))
+                    .map(|decimals| denormalize(transfer.amount, *decimals));
+                tx_types.push(TxType::BridgeOut(BridgeTransfer {
+                    bridge: Bridge::Wormhole,
+                    source_chain: WORMHOLE_SUI,
+                    destination_chain: transfer.to_chain,
+                    sui_address: event.sender.to_string(),
+                    foreign_address: foreign_address(transfer.to.as_slice()),
+                    coin_type,
+                    amount,
+                    sequence: e.sequence,
+                    nonce: Some(e.nonce),
+                }));
+            } else if self.redeemed.matches(&event.type_).is_some() {
+                let e: WormholeTransferRedeemed = parse("Wormhole", event, mode)?;
+                let coin = moved_coin(tx_resp, None, true);
+                tx_types.push(TxType::BridgeIn(BridgeTransfer {
+                    bridge: Bridge::Wormhole,
+                    source_chain: e.emitter_chain,
+                    destination_chain: WORMHOLE_SUI,
+                    sui_address: coin
+                        .as_ref()
+                        .map_or(event.sender, |(owner, _, _)| *owner)
+                        .to_string(),
+                    foreign_address: None,
+                    coin_type: coin.as_ref().map(|(_, coin_type, _)| coin_type.clone()),
+                    amount: coin.map(|(_, _, amount)| U256::from(amount)),
+                    sequence: e.sequence,
+                    nonce: None,
+                }));
+            }
+        }
+
+        Ok(match tx_types.len() {
+            0 => None,
+            1 => tx_types.pop(),
+            _ => Some(TxType::Batch(tx_types)),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy_primitives::{U256, address};
+
+    use super::{denormalize, foreign_address, wormhole_transfer};
+    use crate::ForeignAddress;
+
+    #[test]
+    fn test_wormhole_transfer() {
+        let mut payload = vec![1];
+        payload.extend(U256::from(123_456_789u64).to_be_bytes::<32>());
+        payload.extend([0xaa; 32]);
+        payload.extend(21u16.to_be_bytes());
+        payload.extend([0; 12]);
+        payload.extend([0x12; 20]);
+        payload.extend(2u16.to_be_bytes());
+        payload.extend([0; 32]);
+
+        let transfer = wormhole_transfer(&payload).unwrap();
+        assert_eq!(transfer.amount, U256::from(123_456_789u64));
+        assert_eq!(transfer.token_chain, 21);
+        assert_eq!(transfer.to_chain, 2);
+        assert_eq!(
+            foreign_address(transfer.to.as_slice()),
+            Some(ForeignAddress::Evm(address!(
+                "1212121212121212121212121212121212121212"
+            )))
+        );
+
+        // an attestation, payload 2, moves no tokens
+        payload[0] = 2;
+        assert_eq!(wormhole_transfer(&payload), None);
+        assert_eq!(wormhole_transfer(&[1; 50]), None);
+    }
+
+    #[test]
+    fn test_denormalize() {
+        // 1.5 SUI, 9 decimals
+        assert_eq!(
+            denormalize(U256::from(150_000_000u64), 9),
+            U256::from(1_500_000_000u64)
+        );
+        // 1.5 USDC, 6 decimals, isn't normalized
+        assert_eq!(
+            denormalize(U256::from(1_500_000u64), 6),
+            U256::from(1_500_000u64)
+        );
+    }
+
+    #[test]
+    fn test_foreign_address() {
+        assert!(matches!(
+            foreign_address(&[0x34; 32]),
+            Some(ForeignAddress::Other(_))
+        ));
+        assert_eq!(foreign_address(&[0x34; 8]), None);
+    }
+}
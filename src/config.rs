@@ -0,0 +1,621 @@
+use anyhow::{Context, Result};
+use bigdecimal::BigDecimal;
+use mini_macro::here as h;
+use move_core_types::language_storage::TypeTag;
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+use sui_types::base_types::{ObjectID, SuiAddress};
+
+use crate::{
+    Dex,
+    errors::ConfigError,
+    event_match::EventPattern,
+    pricing::normalize_coin_type,
+    rpc::{RateLimit, RetryPolicy},
+};
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    #[serde(default)]
+    pub default_network: Option<String>,
+    pub networks: BTreeMap<String, NetworkConfig>,
+    #[serde(default)]
+    pub wallets: Vec<WalletConfig>,
+    // added to `default_dexes`, replacing the default of the same name. See
+    // `Config::dexes`.
+    #[serde(default)]
+    pub dex: Vec<DexConfig>,
+    // packages whose calls are reported by name when no event is decoded
+    #[serde(default)]
+    pub packages: Vec<PackageConfig>,
+    // added to `default_coins`, replacing the default of the same type. See
+    // `Config::coins`.
+    #[serde(default)]
+    pub coins: Vec<CoinConfig>,
+    #[serde(default)]
+    pub sinks: Vec<SinkConfig>,
+    #[serde(default)]
+    pub pricing: Vec<PricingConfig>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct NetworkConfig {
+    pub rpc_urls: Vec<String>,
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+    #[serde(default)]
+    pub retry: RetryPolicy,
+    #[serde(default)]
+    pub rate_limit: Option<RateLimit>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct WalletConfig {
+    pub label: String,
+    pub address: String,
+    #[serde(default)]
+    pub network: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct DexConfig {
+    pub name: String,
+    pub kind: Dex,
+    pub package: String,
+    // id of the first version of `package`, which upgraded packages keep
+    // emitting their events under
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub original_package: Option<String>,
+    // `module::Struct` of the swap event emitted by `package`, with `<_, _>`
+    // type params for generic events
+    pub swap_event: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct PackageConfig {
+    pub name: String,
+    pub package: String,
+}
+
+// Decimals of a coin, for amounts that come scaled or priced per whole coin:
+// Wormhole transfers and liquidation penalties.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct CoinConfig {
+    pub coin_type: String,
+    pub decimals: u32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "kind", rename_all = "snake_case", deny_unknown_fields)]
+pub enum SinkConfig {
+    Stdout,
+    Jsonl { path: PathBuf },
+    Csv { path: PathBuf },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "kind", rename_all = "snake_case", deny_unknown_fields)]
+pub enum PricingConfig {
+    // token type -> fixed USD price
+    Static {
+        prices: BTreeMap<String, BigDecimal>,
+    },
+}
+
+impl DexConfig {
+    pub fn swap_event_type(&self) -> String {
+        format!(
+            "{}::{}",
+            ObjectID::from_str(&self.package)
+                .map(|id| id.to_hex_literal())
+                .unwrap_or_else(|_| self.package.clone()),
+            self.swap_event
+        )
+    }
+
+    pub fn swap_event_pattern(&self) -> Result<EventPattern> {
+        self.event_pattern(&self.swap_event)
+    }
+
+    // `event` as emitted by this dex's package, `module::Name<..>`.
+    pub fn event_pattern(&self, event: &str) -> Result<EventPattern> {
+        let addresses = std::iter::once(&self.package)
+            .chain(&self.original_package)
+            .map(|package| Ok(ObjectID::from_str(package)?.into()))
+            .collect::<Result<Vec<_>>>()?;
+        EventPattern::new(addresses, event)
+    }
+}
+
+pub fn default_dexes() -> Vec<DexConfig> {
+    vec![
+        DexConfig {
+            name: "cetus".to_string(),
+            kind: Dex::Cetus,
+            package: "0x1eabed72c53feb3805120a081dc15963c204dc8d091542592abaf7a35689b2fb"
+                .to_string(),
+            original_package: None,
+            swap_event: "pool::SwapEvent".to_string(),
+        },
+        DexConfig {
+            name: "bluefin".to_string(),
+            kind: Dex::Bluefin,
+            package: "0x3492c874c1e3b3e2984e8c41b589e642d4d0a5d6459e5a9cfc2d52fd7c89c267"
+                .to_string(),
+            original_package: None,
+            swap_event: "events::AssetSwap".to_string(),
+        },
+        DexConfig {
+            name: "magma".to_string(),
+            kind: Dex::Magma,
+            package: "0x4a35d3dfef55ed3631b7158544c6322a23bc434fe4fca1234cb680ce0505f82d"
+                .to_string(),
+            original_package: None,
+            swap_event: "pool::SwapEvent".to_string(),
+        },
+    ]
+}
+
+pub fn default_coins() -> Vec<CoinConfig> {
+    [
+        ("0x2::sui::SUI", 9),
+        (
+            "0xdba34672e30cb065b1f93e3ab55318768fd6fef66c15942c9f7cb846e2f900e7::usdc::USDC",
+            6,
+        ),
+        // Wormhole USDC, USDT and WETH
+        (
+            "0x5d4b302506645c37ff133b98c4b50a5ae14841659738d6d733d59d0d217a93bf::coin::COIN",
+            6,
+        ),
+        (
+            "0xc060006111016b8a020ad5b33834984a437aaa7d3c74c18e09a95d48aceab08c::coin::COIN",
+            6,
+        ),
+        (
+            "0xaf8cd5edc19c4512f4259f0bee101a40d41ebed738ade5874359610ef8eeced5::coin::COIN",
+            8,
+        ),
+        (
+            "0x6864a6f921804860930db6ddbe2e16acdf8504495ea7481637a1c8b9a8fe54b::cetus::CETUS",
+            9,
+        ),
+        (
+            "0x549e8b69270defbfafd4f94e17ec44cdbdd99820b33bda2278dea3b9a32d3f55::cert::CERT",
+            9,
+        ),
+        (
+            "0xbde4ba4c2e274a60ce15c1cfff9e5c42e41654ac8b6d906a57efa4bd3c29f47d::hasui::HASUI",
+            9,
+        ),
+        (
+            "0xa99b8952d4f7d947ea77fe0ecdcc9e5fc0bcab2841d6e2a5aa00c3044e5544b5::navx::NAVX",
+            9,
+        ),
+    ]
+    .into_iter()
+    .map(|(coin_type, decimals)| CoinConfig {
+        coin_type: coin_type.to_string(),
+        decimals,
+    })
+    .collect()
+}
+
+// `coins` keyed by their normalized coin type.
+pub fn coin_decimals(coins: &[CoinConfig]) -> HashMap<String, u32> {
+    coins
+        .iter()
+        .map(|coin| (normalize_coin_type(&coin.coin_type), coin.decimals))
+        .collect()
+}
+
+impl FromStr for Config {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let config: Config = toml::from_str(s).context(h!())?;
+        config.validate()?;
+        Ok(config)
+    }
+}
+
+impl Config {
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("failed to read config {}", path.display()))?;
+        Self::from_str(&content).with_context(|| format!("invalid config {}", path.display()))
+    }
+
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        if self.networks.is_empty() {
+            return Err(ConfigError::NoNetworks);
+        }
+        if let Some(default) = &self.default_network {
+            if !self.networks.contains_key(default) {
+                return Err(ConfigError::UnknownDefaultNetwork(default.clone()));
+            }
+        }
+
+        for (name, network) in &self.networks {
+            if network.rpc_urls.is_empty() {
+                return Err(ConfigError::EmptyRpcUrls(name.clone()));
+            }
+            for url in &network.rpc_urls {
+                let parsed = Url::parse(url).map_err(|e| ConfigError::InvalidRpcUrl {
+                    network: name.clone(),
+                    url: url.clone(),
+                    reason: e.to_string(),
+                })?;
+                if !matches!(parsed.scheme(), "http" | "https") {
+                    return Err(ConfigError::InvalidRpcUrl {
+                        network: name.clone(),
+                        url: url.clone(),
+                        reason: "scheme must be http or https".to_string(),
+                    });
+                }
+            }
+            if let Some(rate_limit) = &network.rate_limit {
+                if !rate_limit.is_valid() {
+                    return Err(ConfigError::InvalidRateLimit(name.clone()));
+                }
+            }
+            if !network.retry.is_valid() {
+                return Err(ConfigError::InvalidRetry(name.clone()));
+            }
+            for (key, value) in &network.headers {
+                let valid = reqwest::header::HeaderName::from_str(key).is_ok()
+                    && reqwest::header::HeaderValue::from_str(value).is_ok();
+                if !valid {
+                    return Err(ConfigError::InvalidHeader {
+                        network: name.clone(),
+                        header: key.clone(),
+                    });
+                }
+            }
+        }
+
+        let mut labels = HashSet::new();
+        for wallet in &self.wallets {
+            if !labels.insert(wallet.label.as_str()) {
+                return Err(ConfigError::DuplicateWallet(wallet.label.clone()));
+            }
+            if SuiAddress::from_str(&wallet.address).is_err() {
+                return Err(ConfigError::InvalidWalletAddress {
+                    label: wallet.label.clone(),
+                    address: wallet.address.clone(),
+                });
+            }
+            if let Some(network) = &wallet.network {
+                if !self.networks.contains_key(network) {
+                    return Err(ConfigError::UnknownWalletNetwork {
+                        label: wallet.label.clone(),
+                        network: network.clone(),
+                    });
+                }
+            }
+        }
+
+        for dex in &self.dex {
+            if ObjectID::from_str(&dex.package).is_err() {
+                return Err(ConfigError::InvalidPackageId {
+                    name: dex.name.clone(),
+                    package: dex.package.clone(),
+                });
+            }
+            if let Some(package) = &dex.original_package {
+                if ObjectID::from_str(package).is_err() {
+                    return Err(ConfigError::InvalidPackageId {
+                        name: dex.name.clone(),
+                        package: package.clone(),
+                    });
+                }
+            }
+            if dex.swap_event_pattern().is_err() {
+                return Err(ConfigError::InvalidEventName {
+                    name: dex.name.clone(),
+                    event: dex.swap_event.clone(),
+                });
+            }
+        }
+
+        for package in &self.packages {
+            if ObjectID::from_str(&package.package).is_err() {
+                return Err(ConfigError::InvalidPackageId {
+                    name: package.name.clone(),
+                    package: package.package.clone(),
+                });
+            }
+        }
+
+        for coin in &self.coins {
+            if TypeTag::from_str(&coin.coin_type).is_err() {
+                return Err(ConfigError::InvalidCoin {
+                    coin_type: coin.coin_type.clone(),
+                    reason: "not a type".to_string(),
+                });
+            }
+            // amounts are scaled by 10^decimals in a u64
+            if coin.decimals > 19 {
+                return Err(ConfigError::InvalidCoin {
+                    coin_type: coin.coin_type.clone(),
+                    reason: format!("{} decimals, at most 19 are supported", coin.decimals),
+                });
+            }
+        }
+
+        for (index, sink) in self.sinks.iter().enumerate() {
+            match sink {
+                SinkConfig::Stdout => {}
+                SinkConfig::Jsonl { path } | SinkConfig::Csv { path } => {
+                    if path.as_os_str().is_empty() {
+                        return Err(ConfigError::EmptySinkPath { index });
+                    }
+                }
+            }
+        }
+
+        for (index, pricing) in self.pricing.iter().enumerate() {
+            let PricingConfig::Static { prices } = pricing;
+            if prices.is_empty() {
+                return Err(ConfigError::InvalidPricing {
+                    index,
+                    reason: "no tokens configured".to_string(),
+                });
+            }
+        }
+
+        Ok(())
+    }
+
+    // The default dexes with the configured ones merged in: an entry named
+    // like a default replaces it, the others are added.
+    pub fn dexes(&self) -> Vec<DexConfig> {
+        let mut dexes = default_dexes();
+        for dex in &self.dex {
+            match dexes.iter_mut().find(|d| d.name == dex.name) {
+                Some(default) => {
+                    tracing::info!("dex `{}` replaces the default one", dex.name);
+                    *default = dex.clone();
+                }
+                None => dexes.push(dex.clone()),
+            }
+        }
+        dexes
+    }
+
+    // The default coins with the configured ones merged in, like `dexes`.
+    pub fn coins(&self) -> Vec<CoinConfig> {
+        let mut coins = default_coins();
+        for coin in &self.coins {
+            let coin_type = normalize_coin_type(&coin.coin_type);
+            match coins
+                .iter_mut()
+                .find(|c| normalize_coin_type(&c.coin_type) == coin_type)
+            {
+                Some(default) => {
+                    tracing::info!("coin `{}` replaces the default one", coin.coin_type);
+                    *default = coin.clone();
+                }
+                None => coins.push(coin.clone()),
+            }
+        }
+        coins
+    }
+
+    pub fn network(&self, name: Option<&str>) -> Result<(&String, &NetworkConfig)> {
+        let name = name
+            .or(self.default_network.as_deref())
+            .or_else(|| self.networks.keys().next().map(|k| k.as_str()))
+            .context(h!())?;
+        self.networks
+            .get_key_value(name)
+            .with_context(|| format!("network `{name}` is not configured"))
+    }
+
+    pub fn wallet(&self, label: &str) -> Result<&WalletConfig> {
+        self.wallets
+            .iter()
+            .find(|w| w.label == label)
+            .with_context(|| format!("wallet `{label}` is not configured"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::{Config, SinkConfig, coin_decimals, default_coins};
+    use crate::{Dex, errors::ConfigError, pricing::normalize_coin_type};
+
+    const CONFIG: &str = r#"
+        default_network = "mainnet"
+
+        [networks.mainnet]
+        rpc_urls = ["https://fullnode.mainnet.sui.io:443"]
+
+        [networks.mainnet.headers]
+        x-api-key = "secret"
+
+        [[wallets]]
+        label = "treasury"
+        address = "0x62310ee294108c13f3496ce6895f12f3c2cf3994c74c2911501535e23ccc74ff"
+
+        [[sinks]]
+        kind = "jsonl"
+        path = "txs.jsonl"
+    "#;
+
+    fn validate_err(config: &str) -> ConfigError {
+        Config::from_str(config)
+            .unwrap_err()
+            .downcast::<ConfigError>()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_load_config() {
+        let config = Config::from_str(CONFIG).unwrap();
+
+        assert_eq!(config.network(None).unwrap().0, "mainnet");
+        assert_eq!(config.wallet("treasury").unwrap().network, None);
+        let dexes = config.dexes();
+        assert_eq!(dexes.len(), 3);
+        assert!(matches!(dexes[0].kind, Dex::Cetus));
+        assert_eq!(
+            dexes[1].swap_event_type(),
+            "0x3492c874c1e3b3e2984e8c41b589e642d4d0a5d6459e5a9cfc2d52fd7c89c267::events::AssetSwap"
+        );
+        assert_eq!(
+            config.sinks,
+            vec![SinkConfig::Jsonl {
+                path: "txs.jsonl".into()
+            }]
+        );
+    }
+
+    #[test]
+    fn test_invalid_wallet_address() {
+        let config = CONFIG.replace("0x62310ee294", "0xzz310ee294");
+        assert!(matches!(
+            validate_err(&config),
+            ConfigError::InvalidWalletAddress { label, .. } if label == "treasury"
+        ));
+    }
+
+    #[test]
+    fn test_unknown_default_network() {
+        let config = CONFIG.replace(
+            r#"default_network = "mainnet""#,
+            r#"default_network = "testnet""#,
+        );
+        assert_eq!(
+            validate_err(&config),
+            ConfigError::UnknownDefaultNetwork("testnet".to_string())
+        );
+    }
+
+    #[test]
+    fn test_invalid_rpc_url() {
+        let config = CONFIG.replace("https://fullnode", "ws://fullnode");
+        assert!(matches!(
+            validate_err(&config),
+            ConfigError::InvalidRpcUrl { network, .. } if network == "mainnet"
+        ));
+    }
+
+    #[test]
+    fn test_invalid_dex_event() {
+        let config = format!(
+            r#"{CONFIG}
+            [[dex]]
+            name = "magma"
+            kind = "Magma"
+            package = "0x1"
+            swap_event = "SwapEvent"
+            "#
+        );
+        assert!(matches!(
+            validate_err(&config),
+            ConfigError::InvalidEventName { name, .. } if name == "magma"
+        ));
+    }
+
+    #[test]
+    fn test_generic_dex_event() {
+        let config = format!(
+            r#"{CONFIG}
+            [[dex]]
+            name = "magma"
+            kind = "Magma"
+            package = "0x2a"
+            original_package = "0x1a"
+            swap_event = "pool::SwapEvent<_, _>"
+            "#
+        );
+        let config = Config::from_str(&config).unwrap();
+        let pattern = config.dex[0].swap_event_pattern().unwrap();
+        assert_eq!(pattern.addresses.len(), 2);
+        assert_eq!(pattern.type_params.map(|p| p.len()), Some(2));
+    }
+
+    #[test]
+    fn test_merge_dexes() {
+        let config = format!(
+            r#"{CONFIG}
+            [[dex]]
+            name = "magma"
+            kind = "Magma"
+            package = "0x2a"
+            swap_event = "pool::SwapEvent"
+
+            [[dex]]
+            name = "turbos"
+            kind = "Cetus"
+            package = "0x91bfbc386a41afcfd9b2533058d7e915a1d3829089cc268ff4333d54d6339ca1"
+            swap_event = "pool::SwapEvent<_, _, _>"
+            "#
+        );
+        let dexes = Config::from_str(&config).unwrap().dexes();
+        let names = dexes.iter().map(|d| d.name.as_str()).collect::<Vec<_>>();
+        assert_eq!(names, ["cetus", "bluefin", "magma", "turbos"]);
+        assert_eq!(dexes[2].package, "0x2a");
+    }
+
+    #[test]
+    fn test_merge_coins() {
+        let config = format!(
+            r#"{CONFIG}
+            [[coins]]
+            coin_type = "0x02::sui::SUI"
+            decimals = 8
+
+            [[coins]]
+            coin_type = "0x2a::token::TOKEN"
+            decimals = 18
+            "#
+        );
+        let coins = coin_decimals(&Config::from_str(&config).unwrap().coins());
+        assert_eq!(coins.len(), default_coins().len() + 1);
+        assert_eq!(coins[&normalize_coin_type("0x2::sui::SUI")], 8);
+        assert_eq!(coins[&normalize_coin_type("0x2a::token::TOKEN")], 18);
+
+        let config = format!(
+            r#"{CONFIG}
+            [[coins]]
+            coin_type = "0x2a::token"
+            decimals = 6
+            "#
+        );
+        assert!(matches!(
+            validate_err(&config),
+            ConfigError::InvalidCoin { .. }
+        ));
+    }
+
+    #[test]
+    fn test_empty_pricing() {
+        let config = format!(
+            r#"{CONFIG}
+            [[pricing]]
+            kind = "static"
+            prices = {{}}
+            "#
+        );
+        assert!(matches!(
+            validate_err(&config),
+            ConfigError::InvalidPricing { index: 0, .. }
+        ));
+    }
+}
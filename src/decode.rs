@@ -0,0 +1,543 @@
+use anyhow::{Context, Result, bail};
+use mini_macro::here as h;
+use std::str::FromStr;
+use sui_sdk::rpc_types::{BalanceChange, SuiEvent, SuiTransactionBlockResponse};
+use sui_types::base_types::{ObjectID, SuiAddress};
+
+use crate::{
+    Dex, FlashLoan, Swap, TransactionKind, TxType,
+    bridge::BridgeDecoder,
+    config::{CoinConfig, DexConfig, PackageConfig},
+    deepbook::DeepBookDecoder,
+    errors::DecodeError,
+    event_match::EventPattern,
+    events::{self, DecodeMode},
+    flash::{self, FlashDecoder},
+    kiosk::KioskDecoder,
+    lending::LendingDecoder,
+    liquidation::LiquidationDecoder,
+    liquidity,
+    lst::LstDecoder,
+    objects,
+    orders::OrderDecoder,
+    ptb,
+    staking::StakingDecoder,
+    transfer,
+};
+
+// The (in, out) coins of a lone swap from its balance changes, the sender
+// paying one coin and getting the other. Gas paid in a third coin or a payout
+// to another address leave them unknown.
+fn swap_tokens(balance_changes: &[BalanceChange]) -> Option<(String, String)> {
+    let [first, second] = balance_changes else {
+        return None;
+    };
+    let (paid, got) = if first.amount > second.amount {
+        (second, first)
+    } else {
+        (first, second)
+    };
+    Some((paid.coin_type.to_string(), got.coin_type.to_string()))
+}
+
+// Turns a transaction response into a `TransactionKind` from the point of
+// view of `who`.
+pub struct TxDecoder {
+    who: SuiAddress,
+    dexes: Vec<(DexConfig, EventPattern)>,
+    lp_patterns: Vec<(Dex, EventPattern)>,
+    known_packages: Vec<(ObjectID, String)>,
+    staking: StakingDecoder,
+    lst: LstDecoder,
+    lending: LendingDecoder,
+    liquidation: LiquidationDecoder,
+    deepbook: DeepBookDecoder,
+    flash: FlashDecoder,
+    orders: OrderDecoder,
+    kiosk: KioskDecoder,
+    bridge: BridgeDecoder,
+    mode: DecodeMode,
+}
+
+impl TxDecoder {
+    pub fn new(who: SuiAddress, dexes: Vec<DexConfig>) -> Self {
+        let known_packages = dexes
+            .iter()
+            .filter_map(|d| Some((ObjectID::from_str(&d.package).ok()?, d.name.clone())))
+            .collect();
+        let dexes = dexes
+            .into_iter()
+            .filter_map(|d| match d.swap_event_pattern() {
+                Ok(pattern) => Some((d, pattern)),
+                Err(e) => {
+                    tracing::warn!("skipping dex {}: {e:#}", d.name);
+                    None
+                }
+            })
+            .collect::<Vec<_>>();
+        let flash_swap_packages = dexes
+            .iter()
+            .filter(|(d, _)| d.kind == Dex::Cetus)
+            .flat_map(|(d, _)| std::iter::once(&d.package).chain(&d.original_package))
+            .filter_map(|package| ObjectID::from_str(package).ok())
+            .collect::<Vec<_>>();
+        let lp_patterns = dexes
+            .iter()
+            .flat_map(|(d, _)| {
+                liquidity::lp_events(d.kind)
+                    .iter()
+                    .filter_map(|event| Some((d.kind, d.event_pattern(event).ok()?)))
+            })
+            .collect();
+        Self {
+            who,
+            dexes,
+            lp_patterns,
+            known_packages,
+            staking: StakingDecoder::default(),
+            lst: LstDecoder::default(),
+            lending: LendingDecoder::default(),
+            liquidation: LiquidationDecoder::default(),
+            deepbook: DeepBookDecoder::default(),
+            flash: FlashDecoder::default().with_flash_swap_packages(&flash_swap_packages),
+            orders: OrderDecoder::default(),
+            kiosk: KioskDecoder::default(),
+            bridge: BridgeDecoder::default(),
+            mode: DecodeMode::default(),
+        }
+    }
+
+    pub fn with_mode(mut self, mode: DecodeMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    // The wallet's DeepBook balance managers, for telling its maker fills
+    // apart.
+    pub fn with_balance_managers(mut self, balance_managers: &[ObjectID]) -> Self {
+        self.deepbook = self.deepbook.with_balance_managers(balance_managers);
+        self
+    }
+
+    // Decimals of the coins liquidation penalties and Wormhole amounts are
+    // worked out in, `default_coins` unless set.
+    pub fn with_coins(mut self, coins: &[CoinConfig]) -> Self {
+        self.liquidation = self.liquidation.with_coins(coins);
+        self.bridge = self.bridge.with_coins(coins);
+        self
+    }
+
+    pub fn with_packages(mut self, packages: &[PackageConfig]) -> Self {
+        self.known_packages.extend(
+            packages
+                .iter()
+                .filter_map(|p| Some((ObjectID::from_str(&p.package).ok()?, p.name.clone()))),
+        );
+        self
+    }
+
+    // `event` as a swap, if a configured dex emitted it. Generic swap events
+    // name the pool's coins; for the others they are read off the balance
+    // changes when `from_balances` is set, which only holds for transactions
+    // making a single swap, and left empty otherwise.
+    fn swap(
+        &self,
+        tx_resp: &SuiTransactionBlockResponse,
+        event: &SuiEvent,
+        from_balances: bool,
+    ) -> Result<Option<Swap>> {
+        let Some((dex, coin_types)) = self
+            .dexes
+            .iter()
+            .find_map(|(d, pattern)| Some((d, pattern.matches(&event.type_)?)))
+        else {
+            return Ok(None);
+        };
+        let swap = events::decode_swap(dex.kind, event, self.mode).context(h!())?;
+
+        let (in_token, out_token) = match coin_types.as_slice() {
+            [coin_a, coin_b] if swap.a2b => (coin_a.to_string(), coin_b.to_string()),
+            [coin_a, coin_b] => (coin_b.to_string(), coin_a.to_string()),
+            _ if !from_balances => (String::new(), String::new()),
+            _ => {
+                let balance_changes = tx_resp
+                    .balance_changes
+                    .as_ref()
+                    .ok_or(DecodeError::TransactionResponseWithoutBalanceChanges)?;
+                swap_tokens(balance_changes).unwrap_or_default()
+            }
+        };
+
+        Ok(Some(Swap {
+            pool: swap.pool.to_string(),
+            dex: dex.kind,
+            a2b: swap.a2b,
+            in_amount: swap.amount_in.into(),
+            out_amount: swap.amount_out.into(),
+            in_token,
+            out_token,
+            before_sqrt_price: swap.before_sqrt_price.to_string(),
+            after_sqrt_price: swap.after_sqrt_price.to_string(),
+        }))
+    }
+
+    pub fn decode(&self, tx_resp: SuiTransactionBlockResponse) -> Result<TransactionKind> {
+        let tx_time = tx_resp.timestamp_ms.context(h!())?;
+        tracing::info!("{}", tx_time);
+
+        let mut tx_kind = TransactionKind {
+            tx_type: TxType::Unknown,
+            tx_hash: tx_resp.digest,
+            event_timestamp_ms: tx_time,
+        };
+
+        let events = tx_resp.events.as_ref().context(h!())?;
+        if events.data.is_empty() {
+            // the balance changes of an NFT transfer are only the gas
+            if let Some(tx_type) = objects::decode(&tx_resp) {
+                tx_kind.tx_type = tx_type;
+                return Ok(tx_kind);
+            }
+
+            if let Some(tx_type) = ptb::classify(&tx_resp, &self.known_packages) {
+                tx_kind.tx_type = tx_type;
+                return Ok(tx_kind);
+            }
+
+            let balance_changes = tx_resp
+                .balance_changes
+                .ok_or(DecodeError::TransactionResponseWithoutBalanceChanges)?;
+            let transfer_event =
+                transfer::decode_transfer(balance_changes, Some(self.who)).context(h!())?;
+
+            if transfer_event.sender.eq(&transfer_event.receiver) {
+                tx_kind.tx_type = TxType::SelfTransfer(transfer_event);
+                return Ok(tx_kind);
+            } else {
+                tx_kind.tx_type = TxType::Transfer(transfer_event);
+                return Ok(tx_kind);
+            }
+        }
+
+        tracing::debug!("{}", serde_json::to_string(&tx_resp).unwrap());
+
+        // a swap next to position events is part of a zap in or out, the
+        // position events describe the transaction
+        let lp_events = events
+            .data
+            .iter()
+            .filter_map(|event| {
+                let (dex, _) = self
+                    .lp_patterns
+                    .iter()
+                    .find(|(_, pattern)| pattern.matches(&event.type_).is_some())?;
+                Some(liquidity::decode_lp_event(*dex, event, self.mode))
+            })
+            .collect::<Result<Vec<_>, _>>()
+            .context(h!())?;
+        if let Some(tx_type) = liquidity::fold(lp_events) {
+            tx_kind.tx_type = tx_type;
+            return Ok(tx_kind);
+        }
+
+        // a liquidation repays and withdraws on the obligation, and is often
+        // funded by a flash loan, those events are part of it
+        if let Some(tx_type) = self
+            .liquidation
+            .decode(&events.data, self.mode)
+            .context(h!())?
+        {
+            tx_kind.tx_type = tx_type;
+            return Ok(tx_kind);
+        }
+
+        // keepers fill orders by swapping, the fill is what the transaction
+        // did for the order's owner
+        if let Some(tx_type) = self.orders.decode(&events.data, self.mode).context(h!())? {
+            tx_kind.tx_type = tx_type;
+            return Ok(tx_kind);
+        }
+
+        // an arbitrage borrows, swaps through a few pools and repays, the loan
+        // describes the transaction rather than its first swap
+        let mut loans = self.flash.loans(&events.data, self.mode).context(h!())?;
+        let flash_pools = self.flash.flash_swap_pools(&tx_resp);
+        if !loans.is_empty() || !flash_pools.is_empty() {
+            let swaps = events
+                .data
+                .iter()
+                .filter_map(|event| self.swap(&tx_resp, event, false).transpose())
+                .collect::<Result<Vec<_>>>()?;
+            loans.extend(flash::flash_swap_loans(&swaps, &flash_pools));
+            tx_kind.tx_type = TxType::FlashLoan(FlashLoan {
+                loans,
+                swaps,
+                net: flash::net(&tx_resp, self.who),
+            });
+            return Ok(tx_kind);
+        }
+
+        // minting an LST stakes with a validator too, so this comes first
+        if let Some(tx_type) = self.lst.decode(&events.data, self.mode).context(h!())? {
+            tx_kind.tx_type = tx_type;
+            return Ok(tx_kind);
+        }
+
+        if let Some(tx_type) = self.staking.decode(&tx_resp, self.mode).context(h!())? {
+            tx_kind.tx_type = tx_type;
+            return Ok(tx_kind);
+        }
+
+        if let Some(tx_type) = self.lending.decode(&events.data, self.mode).context(h!())? {
+            tx_kind.tx_type = tx_type;
+            return Ok(tx_kind);
+        }
+
+        if let Some(tx_type) = self
+            .deepbook
+            .decode(&events.data, self.mode)
+            .context(h!())?
+        {
+            tx_kind.tx_type = tx_type;
+            return Ok(tx_kind);
+        }
+
+        // an item paid for with swapped coins is still a purchase
+        if let Some(tx_type) = self.kiosk.decode(&tx_resp, self.mode).context(h!())? {
+            tx_kind.tx_type = tx_type;
+            return Ok(tx_kind);
+        }
+
+        // coins swapped on the way into or out of a bridge are still bridged
+        if let Some(tx_type) = self.bridge.decode(&tx_resp, self.mode).context(h!())? {
+            tx_kind.tx_type = tx_type;
+            return Ok(tx_kind);
+        }
+
+        // a route through several pools emits a swap event per hop, the
+        // balance changes only tell the coins of a single swap
+        let swap_events = events
+            .data
+            .iter()
+            .filter(|event| {
+                self.dexes
+                    .iter()
+                    .any(|(_, pattern)| pattern.matches(&event.type_).is_some())
+            })
+            .collect::<Vec<_>>();
+        let from_balances = swap_events.len() == 1;
+        let mut swaps = swap_events
+            .into_iter()
+            .filter_map(|event| self.swap(&tx_resp, event, from_balances).transpose())
+            .collect::<Result<Vec<_>>>()?;
+        if swaps.len() > 1 {
+            tx_kind.tx_type = TxType::Batch(swaps.into_iter().map(TxType::Swap).collect());
+            return Ok(tx_kind);
+        }
+        if let Some(swap) = swaps.pop() {
+            tx_kind.tx_type = TxType::Swap(swap);
+            return Ok(tx_kind);
+        }
+
+        if let Some(tx_type) = ptb::classify(&tx_resp, &self.known_packages) {
+            tx_kind.tx_type = tx_type;
+            return Ok(tx_kind);
+        }
+
+        // e.g. an NFT mint whose events no decoder knows
+        if let Some(tx_type) = objects::decode(&tx_resp) {
+            tx_kind.tx_type = tx_type;
+            return Ok(tx_kind);
+        }
+
+        tracing::debug!("Unknown tx: {}", serde_json::to_string(&tx_resp).unwrap());
+        bail!("Unknown tx type")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::{Deserialize, Serialize};
+    use serde_json::{Value, json};
+    use std::{path::Path, str::FromStr};
+    use sui_sdk::{
+        SUI_MAINNET_URL,
+        rpc_types::{
+            BalanceChange, SuiTransactionBlockResponse, SuiTransactionBlockResponseOptions,
+        },
+    };
+    use sui_types::{TypeTag, base_types::SuiAddress, object::Owner};
+
+    use super::{TxDecoder, swap_tokens};
+    use crate::{
+        TxType,
+        config::default_dexes,
+        events::DecodeMode,
+        ptb,
+        rpc::{RpcClient, RpcConfig},
+    };
+
+    // Every `<name>.tx.json` is decoded and compared with `<name>.expected.json`.
+    // Run with `BLESS=1` to (re)write the expected files from the current
+    // decoder output.
+    const FIXTURES: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/fixtures/decode");
+    const DEFAULT_WALLET: &str =
+        "0x62310ee294108c13f3496ce6895f12f3c2cf3994c74c2911501535e23ccc74ff";
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Expected {
+        wallet: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        result: Option<Value>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        error: Option<String>,
+    }
+
+    fn decode_fixture(tx_path: &Path, wallet: String, mode: DecodeMode) -> Expected {
+        let tx: SuiTransactionBlockResponse =
+            serde_json::from_str(&std::fs::read_to_string(tx_path).unwrap())
+                .unwrap_or_else(|e| panic!("{}: {e}", tx_path.display()));
+        let decoder =
+            TxDecoder::new(SuiAddress::from_str(&wallet).unwrap(), default_dexes()).with_mode(mode);
+
+        match decoder.decode(tx) {
+            Ok(kind) => Expected {
+                wallet,
+                result: Some(serde_json::to_value(kind).unwrap()),
+                error: None,
+            },
+            Err(e) => Expected {
+                wallet,
+                result: None,
+                error: Some(e.root_cause().to_string()),
+            },
+        }
+    }
+
+    #[test]
+    fn test_swap_tokens() {
+        let change = |coin: &str, amount: i128| BalanceChange {
+            owner: Owner::AddressOwner(SuiAddress::from_str(DEFAULT_WALLET).unwrap()),
+            coin_type: TypeTag::from_str(coin).unwrap(),
+            amount,
+        };
+        let usdc = "0xdba34672e30cb065b1f93e3ab55318768fd6fef66c15942c9f7cb846e2f900e7::usdc::USDC";
+        let deep = "0xdeeb7a4662eec9f2f3def03fb937a663dddaa2e215b8078a284d026b7946c270::deep::DEEP";
+
+        let (paid, got) = swap_tokens(&[
+            change(usdc, 3_500_000),
+            change("0x2::sui::SUI", -1_000_000_000),
+        ])
+        .unwrap();
+        assert_eq!(
+            paid,
+            TypeTag::from_str("0x2::sui::SUI").unwrap().to_string()
+        );
+        assert_eq!(got, TypeTag::from_str(usdc).unwrap().to_string());
+
+        // gas paid in a third coin
+        let changes = [
+            change(usdc, 3_500_000),
+            change(deep, -2_000_000),
+            change("0x2::sui::SUI", -1_000_000),
+        ];
+        assert_eq!(swap_tokens(&changes), None);
+    }
+
+    #[test]
+    fn test_object_transfer_without_object_changes() {
+        let mut tx: SuiTransactionBlockResponse = serde_json::from_str(include_str!(
+            "../fixtures/decode/object_transfer_ptb.tx.json"
+        ))
+        .unwrap();
+        assert!(matches!(
+            ptb::classify(&tx, &[]),
+            Some(TxType::ObjectTransfer(_))
+        ));
+
+        // fetched object changes are decoded by `objects::decode` instead
+        tx.object_changes = Some(vec![]);
+        assert!(ptb::classify(&tx, &[]).is_none());
+    }
+
+    #[test]
+    fn test_decode_fixtures() {
+        let bless = std::env::var_os("BLESS").is_some();
+
+        let mut tx_paths = std::fs::read_dir(FIXTURES)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .filter(|p| p.to_string_lossy().ends_with(".tx.json"))
+            .collect::<Vec<_>>();
+        tx_paths.sort();
+        assert!(!tx_paths.is_empty());
+
+        let mut failures = vec![];
+        for tx_path in tx_paths {
+            let file_name = tx_path.file_name().unwrap().to_string_lossy();
+            let name = file_name.trim_end_matches(".tx.json");
+            let expected_path = tx_path.with_file_name(format!("{name}.expected.json"));
+
+            let expected = std::fs::read_to_string(&expected_path)
+                .ok()
+                .map(|s| serde_json::from_str::<Expected>(&s).unwrap());
+            let wallet = expected
+                .as_ref()
+                .map(|e| e.wallet.clone())
+                .unwrap_or_else(|| DEFAULT_WALLET.to_string());
+            let actual = decode_fixture(&tx_path, wallet.clone(), DecodeMode::Json);
+            let from_bcs = decode_fixture(&tx_path, wallet, DecodeMode::Bcs);
+            if from_bcs != actual {
+                failures.push(format!(
+                    "{name}: bcs decoding differs\n  json: {}\n  bcs:  {}",
+                    serde_json::to_string(&actual).unwrap(),
+                    serde_json::to_string(&from_bcs).unwrap()
+                ));
+            }
+
+            if bless {
+                let content = serde_json::to_string_pretty(&actual).unwrap() + "\n";
+                std::fs::write(&expected_path, content).unwrap();
+            } else if expected.as_ref() != Some(&actual) {
+                failures.push(format!(
+                    "{name}\n  expected: {}\n  actual:   {}",
+                    serde_json::to_string(&expected).unwrap(),
+                    serde_json::to_string(&actual).unwrap()
+                ));
+            }
+        }
+
+        assert!(
+            failures.is_empty(),
+            "{} fixture(s) differ, rerun with BLESS=1 to accept:\n{}",
+            failures.len(),
+            failures.join("\n")
+        );
+    }
+
+    // Records mainnet transactions as fixtures, exactly as the node returns
+    // them: `RECORD=<name>=<digest>,.. cargo test record_fixtures -- --ignored`,
+    // then `BLESS=1` to write their expected files.
+    #[tokio::test]
+    #[ignore = "reads mainnet"]
+    async fn record_fixtures() {
+        let Ok(record) = std::env::var("RECORD") else {
+            return;
+        };
+        let client = RpcClient::new(&[SUI_MAINNET_URL.to_string()], &RpcConfig::default()).unwrap();
+        let options = SuiTransactionBlockResponseOptions::default()
+            .with_input()
+            .with_effects()
+            .with_events()
+            .with_balance_changes()
+            .with_object_changes();
+        for fixture in record.split(',') {
+            let (name, digest) = fixture.split_once('=').expect("<name>=<digest>");
+            let raw = client
+                .call_raw("sui_getTransactionBlock", json!([digest, options]))
+                .await
+                .unwrap();
+            std::fs::write(format!("{FIXTURES}/{name}.tx.json"), raw.get()).unwrap();
+        }
+    }
+}
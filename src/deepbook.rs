@@ -0,0 +1,264 @@
+use anyhow::{Context, Result};
+use mini_macro::here as h;
+use serde_json::Value;
+use std::{collections::HashSet, str::FromStr};
+use sui_sdk::rpc_types::{EventFilter, SuiEvent};
+use sui_types::{base_types::ObjectID, digests::TransactionDigest};
+
+use crate::{
+    BalanceManagerTransfer, Fill, Order, Side, TxType,
+    errors::EventDecodeError,
+    event_match::EventPattern,
+    events::{
+        DecodeMode, DeepBookBalanceEvent, DeepBookOrderCanceled, DeepBookOrderFilled,
+        DeepBookOrderPlaced, parse,
+    },
+    liquidity::coin_type,
+    source::{TransactionSource, newest_first, search_events},
+};
+
+const PROTOCOL: &str = "DeepBook";
+
+pub(crate) const DEEPBOOK: &str =
+    "0x2c8d603bc51326b8c13cef9dd07031a408a48dddb541963357661df5d3204809";
+
+const EVENTS: &[&str] = &[
+    "order_info::OrderPlaced",
+    "order::OrderCanceled",
+    "order_info::OrderFilled",
+    "balance_manager::BalanceEvent",
+];
+
+fn is_transfer(tx_type: &TxType) -> bool {
+    matches!(
+        tx_type,
+        TxType::BalanceManagerDeposit(_) | TxType::BalanceManagerWithdraw(_)
+    )
+}
+
+// DeepBook v3 orders, fills and balance manager transfers.
+pub struct DeepBookDecoder {
+    patterns: Vec<EventPattern>,
+    // the wallet's balance managers, telling its maker fills from its taker
+    // fills
+    balance_managers: HashSet<ObjectID>,
+}
+
+impl Default for DeepBookDecoder {
+    fn default() -> Self {
+        let patterns = EVENTS
+            .iter()
+            .map(|event| {
+                EventPattern::from_str(&format!("{DEEPBOOK}::{event}"))
+                    .expect("valid DeepBook event")
+            })
+            .collect();
+        Self {
+            patterns,
+            balance_managers: HashSet::new(),
+        }
+    }
+}
+
+impl DeepBookDecoder {
+    pub fn with_balance_managers(mut self, balance_managers: &[ObjectID]) -> Self {
+        self.balance_managers.extend(balance_managers);
+        self
+    }
+
+    // A fill is the wallet's maker fill when only the maker side is one of
+    // its balance managers. Otherwise the transaction is the taker's.
+    fn side(&self, e: &DeepBookOrderFilled) -> Side {
+        if self.balance_managers.contains(&e.maker_balance_manager_id)
+            && !self.balance_managers.contains(&e.taker_balance_manager_id)
+        {
+            Side::Maker
+        } else {
+            Side::Taker
+        }
+    }
+
+    fn decode_event(&self, event: &SuiEvent, mode: DecodeMode) -> Result<TxType, EventDecodeError> {
+        let tx_type = match event.type_.name.as_str() {
+            "OrderPlaced" => {
+                let e: DeepBookOrderPlaced = parse(PROTOCOL, event, mode)?;
+                TxType::OrderPlaced(Order {
+                    pool: e.pool_id.to_string(),
+                    balance_manager: e.balance_manager_id.to_string(),
+                    trader: e.trader.to_string(),
+                    order_id: e.order_id.to_string(),
+                    client_order_id: e.client_order_id,
+                    is_bid: e.is_bid,
+                    price: e.price,
+                    quantity: e.placed_quantity,
+                    expire_timestamp_ms: Some(e.expire_timestamp),
+                })
+            }
+            "OrderCanceled" => {
+                let e: DeepBookOrderCanceled = parse(PROTOCOL, event, mode)?;
+                TxType::OrderCancelled(Order {
+                    pool: e.pool_id.to_string(),
+                    balance_manager: e.balance_manager_id.to_string(),
+                    trader: e.trader.to_string(),
+                    order_id: e.order_id.to_string(),
+                    client_order_id: e.client_order_id,
+                    is_bid: e.is_bid,
+                    price: e.price,
+                    quantity: e.base_asset_quantity_canceled,
+                    expire_timestamp_ms: None,
+                })
+            }
+            "OrderFilled" => {
+                let e: DeepBookOrderFilled = parse(PROTOCOL, event, mode)?;
+                let fill = match self.side(&e) {
+                    Side::Maker => Fill {
+                        pool: e.pool_id.to_string(),
+                        balance_manager: e.maker_balance_manager_id.to_string(),
+                        side: Side::Maker,
+                        order_id: e.maker_order_id.to_string(),
+                        client_order_id: e.maker_client_order_id,
+                        is_bid: !e.taker_is_bid,
+                        price: e.price,
+                        base_quantity: e.base_quantity,
+                        quote_quantity: e.quote_quantity,
+                        fee: e.maker_fee,
+                        fee_in_deep: e.maker_fee_is_deep,
+                    },
+                    Side::Taker => Fill {
+                        pool: e.pool_id.to_string(),
+                        balance_manager: e.taker_balance_manager_id.to_string(),
+                        side: Side::Taker,
+                        order_id: e.taker_order_id.to_string(),
+                        client_order_id: e.taker_client_order_id,
+                        is_bid: e.taker_is_bid,
+                        price: e.price,
+                        base_quantity: e.base_quantity,
+                        quote_quantity: e.quote_quantity,
+                        fee: e.taker_fee,
+                        fee_in_deep: e.taker_fee_is_deep,
+                    },
+                };
+                TxType::OrderFilled(fill)
+            }
+            "BalanceEvent" => {
+                let e: DeepBookBalanceEvent = parse(PROTOCOL, event, mode)?;
+                let transfer = BalanceManagerTransfer {
+                    balance_manager: e.balance_manager_id.to_string(),
+                    asset: coin_type(&e.asset.name),
+                    amount: e.amount,
+                };
+                if e.deposit {
+                    TxType::BalanceManagerDeposit(transfer)
+                } else {
+                    TxType::BalanceManagerWithdraw(transfer)
+                }
+            }
+            _ => {
+                return Err(EventDecodeError::Invalid {
+                    protocol: PROTOCOL.to_string(),
+                    event: format!("{}::{}", event.type_.module, event.type_.name),
+                    reason: "not an order or balance event".to_string(),
+                });
+            }
+        };
+        Ok(tx_type)
+    }
+
+    pub fn decode(
+        &self,
+        events: &[SuiEvent],
+        mode: DecodeMode,
+    ) -> Result<Option<TxType>, EventDecodeError> {
+        let mut tx_types = events
+            .iter()
+            .filter(|event| {
+                self.patterns
+                    .iter()
+                    .any(|p| p.matches(&event.type_).is_some())
+            })
+            .map(|event| self.decode_event(event, mode))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        // trading settles through the balance manager too, its transfers only
+        // stand on their own when no order was touched
+        if tx_types.iter().any(|t| !is_transfer(t)) {
+            tx_types.retain(|t| !is_transfer(t));
+        }
+        Ok(match tx_types.len() {
+            0 => None,
+            1 => tx_types.pop(),
+            _ => Some(TxType::Batch(tx_types)),
+        })
+    }
+}
+
+// Transactions filling orders of `balance_managers` as the maker, newest
+// first. The taker sends them, so a wallet's own transaction history doesn't
+// have them.
+pub async fn find_maker_fills(
+    source: &dyn TransactionSource,
+    balance_managers: &[ObjectID],
+    since_ms: Option<u64>,
+) -> Result<Vec<TransactionDigest>> {
+    let filled =
+        EventPattern::from_str(&format!("{DEEPBOOK}::order_info::OrderFilled")).context(h!())?;
+    let mut found = vec![];
+    for balance_manager in balance_managers {
+        let query = EventFilter::MoveEventField {
+            path: "/maker_balance_manager_id".to_string(),
+            value: Value::String(balance_manager.to_string()),
+        };
+        found.extend(
+            search_events(source, &query, since_ms, |event| {
+                if filled.matches(&event.type_).is_none() {
+                    return Ok(false);
+                }
+                let e: DeepBookOrderFilled = parse(PROTOCOL, event, DecodeMode::Json)?;
+                Ok(e.maker_balance_manager_id == *balance_manager)
+            })
+            .await
+            .context(h!())?,
+        );
+    }
+    Ok(newest_first(found))
+}
+
+#[cfg(test)]
+mod tests {
+    use sui_types::base_types::ObjectID;
+
+    use super::DeepBookDecoder;
+    use crate::{Side, events::DeepBookOrderFilled};
+
+    fn fill(maker: u8, taker: u8) -> DeepBookOrderFilled {
+        DeepBookOrderFilled {
+            pool_id: ObjectID::ZERO,
+            maker_order_id: 1,
+            taker_order_id: 2,
+            maker_client_order_id: 0,
+            taker_client_order_id: 0,
+            price: 3_500_000_000,
+            taker_is_bid: true,
+            taker_fee: 10,
+            taker_fee_is_deep: true,
+            maker_fee: 5,
+            maker_fee_is_deep: true,
+            base_quantity: 1_000_000_000,
+            quote_quantity: 3_500_000_000,
+            maker_balance_manager_id: ObjectID::from_single_byte(maker),
+            taker_balance_manager_id: ObjectID::from_single_byte(taker),
+            timestamp: 0,
+        }
+    }
+
+    #[test]
+    fn test_fill_side() {
+        let decoder =
+            DeepBookDecoder::default().with_balance_managers(&[ObjectID::from_single_byte(1)]);
+        assert_eq!(decoder.side(&fill(1, 2)), Side::Maker);
+        assert_eq!(decoder.side(&fill(2, 1)), Side::Taker);
+        // a self-trade is reported from the taker's side
+        assert_eq!(decoder.side(&fill(1, 1)), Side::Taker);
+        assert_eq!(DeepBookDecoder::default().side(&fill(1, 2)), Side::Taker);
+    }
+}
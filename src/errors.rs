@@ -1,3 +1,4 @@
+use std::time::Duration;
 use thiserror::Error;
 
 #[derive(Error, Debug)]
@@ -6,4 +7,93 @@ pub enum DecodeError {
     TooManyAccount,
     #[error("TransactionResponseWithoutEffects")]
     TransactionResponseWithoutEffects,
+    #[error("TransactionResponseWithoutBalanceChanges")]
+    TransactionResponseWithoutBalanceChanges,
+}
+
+#[derive(Error, Debug, PartialEq)]
+pub enum ConfigError {
+    #[error("no networks configured")]
+    NoNetworks,
+    #[error("default_network `{0}` is not declared under [networks]")]
+    UnknownDefaultNetwork(String),
+    #[error("network `{0}` has no rpc_urls")]
+    EmptyRpcUrls(String),
+    #[error("network `{network}`: invalid rpc url `{url}`: {reason}")]
+    InvalidRpcUrl {
+        network: String,
+        url: String,
+        reason: String,
+    },
+    #[error("network `{network}`: invalid header `{header}`")]
+    InvalidHeader { network: String, header: String },
+    #[error("network `{0}`: rate_limit needs a positive requests_per_second and burst")]
+    InvalidRateLimit(String),
+    #[error("network `{0}`: retry multiplier must be at least 1")]
+    InvalidRetry(String),
+    #[error("wallet `{label}`: invalid sui address `{address}`")]
+    InvalidWalletAddress { label: String, address: String },
+    #[error("wallet label `{0}` is declared more than once")]
+    DuplicateWallet(String),
+    #[error("wallet `{label}`: network `{network}` is not declared under [networks]")]
+    UnknownWalletNetwork { label: String, network: String },
+    #[error("dex `{name}`: invalid package id `{package}`")]
+    InvalidPackageId { name: String, package: String },
+    #[error(
+        "dex `{name}`: invalid event `{event}`, expected `module::Struct` or `module::Struct<..>`"
+    )]
+    InvalidEventName { name: String, event: String },
+    #[error("coin `{coin_type}`: {reason}")]
+    InvalidCoin { coin_type: String, reason: String },
+    #[error("sink #{index}: path must not be empty")]
+    EmptySinkPath { index: usize },
+    #[error("pricing source #{index}: {reason}")]
+    InvalidPricing { index: usize, reason: String },
+}
+
+#[derive(Error, Debug, PartialEq)]
+pub enum EventDecodeError {
+    #[error("{protocol} {event}: {reason}")]
+    Invalid {
+        protocol: String,
+        event: String,
+        reason: String,
+    },
+    #[error("{protocol} {event}: field `{field}`: {reason}")]
+    InvalidField {
+        protocol: String,
+        event: String,
+        field: String,
+        reason: String,
+    },
+}
+
+#[derive(Error, Debug)]
+pub enum RpcError {
+    #[error("http status {status}: {body}")]
+    Http { status: u16, body: String },
+    #[error("rate limited, retry after {retry_after:?}")]
+    RateLimited { retry_after: Option<Duration> },
+    #[error("json-rpc error {code}: {message}")]
+    JsonRpc { code: i64, message: String },
+    #[error("json-rpc response without result")]
+    EmptyResponse,
+    #[error("transport error: {0}")]
+    Transport(#[from] reqwest::Error),
+}
+
+impl RpcError {
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            RpcError::Http { status, .. } => *status >= 500,
+            RpcError::RateLimited { .. } | RpcError::Transport(_) => true,
+            RpcError::JsonRpc { .. } | RpcError::EmptyResponse => false,
+        }
+    }
+
+    // A json-rpc error is the node's answer to the request itself, another
+    // endpoint would answer the same.
+    pub fn is_endpoint_failure(&self) -> bool {
+        !matches!(self, RpcError::JsonRpc { .. })
+    }
 }
@@ -0,0 +1,230 @@
+use anyhow::{Context, Result, anyhow, bail};
+use move_core_types::{
+    account_address::AccountAddress,
+    identifier::Identifier,
+    language_storage::{StructTag, TypeTag},
+};
+use std::str::FromStr;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TypeParam {
+    // `_`, matches any type
+    Any,
+    Exact(TypeTag),
+}
+
+// A Move event type to look for, e.g. `0x1eab..::pool::SwapEvent` or
+// `0xabc::pool::SwapEvent<_, 0x2::sui::SUI>`.
+//
+// An upgraded package keeps emitting events under the id of its first
+// version, so a pattern can list several package addresses.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EventPattern {
+    pub addresses: Vec<AccountAddress>,
+    pub module: Identifier,
+    pub name: Identifier,
+    // `None` when the pattern has no `<..>` list and takes any type params
+    pub type_params: Option<Vec<TypeParam>>,
+}
+
+impl EventPattern {
+    // Builds a pattern from a `module::Name<..>` string emitted by any of
+    // `addresses`.
+    pub fn new(addresses: Vec<AccountAddress>, event: &str) -> Result<Self> {
+        let (path, type_params) = split_type_params(event)?;
+        let [module, name] = path.split("::").collect::<Vec<_>>()[..] else {
+            bail!("expected `module::Name`, got `{event}`");
+        };
+        Ok(Self {
+            addresses,
+            module: Identifier::new(module).with_context(|| format!("module in `{event}`"))?,
+            name: Identifier::new(name).with_context(|| format!("name in `{event}`"))?,
+            type_params,
+        })
+    }
+
+    // On a match, returns the concrete type params of `tag`, with the types
+    // the wildcards stood for filled in.
+    pub fn matches(&self, tag: &StructTag) -> Option<Vec<TypeTag>> {
+        if !self.addresses.contains(&tag.address)
+            || tag.module != self.module
+            || tag.name != self.name
+        {
+            return None;
+        }
+        if let Some(params) = &self.type_params {
+            if params.len() != tag.type_params.len() {
+                return None;
+            }
+            let all_match = params.iter().zip(&tag.type_params).all(|(p, t)| match p {
+                TypeParam::Any => true,
+                TypeParam::Exact(expected) => expected == t,
+            });
+            if !all_match {
+                return None;
+            }
+        }
+        Some(tag.type_params.clone())
+    }
+}
+
+impl FromStr for EventPattern {
+    type Err = anyhow::Error;
+
+    // `address::module::Name<..>`
+    fn from_str(s: &str) -> Result<Self> {
+        let (address, event) = s
+            .split_once("::")
+            .ok_or_else(|| anyhow!("expected `address::module::Name`, got `{s}`"))?;
+        let address = AccountAddress::from_hex_literal(address)
+            .with_context(|| format!("address in `{s}`"))?;
+        Self::new(vec![address], event)
+    }
+}
+
+// Splits `pool::SwapEvent<A, B>` into `pool::SwapEvent` and its params.
+fn split_type_params(s: &str) -> Result<(&str, Option<Vec<TypeParam>>)> {
+    let s = s.trim();
+    let Some(open) = s.find('<') else {
+        return Ok((s, None));
+    };
+    let inner = s[open + 1..]
+        .strip_suffix('>')
+        .ok_or_else(|| anyhow!("unbalanced `<` in `{s}`"))?;
+
+    let mut params = vec![];
+    let mut depth = 0;
+    let mut start = 0;
+    for (i, c) in inner.char_indices() {
+        match c {
+            '<' => depth += 1,
+            '>' => depth -= 1,
+            ',' if depth == 0 => {
+                params.push(parse_type_param(&inner[start..i])?);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    if depth != 0 {
+        bail!("unbalanced `<` in `{s}`");
+    }
+    if !inner.trim().is_empty() {
+        params.push(parse_type_param(&inner[start..])?);
+    }
+    Ok((&s[..open], Some(params)))
+}
+
+fn parse_type_param(s: &str) -> Result<TypeParam> {
+    match s.trim() {
+        "_" => Ok(TypeParam::Any),
+        t => TypeTag::from_str(t)
+            .map(TypeParam::Exact)
+            .with_context(|| format!("invalid type param `{t}`")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use move_core_types::{
+        account_address::AccountAddress,
+        language_storage::{StructTag, TypeTag},
+    };
+    use std::str::FromStr;
+
+    use super::{EventPattern, TypeParam};
+
+    const USDC: &str =
+        "0xdba34672e30cb065b1f93e3ab55318768fd6fef66c15942c9f7cb846e2f900e7::usdc::USDC";
+
+    fn tag(s: &str) -> StructTag {
+        StructTag::from_str(s).unwrap()
+    }
+
+    #[test]
+    fn test_match_without_type_params() {
+        let pattern = EventPattern::from_str("0xabc::pool::SwapEvent").unwrap();
+        assert_eq!(pattern.type_params, None);
+
+        assert_eq!(
+            pattern.matches(&tag("0xabc::pool::SwapEvent")),
+            Some(vec![])
+        );
+        // leading zeros and the long form of the address do not matter
+        assert_eq!(
+            pattern.matches(&tag(
+                "0x0000000000000000000000000000000000000000000000000000000000000abc::pool::SwapEvent"
+            )),
+            Some(vec![])
+        );
+        assert_eq!(pattern.matches(&tag("0xabd::pool::SwapEvent")), None);
+        assert_eq!(
+            pattern.matches(&tag("0xabc::pool::AddLiquidityEvent")),
+            None
+        );
+    }
+
+    #[test]
+    fn test_wildcard_type_params() {
+        let pattern = EventPattern::from_str("0xabc::pool::SwapEvent<_, 0x2::sui::SUI>").unwrap();
+        assert_eq!(pattern.type_params.as_ref().unwrap()[0], TypeParam::Any);
+
+        let event = tag(&format!("0xabc::pool::SwapEvent<{USDC}, 0x2::sui::SUI>"));
+        assert_eq!(
+            pattern.matches(&event),
+            Some(vec![
+                TypeTag::from_str(USDC).unwrap(),
+                TypeTag::from_str("0x2::sui::SUI").unwrap()
+            ])
+        );
+
+        assert_eq!(
+            pattern.matches(&tag(&format!(
+                "0xabc::pool::SwapEvent<0x2::sui::SUI, {USDC}>"
+            ))),
+            None
+        );
+        assert_eq!(
+            pattern.matches(&tag("0xabc::pool::SwapEvent<0x2::sui::SUI>")),
+            None
+        );
+    }
+
+    #[test]
+    fn test_nested_type_params() {
+        let pattern =
+            EventPattern::from_str("0xabc::vault::Deposit<0x2::coin::Coin<0x2::sui::SUI>, _>")
+                .unwrap();
+        let event = tag(&format!(
+            "0xabc::vault::Deposit<0x2::coin::Coin<0x2::sui::SUI>, {USDC}>"
+        ));
+        assert_eq!(pattern.matches(&event).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_upgraded_package() {
+        let pattern = EventPattern::new(
+            vec![
+                AccountAddress::from_hex_literal("0xabc").unwrap(),
+                AccountAddress::from_hex_literal("0xdef").unwrap(),
+            ],
+            "pool::SwapEvent",
+        )
+        .unwrap();
+        assert!(pattern.matches(&tag("0xabc::pool::SwapEvent")).is_some());
+        assert!(pattern.matches(&tag("0xdef::pool::SwapEvent")).is_some());
+    }
+
+    #[test]
+    fn test_invalid_patterns() {
+        for pattern in [
+            "0xabc::SwapEvent",
+            "0xabc::pool::SwapEvent<_",
+            "0xabc::pool::SwapEvent<_, 0x2::sui>",
+            "0xabc::pool::Swap-Event",
+            "abc::pool::SwapEvent",
+        ] {
+            assert!(EventPattern::from_str(pattern).is_err(), "{pattern}");
+        }
+    }
+}
@@ -0,0 +1,1118 @@
+use move_core_types::u256::U256;
+use serde::{
+    Deserialize, Deserializer, Serialize,
+    de::{self, DeserializeOwned, Visitor},
+};
+use std::{fmt, marker::PhantomData, str::FromStr};
+use sui_sdk::rpc_types::SuiEvent;
+use sui_types::base_types::{ObjectID, SuiAddress};
+
+use crate::{Dex, errors::EventDecodeError};
+
+// Where event fields are read from. `Bcs` skips the JSON rendering of the
+// event and decodes its raw payload with the struct layouts below.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DecodeMode {
+    #[default]
+    Json,
+    Bcs,
+}
+
+// Move `u64`/`u128`/`u256` values come as JSON strings in `parsed_json`,
+// smaller integers as plain numbers. BCS has them as little endian integers.
+fn number<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+where
+    D: Deserializer<'de>,
+    T: FromStr + Deserialize<'de>,
+    T::Err: fmt::Display,
+{
+    if !deserializer.is_human_readable() {
+        return T::deserialize(deserializer);
+    }
+
+    struct NumberVisitor<T>(PhantomData<T>);
+
+    impl<T> Visitor<'_> for NumberVisitor<T>
+    where
+        T: FromStr,
+        T::Err: fmt::Display,
+    {
+        type Value = T;
+
+        fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            f.write_str("a number or a numeric string")
+        }
+
+        fn visit_str<E: de::Error>(self, v: &str) -> Result<T, E> {
+            v.parse().map_err(|e| E::custom(format!("`{v}`: {e}")))
+        }
+
+        fn visit_u64<E: de::Error>(self, v: u64) -> Result<T, E> {
+            self.visit_str(&v.to_string())
+        }
+    }
+
+    deserializer.deserialize_any(NumberVisitor(PhantomData))
+}
+
+// Move `I32` of the CLMM math libraries, two's complement in a `u32`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub struct I32 {
+    #[serde(deserialize_with = "number")]
+    pub bits: u32,
+}
+
+impl I32 {
+    pub fn value(&self) -> i32 {
+        self.bits as i32
+    }
+}
+
+// The event structs declare their fields in the order of the Move structs,
+// which is the order of their BCS encoding.
+
+// `pool::SwapEvent` of Cetus, and of Magma which forked it.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct CetusSwapEvent {
+    pub atob: bool,
+    pub pool: ObjectID,
+    pub partner: ObjectID,
+    #[serde(deserialize_with = "number")]
+    pub amount_in: u64,
+    #[serde(deserialize_with = "number")]
+    pub amount_out: u64,
+    #[serde(deserialize_with = "number")]
+    pub ref_amount: u64,
+    #[serde(deserialize_with = "number")]
+    pub fee_amount: u64,
+    #[serde(deserialize_with = "number")]
+    pub vault_a_amount: u64,
+    #[serde(deserialize_with = "number")]
+    pub vault_b_amount: u64,
+    #[serde(deserialize_with = "number")]
+    pub before_sqrt_price: u128,
+    #[serde(deserialize_with = "number")]
+    pub after_sqrt_price: u128,
+    #[serde(deserialize_with = "number")]
+    pub steps: u64,
+}
+
+// `events::AssetSwap` of Bluefin.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct BluefinSwapEvent {
+    pub pool_id: ObjectID,
+    pub a2b: bool,
+    #[serde(deserialize_with = "number")]
+    pub amount_in: u64,
+    #[serde(deserialize_with = "number")]
+    pub amount_out: u64,
+    #[serde(deserialize_with = "number")]
+    pub pool_coin_a_amount: u64,
+    #[serde(deserialize_with = "number")]
+    pub pool_coin_b_amount: u64,
+    #[serde(deserialize_with = "number")]
+    pub fee: u64,
+    #[serde(deserialize_with = "number")]
+    pub before_liquidity: u128,
+    #[serde(deserialize_with = "number")]
+    pub after_liquidity: u128,
+    #[serde(deserialize_with = "number")]
+    pub before_sqrt_price: u128,
+    #[serde(deserialize_with = "number")]
+    pub after_sqrt_price: u128,
+    pub current_tick: I32,
+    pub exceeded: bool,
+    #[serde(deserialize_with = "number")]
+    pub sequence_number: u128,
+}
+
+// Move `std::type_name::TypeName`, the type without its `0x` prefix.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct TypeName {
+    pub name: String,
+}
+
+// `pool::OpenPositionEvent` of Cetus and Magma.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct CetusOpenPositionEvent {
+    pub pool: ObjectID,
+    pub tick_lower: I32,
+    pub tick_upper: I32,
+    pub position: ObjectID,
+}
+
+// `pool::ClosePositionEvent` of Cetus and Magma.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct CetusClosePositionEvent {
+    pub pool: ObjectID,
+    pub position: ObjectID,
+}
+
+// `pool::AddLiquidityEvent` and `pool::RemoveLiquidityEvent` of Cetus and
+// Magma, which share a layout.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct CetusLiquidityEvent {
+    pub pool: ObjectID,
+    pub position: ObjectID,
+    pub tick_lower: I32,
+    pub tick_upper: I32,
+    #[serde(deserialize_with = "number")]
+    pub liquidity: u128,
+    #[serde(deserialize_with = "number")]
+    pub after_liquidity: u128,
+    #[serde(deserialize_with = "number")]
+    pub amount_a: u64,
+    #[serde(deserialize_with = "number")]
+    pub amount_b: u64,
+}
+
+// `pool::CollectFeeEvent` of Cetus and Magma.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct CetusCollectFeeEvent {
+    pub position: ObjectID,
+    pub pool: ObjectID,
+    #[serde(deserialize_with = "number")]
+    pub amount_a: u64,
+    #[serde(deserialize_with = "number")]
+    pub amount_b: u64,
+}
+
+// `pool::CollectRewardEvent` of Cetus and Magma.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct CetusCollectRewardEvent {
+    pub position: ObjectID,
+    pub pool: ObjectID,
+    #[serde(deserialize_with = "number")]
+    pub amount: u64,
+}
+
+// `pool::CollectRewardV2Event` of Cetus and Magma.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct CetusCollectRewardV2Event {
+    pub position: ObjectID,
+    pub pool: ObjectID,
+    pub reward_type: TypeName,
+    #[serde(deserialize_with = "number")]
+    pub amount: u64,
+}
+
+// `events::PositionOpened` and `events::PositionClosed` of Bluefin.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct BluefinPositionEvent {
+    pub pool_id: ObjectID,
+    pub position_id: ObjectID,
+    pub tick_lower: I32,
+    pub tick_upper: I32,
+}
+
+// `events::LiquidityProvided` and `events::LiquidityRemoved` of Bluefin.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct BluefinLiquidityEvent {
+    pub pool_id: ObjectID,
+    pub position_id: ObjectID,
+    #[serde(deserialize_with = "number")]
+    pub coin_a_amount: u64,
+    #[serde(deserialize_with = "number")]
+    pub coin_b_amount: u64,
+    #[serde(deserialize_with = "number")]
+    pub pool_coin_a_amount: u64,
+    #[serde(deserialize_with = "number")]
+    pub pool_coin_b_amount: u64,
+    #[serde(deserialize_with = "number")]
+    pub liquidity: u128,
+    #[serde(deserialize_with = "number")]
+    pub before_liquidity: u128,
+    #[serde(deserialize_with = "number")]
+    pub after_liquidity: u128,
+    #[serde(deserialize_with = "number")]
+    pub current_sqrt_price: u128,
+    pub current_tick_index: I32,
+    pub lower_tick: I32,
+    pub upper_tick: I32,
+    #[serde(deserialize_with = "number")]
+    pub sequence_number: u128,
+}
+
+// `events::UserFeeCollected` of Bluefin.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct BluefinFeeCollectedEvent {
+    pub pool_id: ObjectID,
+    pub position_id: ObjectID,
+    #[serde(deserialize_with = "number")]
+    pub coin_a_amount: u64,
+    #[serde(deserialize_with = "number")]
+    pub coin_b_amount: u64,
+    #[serde(deserialize_with = "number")]
+    pub pool_coin_a_amount: u64,
+    #[serde(deserialize_with = "number")]
+    pub pool_coin_b_amount: u64,
+    #[serde(deserialize_with = "number")]
+    pub sequence_number: u128,
+}
+
+// `events::UserRewardCollected` of Bluefin.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct BluefinRewardCollectedEvent {
+    pub pool_id: ObjectID,
+    pub position_id: ObjectID,
+    pub reward_type: String,
+    pub reward_symbol: String,
+    pub reward_decimals: u8,
+    #[serde(deserialize_with = "number")]
+    pub reward_amount: u64,
+    #[serde(deserialize_with = "number")]
+    pub sequence_number: u128,
+}
+
+// `0x3::validator::StakingRequestEvent`.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct StakingRequestEvent {
+    pub pool_id: ObjectID,
+    pub validator_address: SuiAddress,
+    pub staker_address: SuiAddress,
+    #[serde(deserialize_with = "number")]
+    pub epoch: u64,
+    #[serde(deserialize_with = "number")]
+    pub amount: u64,
+}
+
+// `0x3::validator::UnstakingRequestEvent`.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct UnstakingRequestEvent {
+    pub pool_id: ObjectID,
+    pub validator_address: SuiAddress,
+    pub staker_address: SuiAddress,
+    #[serde(deserialize_with = "number")]
+    pub stake_activation_epoch: u64,
+    #[serde(deserialize_with = "number")]
+    pub unstaking_epoch: u64,
+    #[serde(deserialize_with = "number")]
+    pub principal_amount: u64,
+    #[serde(deserialize_with = "number")]
+    pub reward_amount: u64,
+}
+
+// `events::StakedEvent` of Aftermath's afSUI vault.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct AftermathStakedEvent {
+    pub staker: SuiAddress,
+    pub validator: SuiAddress,
+    pub staked_sui_id: ObjectID,
+    pub sui_id: ObjectID,
+    #[serde(deserialize_with = "number")]
+    pub sui_amount: u64,
+    pub afsui_id: ObjectID,
+    #[serde(deserialize_with = "number")]
+    pub afsui_amount: u64,
+    #[serde(deserialize_with = "number")]
+    pub validator_fee: u64,
+    pub referrer: Option<SuiAddress>,
+    #[serde(deserialize_with = "number")]
+    pub epoch: u64,
+    pub is_restaked: bool,
+}
+
+// `events::UnstakeRequestedEvent` of Aftermath, paid out at the end of the
+// epoch.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct AftermathUnstakeRequestedEvent {
+    pub afsui_id: ObjectID,
+    #[serde(deserialize_with = "number")]
+    pub provided_afsui_amount: u64,
+    pub requester: SuiAddress,
+    #[serde(deserialize_with = "number")]
+    pub epoch: u64,
+}
+
+// `events::UnstakedEvent` of Aftermath, an atomic unstake.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct AftermathUnstakedEvent {
+    pub afsui_id: ObjectID,
+    #[serde(deserialize_with = "number")]
+    pub provided_afsui_amount: u64,
+    pub sui_id: ObjectID,
+    #[serde(deserialize_with = "number")]
+    pub returned_sui_amount: u64,
+    pub requester: SuiAddress,
+    #[serde(deserialize_with = "number")]
+    pub epoch: u64,
+}
+
+// `staking::UserStaked` of Haedal.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct HaedalStakedEvent {
+    pub owner: SuiAddress,
+    #[serde(deserialize_with = "number")]
+    pub sui_amount: u64,
+    #[serde(deserialize_with = "number")]
+    pub st_amount: u64,
+    pub validator: SuiAddress,
+}
+
+// `staking::UserNormalUnstaked` of Haedal, claimed with `ticket_id` once
+// `unstake_timestamp_ms` has passed.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct HaedalNormalUnstakedEvent {
+    pub owner: SuiAddress,
+    #[serde(deserialize_with = "number")]
+    pub epoch: u64,
+    #[serde(deserialize_with = "number")]
+    pub epoch_timestamp_ms: u64,
+    #[serde(deserialize_with = "number")]
+    pub unstake_timestamp_ms: u64,
+    #[serde(deserialize_with = "number")]
+    pub st_amount: u64,
+    pub ticket_id: ObjectID,
+}
+
+// `staking::UserInstantUnstaked` of Haedal.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct HaedalInstantUnstakedEvent {
+    pub owner: SuiAddress,
+    #[serde(deserialize_with = "number")]
+    pub sui_amount: u64,
+    #[serde(deserialize_with = "number")]
+    pub st_amount: u64,
+}
+
+// `native_pool::StakedEvent` and `native_pool::UnstakedEvent` of Volo, the
+// field order differs.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct VoloStakedEvent {
+    pub staker: SuiAddress,
+    #[serde(deserialize_with = "number")]
+    pub sui_amount: u64,
+    #[serde(deserialize_with = "number")]
+    pub cert_amount: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct VoloUnstakedEvent {
+    pub staker: SuiAddress,
+    #[serde(deserialize_with = "number")]
+    pub cert_amount: u64,
+    #[serde(deserialize_with = "number")]
+    pub sui_amount: u64,
+}
+
+// `liquid_staking::MintEvent` of SpringSui, `typename` is the minted LST.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct SpringSuiMintEvent {
+    pub typename: TypeName,
+    #[serde(deserialize_with = "number")]
+    pub sui_amount_in: u64,
+    #[serde(deserialize_with = "number")]
+    pub lst_amount_out: u64,
+    #[serde(deserialize_with = "number")]
+    pub fee_amount: u64,
+}
+
+// `liquid_staking::RedeemEvent` of SpringSui.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct SpringSuiRedeemEvent {
+    pub typename: TypeName,
+    #[serde(deserialize_with = "number")]
+    pub lst_amount_in: u64,
+    #[serde(deserialize_with = "number")]
+    pub sui_amount_out: u64,
+    #[serde(deserialize_with = "number")]
+    pub fee_amount: u64,
+}
+
+// `deposit_collateral::CollateralDepositEvent` of Scallop.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct ScallopCollateralDepositEvent {
+    pub provider: SuiAddress,
+    pub obligation: ObjectID,
+    pub deposit_asset: TypeName,
+    #[serde(deserialize_with = "number")]
+    pub deposit_amount: u64,
+}
+
+// `withdraw_collateral::CollateralWithdrawEvent` of Scallop.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct ScallopCollateralWithdrawEvent {
+    pub taker: SuiAddress,
+    pub obligation: ObjectID,
+    pub withdraw_asset: TypeName,
+    #[serde(deserialize_with = "number")]
+    pub withdraw_amount: u64,
+}
+
+// `borrow::BorrowEvent` of Scallop.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct ScallopBorrowEvent {
+    pub borrower: SuiAddress,
+    pub obligation: ObjectID,
+    pub asset: TypeName,
+    #[serde(deserialize_with = "number")]
+    pub amount: u64,
+    #[serde(deserialize_with = "number")]
+    pub time: u64,
+}
+
+// `borrow::BorrowEventV2` of Scallop, with the borrow fee.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct ScallopBorrowEventV2 {
+    pub borrower: SuiAddress,
+    pub obligation: ObjectID,
+    pub asset: TypeName,
+    #[serde(deserialize_with = "number")]
+    pub amount: u64,
+    #[serde(deserialize_with = "number")]
+    pub borrow_fee: u64,
+    #[serde(deserialize_with = "number")]
+    pub time: u64,
+}
+
+// `repay::RepayEvent` of Scallop.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct ScallopRepayEvent {
+    pub repayer: SuiAddress,
+    pub obligation: ObjectID,
+    pub asset: TypeName,
+    #[serde(deserialize_with = "number")]
+    pub amount: u64,
+    #[serde(deserialize_with = "number")]
+    pub time: u64,
+}
+
+// `mint::MintEvent` of Scallop, a supply to a lending pool for its sCoin.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct ScallopMintEvent {
+    pub minter: SuiAddress,
+    pub deposit_asset: TypeName,
+    #[serde(deserialize_with = "number")]
+    pub deposit_amount: u64,
+    pub mint_asset: TypeName,
+    #[serde(deserialize_with = "number")]
+    pub mint_amount: u64,
+    #[serde(deserialize_with = "number")]
+    pub time: u64,
+}
+
+// `redeem::RedeemEvent` of Scallop.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct ScallopRedeemEvent {
+    pub redeemer: SuiAddress,
+    pub withdraw_asset: TypeName,
+    #[serde(deserialize_with = "number")]
+    pub withdraw_amount: u64,
+    pub burn_asset: TypeName,
+    #[serde(deserialize_with = "number")]
+    pub burn_amount: u64,
+    #[serde(deserialize_with = "number")]
+    pub time: u64,
+}
+
+// `lending::DepositEvent`, `BorrowEvent` and `RepayEvent` of Navi. `reserve`
+// is the index of the asset in Navi's storage, and `sender` the account acted
+// on: the wallet, or the id of the `AccountCap` it acted through.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct NaviLendingEvent {
+    pub reserve: u8,
+    pub sender: SuiAddress,
+    #[serde(deserialize_with = "number")]
+    pub amount: u64,
+}
+
+// `lending::WithdrawEvent` of Navi.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct NaviWithdrawEvent {
+    pub reserve: u8,
+    pub sender: SuiAddress,
+    pub to: SuiAddress,
+    #[serde(deserialize_with = "number")]
+    pub amount: u64,
+}
+
+// `lending_market::MintEvent` and `RedeemEvent` of Suilend, underlying
+// liquidity swapped for cTokens and back.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct SuilendMintEvent {
+    pub lending_market_id: SuiAddress,
+    pub coin_type: TypeName,
+    pub reserve_id: SuiAddress,
+    #[serde(deserialize_with = "number")]
+    pub liquidity_amount: u64,
+    #[serde(deserialize_with = "number")]
+    pub ctoken_amount: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct SuilendRedeemEvent {
+    pub lending_market_id: SuiAddress,
+    pub coin_type: TypeName,
+    pub reserve_id: SuiAddress,
+    #[serde(deserialize_with = "number")]
+    pub ctoken_amount: u64,
+    #[serde(deserialize_with = "number")]
+    pub liquidity_amount: u64,
+}
+
+// `lending_market::DepositEvent` and `WithdrawEvent` of Suilend, cTokens
+// moved into or out of an obligation.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct SuilendCollateralEvent {
+    pub lending_market_id: SuiAddress,
+    pub coin_type: TypeName,
+    pub reserve_id: SuiAddress,
+    pub obligation_id: SuiAddress,
+    #[serde(deserialize_with = "number")]
+    pub ctoken_amount: u64,
+}
+
+// `lending_market::BorrowEvent` of Suilend.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct SuilendBorrowEvent {
+    pub lending_market_id: SuiAddress,
+    pub coin_type: TypeName,
+    pub reserve_id: SuiAddress,
+    pub obligation_id: SuiAddress,
+    #[serde(deserialize_with = "number")]
+    pub liquidity_amount: u64,
+    #[serde(deserialize_with = "number")]
+    pub origination_fee_amount: u64,
+}
+
+// `lending_market::RepayEvent` of Suilend.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct SuilendRepayEvent {
+    pub lending_market_id: SuiAddress,
+    pub coin_type: TypeName,
+    pub reserve_id: SuiAddress,
+    pub obligation_id: SuiAddress,
+    #[serde(deserialize_with = "number")]
+    pub liquidity_amount: u64,
+}
+
+// `lending_market::ClaimRewardEvent` of Suilend.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct SuilendClaimRewardEvent {
+    pub lending_market_id: SuiAddress,
+    pub reserve_id: SuiAddress,
+    pub obligation_id: SuiAddress,
+    pub is_deposit_reward: bool,
+    pub pool_reward_id: SuiAddress,
+    pub coin_type: TypeName,
+    #[serde(deserialize_with = "number")]
+    pub liquidity_amount: u64,
+}
+
+// Move `std::fixed_point32::FixedPoint32`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub struct FixedPoint32 {
+    #[serde(deserialize_with = "number")]
+    pub value: u64,
+}
+
+// `liquidate::LiquidateEvent` of Scallop.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct ScallopLiquidateEvent {
+    pub liquidator: SuiAddress,
+    pub obligation: ObjectID,
+    pub debt_type: TypeName,
+    pub collateral_type: TypeName,
+    #[serde(deserialize_with = "number")]
+    pub repay_on_behalf: u64,
+    #[serde(deserialize_with = "number")]
+    pub repay_revenue: u64,
+    #[serde(deserialize_with = "number")]
+    pub liq_amount: u64,
+}
+
+// `liquidate::LiquidateEventV2` of Scallop, with the oracle prices used.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct ScallopLiquidateEventV2 {
+    pub liquidator: SuiAddress,
+    pub obligation: ObjectID,
+    pub debt_type: TypeName,
+    pub collateral_type: TypeName,
+    #[serde(deserialize_with = "number")]
+    pub repay_on_behalf: u64,
+    #[serde(deserialize_with = "number")]
+    pub repay_revenue: u64,
+    #[serde(deserialize_with = "number")]
+    pub liq_amount: u64,
+    pub collateral_price: FixedPoint32,
+    pub debt_price: FixedPoint32,
+    #[serde(deserialize_with = "number")]
+    pub timestamp: u64,
+}
+
+// `lending::LiquidationEvent` of Navi. `treasury` is the protocol's share of
+// the seized collateral.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct NaviLiquidationEvent {
+    pub sender: SuiAddress,
+    pub user: SuiAddress,
+    pub collateral_asset: u8,
+    #[serde(deserialize_with = "number")]
+    pub collateral_price: U256,
+    #[serde(deserialize_with = "number")]
+    pub collateral_amount: u64,
+    #[serde(deserialize_with = "number")]
+    pub treasury: u64,
+    pub debt_asset: u8,
+    #[serde(deserialize_with = "number")]
+    pub debt_price: U256,
+    #[serde(deserialize_with = "number")]
+    pub debt_amount: u64,
+}
+
+// `lending_market::LiquidateEvent` of Suilend. The withdrawn collateral,
+// fee and bonus are in cTokens.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct SuilendLiquidateEvent {
+    pub lending_market_id: SuiAddress,
+    pub repay_reserve_id: SuiAddress,
+    pub withdraw_reserve_id: SuiAddress,
+    pub obligation_id: SuiAddress,
+    pub repay_coin_type: TypeName,
+    #[serde(deserialize_with = "number")]
+    pub repay_amount: u64,
+    pub withdraw_coin_type: TypeName,
+    #[serde(deserialize_with = "number")]
+    pub withdraw_amount: u64,
+    #[serde(deserialize_with = "number")]
+    pub protocol_fee_amount: u64,
+    #[serde(deserialize_with = "number")]
+    pub liquidator_bonus_amount: u64,
+}
+
+// `order_info::OrderPlaced` of DeepBook v3.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct DeepBookOrderPlaced {
+    pub balance_manager_id: ObjectID,
+    pub pool_id: ObjectID,
+    #[serde(deserialize_with = "number")]
+    pub order_id: u128,
+    #[serde(deserialize_with = "number")]
+    pub client_order_id: u64,
+    pub trader: SuiAddress,
+    #[serde(deserialize_with = "number")]
+    pub price: u64,
+    pub is_bid: bool,
+    #[serde(deserialize_with = "number")]
+    pub placed_quantity: u64,
+    #[serde(deserialize_with = "number")]
+    pub expire_timestamp: u64,
+    #[serde(deserialize_with = "number")]
+    pub timestamp: u64,
+}
+
+// `order::OrderCanceled` of DeepBook v3.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct DeepBookOrderCanceled {
+    pub balance_manager_id: ObjectID,
+    pub pool_id: ObjectID,
+    #[serde(deserialize_with = "number")]
+    pub order_id: u128,
+    #[serde(deserialize_with = "number")]
+    pub client_order_id: u64,
+    pub trader: SuiAddress,
+    #[serde(deserialize_with = "number")]
+    pub price: u64,
+    pub is_bid: bool,
+    #[serde(deserialize_with = "number")]
+    pub original_quantity: u64,
+    #[serde(deserialize_with = "number")]
+    pub base_asset_quantity_canceled: u64,
+    #[serde(deserialize_with = "number")]
+    pub timestamp: u64,
+}
+
+// `order_info::OrderFilled` of DeepBook v3, one per maker order a taker
+// order matched.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct DeepBookOrderFilled {
+    pub pool_id: ObjectID,
+    #[serde(deserialize_with = "number")]
+    pub maker_order_id: u128,
+    #[serde(deserialize_with = "number")]
+    pub taker_order_id: u128,
+    #[serde(deserialize_with = "number")]
+    pub maker_client_order_id: u64,
+    #[serde(deserialize_with = "number")]
+    pub taker_client_order_id: u64,
+    #[serde(deserialize_with = "number")]
+    pub price: u64,
+    pub taker_is_bid: bool,
+    #[serde(deserialize_with = "number")]
+    pub taker_fee: u64,
+    pub taker_fee_is_deep: bool,
+    #[serde(deserialize_with = "number")]
+    pub maker_fee: u64,
+    pub maker_fee_is_deep: bool,
+    #[serde(deserialize_with = "number")]
+    pub base_quantity: u64,
+    #[serde(deserialize_with = "number")]
+    pub quote_quantity: u64,
+    pub maker_balance_manager_id: ObjectID,
+    pub taker_balance_manager_id: ObjectID,
+    #[serde(deserialize_with = "number")]
+    pub timestamp: u64,
+}
+
+// `balance_manager::BalanceEvent` of DeepBook v3.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct DeepBookBalanceEvent {
+    pub balance_manager_id: ObjectID,
+    pub asset: TypeName,
+    #[serde(deserialize_with = "number")]
+    pub amount: u64,
+    pub deposit: bool,
+}
+
+// `vault::FlashLoanBorrowed` of DeepBook v3. Repaying emits nothing, the
+// loan is free and has to come back in full within the transaction.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct DeepBookFlashLoanBorrowed {
+    pub pool_id: ObjectID,
+    #[serde(deserialize_with = "number")]
+    pub borrow_quantity: u64,
+    pub type_name: TypeName,
+}
+
+// `flash_loan::FlashLoan` of Navi.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct NaviFlashLoan {
+    pub sender: SuiAddress,
+    pub asset: TypeName,
+    #[serde(deserialize_with = "number")]
+    pub amount: u64,
+}
+
+// `flash_loan::FlashRepay` of Navi. `amount` is the principal, the fees are
+// paid on top of it.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct NaviFlashRepay {
+    pub sender: SuiAddress,
+    pub asset: TypeName,
+    #[serde(deserialize_with = "number")]
+    pub amount: u64,
+    #[serde(deserialize_with = "number")]
+    pub fee_to_supplier: u64,
+    #[serde(deserialize_with = "number")]
+    pub fee_to_treasury: u64,
+}
+
+// `limit_order::OpenLimitOrderEvent` of Cetus limit orders. `price` is the
+// target coin per pay coin, scaled by 2^64.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct CetusOpenLimitOrderEvent {
+    pub order_id: ObjectID,
+    pub owner: SuiAddress,
+    pub pay_coin_type: TypeName,
+    pub target_coin_type: TypeName,
+    #[serde(deserialize_with = "number")]
+    pub pay_amount: u64,
+    #[serde(deserialize_with = "number")]
+    pub price: u128,
+    #[serde(deserialize_with = "number")]
+    pub expire_ts: u64,
+}
+
+// `limit_order::FlowLimitOrderEvent` of Cetus limit orders, a keeper filling
+// part of an order. The target coins stay in the order until claimed.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct CetusFlowLimitOrderEvent {
+    pub order_id: ObjectID,
+    pub pay_coin_type: TypeName,
+    pub target_coin_type: TypeName,
+    #[serde(deserialize_with = "number")]
+    pub pay_amount: u64,
+    #[serde(deserialize_with = "number")]
+    pub target_amount: u64,
+}
+
+// `limit_order::CancelLimitOrderEvent` of Cetus limit orders. The unspent
+// pay coins are refunded and the unclaimed target coins paid out with them.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct CetusCancelLimitOrderEvent {
+    pub order_id: ObjectID,
+    pub owner: SuiAddress,
+    pub pay_coin_type: TypeName,
+    pub target_coin_type: TypeName,
+    #[serde(deserialize_with = "number")]
+    pub pay_amount: u64,
+    #[serde(deserialize_with = "number")]
+    pub target_amount: u64,
+}
+
+// `limit_order::ClaimTargetCoinEvent` of Cetus limit orders.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct CetusClaimTargetCoinEvent {
+    pub order_id: ObjectID,
+    pub owner: SuiAddress,
+    pub target_coin_type: TypeName,
+    #[serde(deserialize_with = "number")]
+    pub target_amount: u64,
+}
+
+// `kiosk::ItemListed<T>` and `kiosk::ItemPurchased<T>` of the Sui framework,
+// `T` being the item's type.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct KioskItemEvent {
+    pub kiosk: ObjectID,
+    pub id: ObjectID,
+    #[serde(deserialize_with = "number")]
+    pub price: u64,
+}
+
+// `kiosk::ItemDelisted<T>` of the Sui framework.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct KioskItemDelisted {
+    pub kiosk: ObjectID,
+    pub id: ObjectID,
+}
+
+// `bridge::TokenDepositedEvent` of the Sui Bridge, coins locked on Sui to be
+// minted on the target chain. Addresses are raw bytes, 20 for Ethereum.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct SuiBridgeTokenDeposited {
+    #[serde(deserialize_with = "number")]
+    pub seq_num: u64,
+    pub source_chain: u8,
+    pub sender_address: Vec<u8>,
+    pub target_chain: u8,
+    pub target_address: Vec<u8>,
+    pub token_type: u8,
+    #[serde(deserialize_with = "number")]
+    pub amount: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct SuiBridgeMessageKey {
+    pub source_chain: u8,
+    pub message_type: u8,
+    #[serde(deserialize_with = "number")]
+    pub bridge_seq_num: u64,
+}
+
+// `bridge::TokenTransferClaimed` of the Sui Bridge, coins bridged in and
+// released to their Sui recipient.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct SuiBridgeTransferClaimed {
+    pub message_key: SuiBridgeMessageKey,
+}
+
+// `publish_message::WormholeMessage` of Wormhole core, the payload being a
+// token bridge transfer when sent by the token bridge.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct WormholeMessage {
+    pub sender: ObjectID,
+    #[serde(deserialize_with = "number")]
+    pub sequence: u64,
+    pub nonce: u32,
+    pub payload: Vec<u8>,
+    pub consistency_level: u8,
+    #[serde(deserialize_with = "number")]
+    pub timestamp: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct WormholeBytes32 {
+    pub data: Vec<u8>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct WormholeExternalAddress {
+    pub value: WormholeBytes32,
+}
+
+// `complete_transfer::TransferRedeemed` of the Wormhole token bridge.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct WormholeTransferRedeemed {
+    pub emitter_chain: u16,
+    pub emitter_address: WormholeExternalAddress,
+    #[serde(deserialize_with = "number")]
+    pub sequence: u64,
+}
+
+// What the decoder needs from a swap event, whichever dex emitted it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SwapFields {
+    pub pool: ObjectID,
+    pub a2b: bool,
+    pub amount_in: u64,
+    pub amount_out: u64,
+    pub before_sqrt_price: u128,
+    pub after_sqrt_price: u128,
+}
+
+impl From<CetusSwapEvent> for SwapFields {
+    fn from(e: CetusSwapEvent) -> Self {
+        Self {
+            pool: e.pool,
+            a2b: e.atob,
+            amount_in: e.amount_in,
+            amount_out: e.amount_out,
+            before_sqrt_price: e.before_sqrt_price,
+            after_sqrt_price: e.after_sqrt_price,
+        }
+    }
+}
+
+impl From<BluefinSwapEvent> for SwapFields {
+    fn from(e: BluefinSwapEvent) -> Self {
+        Self {
+            pool: e.pool_id,
+            a2b: e.a2b,
+            amount_in: e.amount_in,
+            amount_out: e.amount_out,
+            before_sqrt_price: e.before_sqrt_price,
+            after_sqrt_price: e.after_sqrt_price,
+        }
+    }
+}
+
+fn event_name(event: &SuiEvent) -> String {
+    format!("{}::{}", event.type_.module, event.type_.name)
+}
+
+// Deserializes `event` into `T`. Errors name the protocol, the event and,
+// for JSON, the offending field; BCS payloads carry no field names.
+pub fn parse<T: DeserializeOwned>(
+    protocol: &str,
+    event: &SuiEvent,
+    mode: DecodeMode,
+) -> Result<T, EventDecodeError> {
+    match mode {
+        DecodeMode::Json => parse_json(protocol, event),
+        DecodeMode::Bcs => {
+            bcs::from_bytes(event.bcs.bytes()).map_err(|e| EventDecodeError::Invalid {
+                protocol: protocol.to_string(),
+                event: event_name(event),
+                reason: format!("bcs: {e}"),
+            })
+        }
+    }
+}
+
+fn parse_json<T: DeserializeOwned>(
+    protocol: &str,
+    event: &SuiEvent,
+) -> Result<T, EventDecodeError> {
+    serde_path_to_error::deserialize(&event.parsed_json).map_err(|e| {
+        let reason = e.inner().to_string();
+        if e.path().iter().next().is_none() {
+            EventDecodeError::Invalid {
+                protocol: protocol.to_string(),
+                event: event_name(event),
+                reason,
+            }
+        } else {
+            EventDecodeError::InvalidField {
+                protocol: protocol.to_string(),
+                event: event_name(event),
+                field: e.path().to_string(),
+                reason,
+            }
+        }
+    })
+}
+
+pub fn decode_swap(
+    dex: Dex,
+    event: &SuiEvent,
+    mode: DecodeMode,
+) -> Result<SwapFields, EventDecodeError> {
+    let protocol = format!("{dex:?}");
+    match dex {
+        Dex::Cetus | Dex::Magma => parse::<CetusSwapEvent>(&protocol, event, mode).map(Into::into),
+        Dex::Bluefin => parse::<BluefinSwapEvent>(&protocol, event, mode).map(Into::into),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use sui_sdk::rpc_types::SuiEvent;
+
+    use super::{BluefinSwapEvent, CetusSwapEvent, DecodeMode, decode_swap, parse};
+    use crate::{Dex, errors::EventDecodeError};
+
+    const FIXTURES: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/fixtures/decode");
+
+    fn fixture_event(name: &str) -> SuiEvent {
+        let tx: serde_json::Value = serde_json::from_str(
+            &std::fs::read_to_string(format!("{FIXTURES}/{name}.tx.json")).unwrap(),
+        )
+        .unwrap();
+        serde_json::from_value(tx["events"][0].clone()).unwrap()
+    }
+
+    #[test]
+    fn test_parse_cetus_swap() {
+        let event = fixture_event("cetus_swap_sui_usdc");
+        let swap: CetusSwapEvent = parse("Cetus", &event, DecodeMode::Json).unwrap();
+        assert!(!swap.atob);
+        assert_eq!(swap.amount_in, 1_000_000_000);
+        assert_eq!(swap.before_sqrt_price, 311_800_000_000_000_000_000);
+        assert_eq!(swap.steps, 1);
+    }
+
+    #[test]
+    fn test_parse_bluefin_swap() {
+        let event = fixture_event("bluefin_swap_usdc_sui");
+        let swap: BluefinSwapEvent = parse("Bluefin", &event, DecodeMode::Json).unwrap();
+        assert_eq!(swap.amount_out, 285_000_000);
+        assert_eq!(swap.current_tick.value(), -56557);
+        assert_eq!(swap.sequence_number, 9_876_543);
+    }
+
+    #[test]
+    fn test_schema_drift_names_the_field() {
+        let mut event = fixture_event("cetus_swap_sui_usdc");
+        event.parsed_json["amount_out"] = json!("35x");
+        let err = decode_swap(Dex::Cetus, &event, DecodeMode::Json).unwrap_err();
+        assert!(matches!(
+            &err,
+            EventDecodeError::InvalidField { protocol, field, .. }
+                if protocol == "Cetus" && field == "amount_out"
+        ));
+
+        event
+            .parsed_json
+            .as_object_mut()
+            .unwrap()
+            .remove("amount_out");
+        assert_eq!(
+            decode_swap(Dex::Magma, &event, DecodeMode::Json)
+                .unwrap_err()
+                .to_string(),
+            "Magma pool::SwapEvent: missing field `amount_out`"
+        );
+    }
+
+    #[test]
+    fn test_bcs_matches_json() {
+        for (name, dex) in [
+            ("cetus_swap_sui_usdc", Dex::Cetus),
+            ("bluefin_swap_usdc_sui", Dex::Bluefin),
+        ] {
+            let event = fixture_event(name);
+            assert_eq!(
+                decode_swap(dex, &event, DecodeMode::Bcs).unwrap(),
+                decode_swap(dex, &event, DecodeMode::Json).unwrap(),
+                "{name}"
+            );
+        }
+
+        let event = fixture_event("bluefin_swap_usdc_sui");
+        let json: BluefinSwapEvent = parse("Bluefin", &event, DecodeMode::Json).unwrap();
+        let bcs: BluefinSwapEvent = parse("Bluefin", &event, DecodeMode::Bcs).unwrap();
+        assert_eq!(json, bcs);
+    }
+
+    #[test]
+    fn test_bcs_wrong_layout() {
+        let event = fixture_event("cetus_swap_sui_usdc");
+        assert!(matches!(
+            decode_swap(Dex::Bluefin, &event, DecodeMode::Bcs),
+            Err(EventDecodeError::Invalid { protocol, .. }) if protocol == "Bluefin"
+        ));
+    }
+}
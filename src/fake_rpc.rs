@@ -0,0 +1,225 @@
+use anyhow::{Context, Result};
+use axum::{
+    Json, Router,
+    extract::State,
+    http::{StatusCode, header::RETRY_AFTER},
+    response::{IntoResponse, Response},
+    routing::post,
+};
+use mini_macro::here as h;
+use serde::de::DeserializeOwned;
+use serde_json::{Value, json};
+use std::{
+    collections::VecDeque,
+    net::SocketAddr,
+    sync::{Arc, Mutex},
+};
+use sui_sdk::rpc_types::{
+    EventFilter, Filter, SuiEvent, SuiTransactionBlockResponse, SuiTransactionBlockResponseQuery,
+};
+use sui_types::{digests::TransactionDigest, event::EventID};
+use tokio::{net::TcpListener, task::JoinHandle};
+
+use crate::source::{matches_filter, page};
+
+#[derive(Debug, Clone)]
+pub enum Fault {
+    Status(u16),
+    RateLimited,
+    JsonRpc(i64, String),
+}
+
+#[derive(Default)]
+struct FakeState {
+    // ascending by checkpoint, the way the node returns them with
+    // `descending_order = false`
+    txs: Vec<SuiTransactionBlockResponse>,
+    faults: VecDeque<Fault>,
+    down: bool,
+    requests: usize,
+}
+
+// In-process JSON-RPC node serving a fixed set of transactions, with
+// injectable faults for exercising retries and failover.
+pub struct FakeRpcServer {
+    addr: SocketAddr,
+    state: Arc<Mutex<FakeState>>,
+    handle: JoinHandle<()>,
+}
+
+impl FakeRpcServer {
+    pub async fn start(txs: Vec<SuiTransactionBlockResponse>) -> Result<Self> {
+        let state = Arc::new(Mutex::new(FakeState {
+            txs,
+            ..Default::default()
+        }));
+        let app = Router::new()
+            .route("/", post(handle))
+            .with_state(state.clone());
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.context(h!())?;
+        let addr = listener.local_addr().context(h!())?;
+        let handle = tokio::spawn(async move {
+            if let Err(e) = axum::serve(listener, app).await {
+                tracing::error!("fake rpc server stopped: {e:?}");
+            }
+        });
+
+        Ok(Self {
+            addr,
+            state,
+            handle,
+        })
+    }
+
+    pub fn url(&self) -> String {
+        format!("http://{}", self.addr)
+    }
+
+    // Faults are consumed one per request, in order.
+    pub fn inject(&self, fault: Fault) {
+        self.state.lock().unwrap().faults.push_back(fault);
+    }
+
+    // Answer every request with 503 until turned off again.
+    pub fn set_down(&self, down: bool) {
+        self.state.lock().unwrap().down = down;
+    }
+
+    pub fn requests(&self) -> usize {
+        self.state.lock().unwrap().requests
+    }
+}
+
+impl Drop for FakeRpcServer {
+    fn drop(&mut self) {
+        self.handle.abort();
+    }
+}
+
+async fn handle(State(state): State<Arc<Mutex<FakeState>>>, Json(req): Json<Value>) -> Response {
+    let (down, fault) = {
+        let mut state = state.lock().unwrap();
+        state.requests += 1;
+        (state.down, state.faults.pop_front())
+    };
+
+    if down {
+        return (StatusCode::SERVICE_UNAVAILABLE, "down").into_response();
+    }
+    let id = req.get("id").cloned().unwrap_or(Value::Null);
+    match fault {
+        Some(Fault::Status(status)) => {
+            let status = StatusCode::from_u16(status).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+            return (status, "injected fault").into_response();
+        }
+        Some(Fault::RateLimited) => {
+            return (
+                StatusCode::TOO_MANY_REQUESTS,
+                [(RETRY_AFTER, "0")],
+                "slow down",
+            )
+                .into_response();
+        }
+        Some(Fault::JsonRpc(code, message)) => {
+            return Json(json!({
+                "jsonrpc": "2.0",
+                "id": id,
+                "error": {"code": code, "message": message},
+            }))
+            .into_response();
+        }
+        None => {}
+    }
+
+    let method = req
+        .get("method")
+        .and_then(Value::as_str)
+        .unwrap_or_default();
+    let params = req.get("params").cloned().unwrap_or(json!([]));
+    let result = {
+        let state = state.lock().unwrap();
+        match method {
+            "suix_queryTransactionBlocks" => query_transaction_blocks(&state, &params),
+            "sui_getTransactionBlock" => get_transaction_block(&state, &params),
+            "suix_queryEvents" => query_events(&state, &params),
+            _ => Err((-32601, format!("Method not found: {method}"))),
+        }
+    };
+
+    let body = match result {
+        Ok(result) => json!({"jsonrpc": "2.0", "id": id, "result": result}),
+        Err((code, message)) => json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "error": {"code": code, "message": message},
+        }),
+    };
+    Json(body).into_response()
+}
+
+// The `index`th param, null when left out.
+fn param<T: DeserializeOwned>(params: &Value, index: usize) -> Result<T, (i64, String)> {
+    let value = params.get(index).cloned().unwrap_or(Value::Null);
+    serde_json::from_value(value).map_err(|e| (-32602, format!("invalid param {index}: {e}")))
+}
+
+fn invalid_params(e: anyhow::Error) -> (i64, String) {
+    (-32602, format!("{e:#}"))
+}
+
+fn query_transaction_blocks(state: &FakeState, params: &Value) -> Result<Value, (i64, String)> {
+    let query: SuiTransactionBlockResponseQuery = param(params, 0)?;
+    let cursor: Option<TransactionDigest> = param(params, 1)?;
+    let limit: Option<usize> = param(params, 2)?;
+    let descending: Option<bool> = param(params, 3)?;
+
+    let mut txs = state
+        .txs
+        .iter()
+        .filter(|tx| {
+            query
+                .filter
+                .as_ref()
+                .is_none_or(|filter| matches_filter(filter, tx))
+        })
+        .collect::<Vec<_>>();
+    if descending.unwrap_or(false) {
+        txs.reverse();
+    }
+    let page = page(txs, |tx| tx.digest, cursor, limit).map_err(invalid_params)?;
+    Ok(json!(page))
+}
+
+fn get_transaction_block(state: &FakeState, params: &Value) -> Result<Value, (i64, String)> {
+    let digest: TransactionDigest = param(params, 0)?;
+    state
+        .txs
+        .iter()
+        .find(|tx| tx.digest == digest)
+        .map(|tx| json!(tx))
+        .ok_or((
+            -32602,
+            format!("Could not find the referenced transaction {digest}"),
+        ))
+}
+
+fn query_events(state: &FakeState, params: &Value) -> Result<Value, (i64, String)> {
+    let filter: EventFilter = param(params, 0)?;
+    let cursor: Option<EventID> = param(params, 1)?;
+    let limit: Option<usize> = param(params, 2)?;
+    let descending: Option<bool> = param(params, 3)?;
+
+    let mut events = state
+        .txs
+        .iter()
+        .filter_map(|tx| tx.events.as_ref())
+        .flat_map(|events| &events.data)
+        .filter(|event| filter.matches(event))
+        .collect::<Vec<&SuiEvent>>();
+    if descending.unwrap_or(false) {
+        events.reverse();
+    }
+    let page = page(events, |event| event.id, cursor, limit).map_err(invalid_params)?;
+    Ok(json!(page))
+}
@@ -1,76 +1,564 @@
 use anyhow::{Context, Result, bail};
 use bigdecimal::BigDecimal;
-use futures::{future, stream::StreamExt};
 use mini_macro::here as h;
-use std::str::FromStr;
-use sui_sdk::{
-    SuiClient, SuiClientBuilder,
-    rpc_types::{
-        SuiTransactionBlockEffects, SuiTransactionBlockEffectsAPI, SuiTransactionBlockResponse,
-        SuiTransactionBlockResponseOptions, SuiTransactionBlockResponseQuery, TransactionFilter,
+use std::{
+    collections::{BTreeMap, HashSet},
+    path::PathBuf,
+    str::FromStr,
+    time::Duration,
+};
+use sui_sdk::rpc_types::{
+    SuiTransactionBlockEffects, SuiTransactionBlockEffectsAPI, SuiTransactionBlockResponse,
+    SuiTransactionBlockResponseOptions, SuiTransactionBlockResponseQuery, TransactionFilter,
+};
+use sui_types::{
+    base_types::{ObjectID, SuiAddress},
+    digests::TransactionDigest,
+};
+
+use crate::{
+    TransactionKind, TxType,
+    arbitrage::{ArbitrageAnalyzer, Trade},
+    config::{
+        CoinConfig, Config, DexConfig, PackageConfig, SinkConfig, default_coins, default_dexes,
     },
+    decode::TxDecoder,
+    deepbook,
+    errors::DecodeError,
+    events::DecodeMode,
+    kiosk, liquidation,
+    network::Network,
+    orders,
+    replay::{RecordingSource, ReplaySource},
+    rpc::{RateLimit, RetryPolicy, RpcClient, RpcConfig},
+    sink,
+    source::TransactionSource,
 };
-use sui_types::base_types::SuiAddress;
 
-use crate::{Dex, Swap, TransactionKind, TxType, errors::DecodeError, transfer};
+fn is_kiosk(tx_type: &TxType) -> bool {
+    match tx_type {
+        TxType::NftListed(_) | TxType::NftDelisted(_) | TxType::NftTrade(_) => true,
+        TxType::Batch(tx_types) => tx_types.iter().any(is_kiosk),
+        _ => false,
+    }
+}
+
+fn is_fill(tx_type: &TxType) -> bool {
+    match tx_type {
+        TxType::OrderFilled(_) => true,
+        TxType::Batch(tx_types) => tx_types.iter().any(is_fill),
+        _ => false,
+    }
+}
+
+fn is_bridge(tx_type: &TxType) -> bool {
+    match tx_type {
+        TxType::BridgeOut(_) | TxType::BridgeIn(_) => true,
+        TxType::Batch(tx_types) => tx_types.iter().any(is_bridge),
+        _ => false,
+    }
+}
 
 pub struct ActivityFetcher {
-    sui_client: SuiClient,
+    source: Box<dyn TransactionSource>,
     who: SuiAddress,
     old_first: bool,
     from: Option<i64>,
     to: Option<i64>,
+    decoder: TxDecoder,
+    balance_managers: Vec<ObjectID>,
+    options: SuiTransactionBlockResponseOptions,
+    sinks: Vec<SinkConfig>,
+}
+
+pub struct ActivityFetcherBuilder {
+    who: String,
+    network: Network,
+    fallback_urls: Vec<String>,
+    rpc_config: RpcConfig,
+    source: Option<Box<dyn TransactionSource>>,
+    replay_from: Option<PathBuf>,
+    record_to: Option<PathBuf>,
+    options: SuiTransactionBlockResponseOptions,
+    old_first: bool,
+    from: Option<i64>,
+    to: Option<i64>,
+    dexes: Vec<DexConfig>,
+    packages: Vec<PackageConfig>,
+    coins: Vec<CoinConfig>,
+    balance_managers: Vec<ObjectID>,
+    decode_mode: DecodeMode,
+    sinks: Vec<SinkConfig>,
+}
+
+impl ActivityFetcherBuilder {
+    pub fn network(mut self, network: Network) -> Self {
+        self.network = network;
+        self
+    }
+
+    pub fn rpc_url(mut self, url: &str) -> Self {
+        self.network = Network::Custom(url.to_string());
+        self
+    }
+
+    // Endpoints to fail over to, in order, when the primary one keeps failing.
+    pub fn fallback_urls(mut self, urls: Vec<String>) -> Self {
+        self.fallback_urls = urls;
+        self
+    }
+
+    pub fn header(mut self, key: &str, value: &str) -> Self {
+        self.rpc_config
+            .headers
+            .insert(key.to_string(), value.to_string());
+        self
+    }
+
+    pub fn headers(mut self, headers: BTreeMap<String, String>) -> Self {
+        self.rpc_config.headers.extend(headers);
+        self
+    }
+
+    pub fn request_timeout(mut self, timeout: Duration) -> Self {
+        self.rpc_config.request_timeout = timeout;
+        self
+    }
+
+    pub fn retry_policy(mut self, retry: RetryPolicy) -> Self {
+        self.rpc_config.retry = retry;
+        self
+    }
+
+    // Applied to each endpoint separately.
+    pub fn rate_limit(mut self, rate_limit: RateLimit) -> Self {
+        self.rpc_config.rate_limit = Some(rate_limit);
+        self
+    }
+
+    pub fn response_options(mut self, options: SuiTransactionBlockResponseOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    pub fn old_first(mut self, old_first: bool) -> Self {
+        self.old_first = old_first;
+        self
+    }
+
+    pub fn time_range(mut self, from: Option<i64>, to: Option<i64>) -> Self {
+        self.from = from;
+        self.to = to;
+        self
+    }
+
+    pub fn dexes(mut self, dexes: Vec<DexConfig>) -> Self {
+        self.dexes = dexes;
+        self
+    }
+
+    pub fn packages(mut self, packages: Vec<PackageConfig>) -> Self {
+        self.packages = packages;
+        self
+    }
+
+    // Coins with their decimals, `default_coins` unless set.
+    pub fn coins(mut self, coins: Vec<CoinConfig>) -> Self {
+        self.coins = coins;
+        self
+    }
+
+    // DeepBook balance managers of the wallet, so its maker fills are
+    // reported as such.
+    pub fn balance_managers(mut self, balance_managers: Vec<ObjectID>) -> Self {
+        self.balance_managers = balance_managers;
+        self
+    }
+
+    // Decode events from their BCS payload instead of `parsed_json`.
+    pub fn decode_mode(mut self, mode: DecodeMode) -> Self {
+        self.decode_mode = mode;
+        self
+    }
+
+    // Where `export_txs` writes the decoded transactions.
+    pub fn sinks(mut self, sinks: Vec<SinkConfig>) -> Self {
+        self.sinks = sinks;
+        self
+    }
+
+    // Read from `source` instead of the configured network, e.g. a
+    // `RecordedSource` or a `FakeRpcServer` client in tests.
+    pub fn source(mut self, source: impl TransactionSource + 'static) -> Self {
+        self.source = Some(Box::new(source));
+        self
+    }
+
+    // Save every response the fetch reads into a compressed archive.
+    pub fn record(mut self, path: impl Into<PathBuf>) -> Self {
+        self.record_to = Some(path.into());
+        self
+    }
+
+    // Serve everything from an archive written by `record`.
+    pub fn replay(mut self, path: impl Into<PathBuf>) -> Self {
+        self.replay_from = Some(path.into());
+        self
+    }
+
+    pub async fn build(self) -> Result<ActivityFetcher> {
+        // the decoder reads all three of every transaction
+        let options = &self.options;
+        if !(options.show_effects && options.show_events && options.show_balance_changes) {
+            bail!("response options must show effects, events and balance changes");
+        }
+
+        let mut source: Box<dyn TransactionSource> = match (self.replay_from, self.source) {
+            (Some(path), _) => Box::new(ReplaySource::load(path).context(h!())?),
+            (None, Some(source)) => source,
+            (None, None) => {
+                let mut urls = vec![self.network.rpc_url().to_string()];
+                urls.extend(self.fallback_urls);
+                Box::new(RpcClient::new(&urls, &self.rpc_config).context(h!())?)
+            }
+        };
+        if let Some(path) = self.record_to {
+            source = Box::new(RecordingSource::create(source, path).context(h!())?);
+        }
+
+        let who = SuiAddress::from_str(&self.who).context(h!())?;
+        Ok(ActivityFetcher {
+            source,
+            who,
+            old_first: self.old_first,
+            from: self.from,
+            to: self.to,
+            decoder: TxDecoder::new(who, self.dexes)
+                .with_packages(&self.packages)
+                .with_coins(&self.coins)
+                .with_balance_managers(&self.balance_managers)
+                .with_mode(self.decode_mode),
+            balance_managers: self.balance_managers,
+            options: self.options,
+            sinks: self.sinks,
+        })
+    }
 }
 
 impl ActivityFetcher {
+    pub fn builder(who: &str) -> ActivityFetcherBuilder {
+        ActivityFetcherBuilder {
+            who: who.to_string(),
+            network: Network::Mainnet,
+            fallback_urls: vec![],
+            rpc_config: RpcConfig::default(),
+            source: None,
+            replay_from: None,
+            record_to: None,
+            options: SuiTransactionBlockResponseOptions::default()
+                .with_input()
+                .with_effects()
+                .with_events()
+                .with_balance_changes()
+                .with_object_changes(),
+            old_first: false,
+            from: None,
+            to: None,
+            dexes: default_dexes(),
+            packages: vec![],
+            coins: default_coins(),
+            balance_managers: vec![],
+            decode_mode: DecodeMode::default(),
+            sinks: vec![],
+        }
+    }
+
     pub async fn new_mainnet(
         who: &str,
         old_first: bool,
         from: Option<i64>,
         to: Option<i64>,
     ) -> Result<Self> {
-        let sui_client = SuiClientBuilder::default()
-            .build_mainnet()
+        Self::builder(who)
+            .old_first(old_first)
+            .time_range(from, to)
+            .build()
             .await
-            .context(h!())?;
+    }
 
-        Ok(Self {
-            sui_client,
-            who: SuiAddress::from_str(who).context(h!())?,
-            old_first,
-            from,
-            to,
-        })
+    pub async fn from_config(
+        config: &Config,
+        wallet: &str,
+        old_first: bool,
+        from: Option<i64>,
+        to: Option<i64>,
+    ) -> Result<Self> {
+        let wallet = config.wallet(wallet)?;
+        let (name, network) = config.network(wallet.network.as_deref())?;
+        let (url, fallback_urls) = network.rpc_urls.split_first().context(h!())?;
+
+        let mut builder = Self::builder(&wallet.address)
+            .rpc_url(url)
+            .fallback_urls(fallback_urls.to_vec())
+            .headers(network.headers.clone())
+            .retry_policy(network.retry.clone());
+        if let Some(rate_limit) = &network.rate_limit {
+            builder = builder.rate_limit(rate_limit.clone());
+        }
+        builder
+            .old_first(old_first)
+            .time_range(from, to)
+            .dexes(config.dexes())
+            .packages(config.packages.clone())
+            .coins(config.coins())
+            .sinks(config.sinks.clone())
+            .build()
+            .await
+            .with_context(|| format!("build fetcher for network {name}"))
     }
 
     pub async fn fetch_txs(self, by_from: bool) -> Result<()> {
+        self.for_each_tx(by_from, |tx_resp| self.log_sui_tx_resp(tx_resp))
+            .await?;
+        self.finish()
+    }
+
+    // Finalizes the source, e.g. the archive of a `record` run. Call it once
+    // done fetching.
+    pub fn finish(self) -> Result<()> {
+        self.source.finish().context(h!())
+    }
+
+    // Decodes the wallet's transactions and writes them to the configured
+    // sinks.
+    pub async fn export_txs(&self, by_from: bool) -> Result<Vec<TransactionKind>> {
+        let txs = self.collect_txs(by_from).await.context(h!())?;
+        sink::write(&self.sinks, &txs).context(h!())?;
+        Ok(txs)
+    }
+
+    // Decodes every successful transaction in the time range. Those that
+    // cannot be decoded are logged and left out, one unknown transaction
+    // doesn't fail the whole history.
+    pub async fn collect_txs(&self, by_from: bool) -> Result<Vec<TransactionKind>> {
+        let mut txs = vec![];
+        self.for_each_tx(by_from, |tx_resp| txs.extend(self.decode_or_skip(tx_resp)))
+            .await?;
+        Ok(txs)
+    }
+
+    fn decode_or_skip(&self, tx_resp: SuiTransactionBlockResponse) -> Option<TransactionKind> {
+        if !self.in_range(&tx_resp) {
+            return None;
+        }
+        let digest = tx_resp.digest;
+        let decoded = Self::is_err(&tx_resp).and_then(|failed| {
+            if failed {
+                return Ok(None);
+            }
+            self.decoder.decode(tx_resp).map(Some)
+        });
+        match decoded {
+            Ok(tx) => tx,
+            Err(e) => {
+                tracing::warn!(%digest, "skipping tx that cannot be decoded: {e:#}");
+                None
+            }
+        }
+    }
+
+    // Liquidations of the wallet's lending accounts. The liquidator sends
+    // them, so `collect_txs` never sees them; they are found through the
+    // obligation keys and caps the wallet owns instead.
+    pub async fn collect_liquidations(&self) -> Result<Vec<TransactionKind>> {
+        let accounts = liquidation::lending_accounts(&*self.source, self.who)
+            .await
+            .context(h!())?;
+        let since_ms = self.from.map(|from| from as u64 * 1000);
+        let digests = liquidation::find_liquidations(&*self.source, &accounts, since_ms)
+            .await
+            .context(h!())?;
+
+        let mut txs = self.decode_digests(digests).await?;
+        self.sort(&mut txs);
+        Ok(txs)
+    }
+
+    // The wallet's limit order transactions, and the keeper
+    // transactions filling its orders, which `collect_txs` never sees.
+    // `orders::order_history` groups them by order.
+    pub async fn collect_orders(&self) -> Result<Vec<TransactionKind>> {
+        let mut txs = self.collect_txs(true).await.context(h!())?;
+        txs.retain(|tx| !orders::order_ids(&tx.tx_type).is_empty());
+
+        let ids = txs
+            .iter()
+            .flat_map(|tx| orders::order_ids(&tx.tx_type))
+            .map(str::to_string)
+            .collect::<HashSet<_>>();
+        let since_ms = self.from.map(|from| from as u64 * 1000);
+        let digests = orders::find_fills(&*self.source, &ids, since_ms)
+            .await
+            .context(h!())?
+            .into_iter()
+            .filter(|digest| !txs.iter().any(|tx| tx.tx_hash == *digest))
+            .collect();
+
+        txs.extend(self.decode_digests(digests).await?);
+        self.sort(&mut txs);
+        Ok(txs)
+    }
+
+    // The wallet's DeepBook fills: its taker fills from its own transactions,
+    // and the fills of its resting orders, which the takers send, through the
+    // events of its balance managers.
+    pub async fn collect_fills(&self) -> Result<Vec<TransactionKind>> {
+        let mut txs = self.collect_txs(true).await.context(h!())?;
+        txs.retain(|tx| is_fill(&tx.tx_type));
+
+        let since_ms = self.from.map(|from| from as u64 * 1000);
+        let digests = deepbook::find_maker_fills(&*self.source, &self.balance_managers, since_ms)
+            .await
+            .context(h!())?
+            .into_iter()
+            .filter(|digest| !txs.iter().any(|tx| tx.tx_hash == *digest))
+            .collect();
+
+        txs.extend(self.decode_digests(digests).await?);
+        self.sort(&mut txs);
+        Ok(txs)
+    }
+
+    // The wallet's Kiosk listings and purchases, and the sales out of its
+    // kiosks, which the buyers send. The sellers of the items it bought are
+    // looked up.
+    pub async fn collect_nft_trades(&self) -> Result<Vec<TransactionKind>> {
+        let mut txs = self.collect_txs(true).await.context(h!())?;
+        txs.retain(|tx| is_kiosk(&tx.tx_type));
+
+        let kiosks = kiosk::owned_kiosks(&*self.source, self.who)
+            .await
+            .context(h!())?;
+        let since_ms = self.from.map(|from| from as u64 * 1000);
+        let digests = kiosk::find_sales(&*self.source, &kiosks, since_ms)
+            .await
+            .context(h!())?
+            .into_iter()
+            .filter(|digest| !txs.iter().any(|tx| tx.tx_hash == *digest))
+            .collect();
+
+        txs.extend(self.decode_digests(digests).await?);
+        kiosk::set_seller(&mut txs, &kiosks, self.who);
+        kiosk::resolve_sellers(&*self.source, &mut txs)
+            .await
+            .context(h!())?;
+        self.sort(&mut txs);
+        Ok(txs)
+    }
+
+    // The wallet's bridge transfers, to be matched with their other side by
+    // `bridge::transfer_id`.
+    pub async fn collect_bridge_transfers(&self) -> Result<Vec<TransactionKind>> {
+        let mut txs = self.collect_txs(true).await.context(h!())?;
+        // relayers send the claims of inbound transfers, the wallet only
+        // receives the coins
+        for tx in self.collect_txs(false).await.context(h!())? {
+            if !txs.iter().any(|sent| sent.tx_hash == tx.tx_hash) {
+                txs.push(tx);
+            }
+        }
+        txs.retain(|tx| is_bridge(&tx.tx_type));
+        self.sort(&mut txs);
+        Ok(txs)
+    }
+
+    // Fetches and decodes transactions found through their events, leaving
+    // out failed ones and those outside the time range.
+    async fn decode_digests(
+        &self,
+        digests: Vec<TransactionDigest>,
+    ) -> Result<Vec<TransactionKind>> {
+        let mut txs = vec![];
+        for digest in digests {
+            let tx_resp = self
+                .source
+                .get_transaction_block(digest, &self.options)
+                .await
+                .context(h!())?;
+            txs.extend(self.decode_or_skip(tx_resp));
+        }
+        Ok(txs)
+    }
+
+    fn sort(&self, txs: &mut [TransactionKind]) {
+        txs.sort_by_key(|tx| tx.event_timestamp_ms);
+        if !self.old_first {
+            txs.reverse();
+        }
+    }
+
+    // The trades the wallet sent, classified by `analyzer`. The coins of
+    // pools its swap events don't name are looked up first.
+    pub async fn collect_trades(&self, analyzer: &mut ArbitrageAnalyzer<'_>) -> Result<Vec<Trade>> {
+        let txs = self.collect_txs(true).await.context(h!())?;
+        analyzer
+            .resolve_pools(&*self.source, &txs)
+            .await
+            .context(h!())?;
+        Ok(analyzer.analyze(&txs))
+    }
+
+    async fn for_each_tx(
+        &self,
+        by_from: bool,
+        mut f: impl FnMut(SuiTransactionBlockResponse),
+    ) -> Result<()> {
         let filter = if by_from {
             TransactionFilter::FromAddress(self.who)
         } else {
             TransactionFilter::ToAddress(self.who)
         };
-
-        let options = SuiTransactionBlockResponseOptions::default()
-            .with_effects()
-            .with_events()
-            .with_balance_changes();
-        let filter = SuiTransactionBlockResponseQuery::new(Some(filter), Some(options));
+        let query = SuiTransactionBlockResponseQuery::new(Some(filter), Some(self.options.clone()));
 
         let descending_order = !self.old_first;
-        let txs =
-            self.sui_client
-                .read_api()
-                .get_transactions_stream(filter, None, descending_order);
-
-        txs.for_each(|tx_resp| {
-            self.log_sui_tx_resp(tx_resp);
-            future::ready(())
-        })
-        .await;
+        let mut cursor = None;
+        loop {
+            let page = self
+                .source
+                .query_transaction_blocks(&query, cursor, None, descending_order)
+                .await
+                .context(h!())?;
+
+            for tx_resp in page.data {
+                f(tx_resp);
+            }
+
+            if !page.has_next_page {
+                break;
+            }
+            cursor = page.next_cursor;
+        }
 
         Ok(())
     }
 
+    fn in_range(&self, tx_resp: &SuiTransactionBlockResponse) -> bool {
+        let Some(timestamp_ms) = tx_resp.timestamp_ms else {
+            return true;
+        };
+        if let Some(from) = self.from {
+            if timestamp_ms / 1000 < from as u64 {
+                return false;
+            }
+        }
+        if let Some(to) = self.to {
+            if timestamp_ms / 1000 > to as u64 {
+                return false;
+            }
+        }
+        true
+    }
+
     fn is_err(tx_resp: &SuiTransactionBlockResponse) -> Result<bool> {
         let res = tx_resp
             .effects
@@ -82,22 +570,20 @@ impl ActivityFetcher {
 
     // TODO: 允许用户自己注册解码代码！
     fn log_sui_tx_resp(&self, tx_resp: SuiTransactionBlockResponse) {
-        if let Some(from) = self.from {
-            if tx_resp.timestamp_ms.unwrap() / 1000 < from as u64 {
-                return;
-            }
+        if !self.in_range(&tx_resp) {
+            return;
         }
-        if let Some(to) = self.to {
-            if tx_resp.timestamp_ms.unwrap() / 1000 > to as u64 {
+
+        match Self::is_err(&tx_resp) {
+            Ok(false) => {}
+            Ok(true) => return,
+            Err(e) => {
+                tracing::warn!(digest = %tx_resp.digest, "skipping tx that cannot be decoded: {e:#}");
                 return;
             }
         }
 
-        if Self::is_err(&tx_resp).unwrap() {
-            return;
-        }
-
-        match self.decode_tx_type(tx_resp.clone()) {
+        match self.decoder.decode(tx_resp.clone()) {
             Ok(res) => {
                 tracing::info!("{}", serde_json::to_string(&res).unwrap())
             }
@@ -109,160 +595,6 @@ impl ActivityFetcher {
         };
     }
 
-    fn decode_tx_type(&self, tx_resp: SuiTransactionBlockResponse) -> Result<TransactionKind> {
-        let tx_time = tx_resp.timestamp_ms.context(h!())?;
-        tracing::info!("{}", tx_time);
-
-        let mut tx_kind = TransactionKind {
-            tx_type: TxType::Unknown,
-            tx_hash: tx_resp.digest,
-            event_timestamp_ms: tx_time,
-        };
-
-        let events = tx_resp.events.as_ref().context(h!())?;
-        if events.data.is_empty() {
-            let balance_changes = tx_resp.balance_changes.unwrap();
-            let transfer_event =
-                transfer::decode_transfer(balance_changes, Some(self.who)).context(h!())?;
-
-            if transfer_event.sender.eq(&transfer_event.receiver) {
-                tx_kind.tx_type = TxType::SelfTransfer(transfer_event);
-                return Ok(tx_kind);
-            } else {
-                tx_kind.tx_type = TxType::Transfer(transfer_event);
-                return Ok(tx_kind);
-            }
-        }
-
-        tracing::debug!("{}", serde_json::to_string(&tx_resp).unwrap());
-        for event in &events.data {
-            if event.type_.to_string()
-                == "0x1eabed72c53feb3805120a081dc15963c204dc8d091542592abaf7a35689b2fb::pool::SwapEvent"
-            {
-                let balance_changes = tx_resp.balance_changes.as_ref().unwrap();
-
-                assert!(
-                    balance_changes.len() == 2,
-                    "{}",
-                    serde_json::to_string(&tx_resp).unwrap()
-                );
-                let asset1_amount = balance_changes[0].amount;
-                let asset2_amount = balance_changes[1].amount;
-                let (in_token, out_token) = if asset1_amount > asset2_amount {
-                    (
-                        balance_changes[1].coin_type.to_string(),
-                        balance_changes[0].coin_type.to_string(),
-                    )
-                } else {
-                    (
-                        balance_changes[0].coin_type.to_string(),
-                        balance_changes[1].coin_type.to_string(),
-                    )
-                };
-
-                let _e = &event.parsed_json;
-                let tx_detail = TxType::Swap(Swap {
-                    pool: _e.get("pool").unwrap().as_str().unwrap().to_string(),
-                    dex: Dex::Cetus,
-                    a2b: event.parsed_json.get("atob").unwrap().as_bool().unwrap(),
-                    in_amount: _e
-                        .get("amount_in")
-                        .unwrap()
-                        .as_str()
-                        .unwrap()
-                        .parse::<i128>()
-                        .unwrap(),
-                    out_amount: _e
-                        .get("amount_out")
-                        .unwrap()
-                        .as_str()
-                        .unwrap()
-                        .parse::<i128>()?,
-                    in_token,
-                    out_token,
-                    before_sqrt_price: _e
-                        .get("before_sqrt_price")
-                        .unwrap()
-                        .as_str()
-                        .unwrap()
-                        .to_string(),
-                    after_sqrt_price: _e
-                        .get("after_sqrt_price")
-                        .unwrap()
-                        .as_str()
-                        .unwrap()
-                        .to_string(),
-                });
-                tx_kind.tx_type = tx_detail;
-                return Ok(tx_kind);
-            } else if event.type_.to_string()
-                == "0x3492c874c1e3b3e2984e8c41b589e642d4d0a5d6459e5a9cfc2d52fd7c89c267::events::AssetSwap"
-            {
-                let balance_changes = tx_resp.balance_changes.as_ref().unwrap();
-                assert!(
-                    balance_changes.len() == 2,
-                    "{}",
-                    serde_json::to_string(&tx_resp).unwrap()
-                );
-                let asset1_amount = balance_changes[0].amount;
-                let asset2_amount = balance_changes[1].amount;
-                let (in_token, out_token) = if asset1_amount > asset2_amount {
-                    (
-                        balance_changes[1].coin_type.to_string(),
-                        balance_changes[0].coin_type.to_string(),
-                    )
-                } else {
-                    (
-                        balance_changes[0].coin_type.to_string(),
-                        balance_changes[1].coin_type.to_string(),
-                    )
-                };
-
-                let _e = &event.parsed_json;
-                let tx_detail = TxType::Swap(Swap {
-                    pool: _e.get("pool_id").unwrap().as_str().unwrap().to_string(),
-                    dex: Dex::Bluefin,
-                    a2b: event.parsed_json.get("a2b").unwrap().as_bool().unwrap(),
-                    in_amount: _e
-                        .get("amount_in")
-                        .unwrap()
-                        .as_str()
-                        .unwrap()
-                        .parse::<i128>()
-                        .unwrap(),
-                    out_amount: _e
-                        .get("amount_out")
-                        .unwrap()
-                        .as_str()
-                        .unwrap()
-                        .parse::<i128>()?,
-                    in_token,
-                    out_token,
-                    before_sqrt_price: _e
-                        .get("before_sqrt_price")
-                        .unwrap()
-                        .as_str()
-                        .unwrap()
-                        .to_string(),
-                    after_sqrt_price: _e
-                        .get("after_sqrt_price")
-                        .unwrap()
-                        .as_str()
-                        .unwrap()
-                        .to_string(),
-                });
-                tx_kind.tx_type = tx_detail;
-                return Ok(tx_kind);
-            } else {
-                println!("### txs: {}", serde_json::to_string(&tx_resp).unwrap());
-            }
-        }
-
-        println!("### txs: {}", serde_json::to_string(&tx_resp).unwrap());
-
-        bail!("Unknown tx type")
-    }
-
     pub fn tx_gas(effect: SuiTransactionBlockEffects) -> BigDecimal {
         let fee = effect.gas_cost_summary();
         let fee = fee.computation_cost + fee.storage_cost - fee.storage_rebate;
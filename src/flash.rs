@@ -0,0 +1,213 @@
+use std::str::FromStr;
+use sui_sdk::rpc_types::{SuiArgument, SuiCommand, SuiEvent, SuiTransactionBlockResponse};
+use sui_types::base_types::{ObjectID, SuiAddress};
+
+use crate::{
+    CoinAmount, FlashLender, Loan, Swap,
+    deepbook::DEEPBOOK,
+    errors::EventDecodeError,
+    event_match::EventPattern,
+    events::{DecodeMode, DeepBookFlashLoanBorrowed, NaviFlashLoan, NaviFlashRepay, parse},
+    lending::NAVI,
+    liquidity::coin_type,
+    ptb,
+};
+
+const EVENTS: &[(FlashLender, &str, &str)] = &[
+    (FlashLender::DeepBook, DEEPBOOK, "vault::FlashLoanBorrowed"),
+    (FlashLender::Navi, NAVI, "flash_loan::FlashLoan"),
+    (FlashLender::Navi, NAVI, "flash_loan::FlashRepay"),
+];
+
+// Flash loans from DeepBook and Navi, recognised by their events, and Cetus
+// flash swaps, which emit a plain swap event and are recognised by the call
+// instead.
+pub struct FlashDecoder {
+    patterns: Vec<(FlashLender, EventPattern)>,
+    // packages whose `pool::flash_swap` is a Cetus flash swap, the current
+    // and the original ones as calls may go to either
+    flash_swap_packages: Vec<ObjectID>,
+}
+
+impl Default for FlashDecoder {
+    fn default() -> Self {
+        let patterns = EVENTS
+            .iter()
+            .map(|(lender, package, event)| {
+                let pattern = EventPattern::from_str(&format!("{package}::{event}"))
+                    .expect("valid flash loan event");
+                (*lender, pattern)
+            })
+            .collect();
+        Self {
+            patterns,
+            flash_swap_packages: vec![],
+        }
+    }
+}
+
+impl FlashDecoder {
+    pub fn with_flash_swap_packages(mut self, packages: &[ObjectID]) -> Self {
+        self.flash_swap_packages.extend(packages);
+        self
+    }
+
+    // The loans taken in the transaction. A Navi repayment is matched to the
+    // loan of the same asset for its fee.
+    pub fn loans(
+        &self,
+        events: &[SuiEvent],
+        mode: DecodeMode,
+    ) -> Result<Vec<Loan>, EventDecodeError> {
+        let mut loans: Vec<Loan> = vec![];
+        for event in events {
+            let Some((lender, _)) = self
+                .patterns
+                .iter()
+                .find(|(_, pattern)| pattern.matches(&event.type_).is_some())
+            else {
+                continue;
+            };
+            let protocol = format!("{lender:?}");
+            match (lender, event.type_.name.as_str()) {
+                (FlashLender::DeepBook, "FlashLoanBorrowed") => {
+                    let e: DeepBookFlashLoanBorrowed = parse(&protocol, event, mode)?;
+                    loans.push(Loan {
+                        lender: *lender,
+                        pool: Some(e.pool_id.to_string()),
+                        asset: Some(coin_type(&e.type_name.name)),
+                        amount: e.borrow_quantity,
+                        fee: Some(0),
+                    });
+                }
+                (FlashLender::Navi, "FlashLoan") => {
+                    let e: NaviFlashLoan = parse(&protocol, event, mode)?;
+                    loans.push(Loan {
+                        lender: *lender,
+                        pool: None,
+                        asset: Some(coin_type(&e.asset.name)),
+                        amount: e.amount,
+                        fee: None,
+                    });
+                }
+                (FlashLender::Navi, "FlashRepay") => {
+                    let e: NaviFlashRepay = parse(&protocol, event, mode)?;
+                    let asset = coin_type(&e.asset.name);
+                    let loan = loans.iter_mut().find(|loan| {
+                        loan.lender == FlashLender::Navi
+                            && loan.fee.is_none()
+                            && loan.asset.as_deref() == Some(asset.as_str())
+                    });
+                    if let Some(loan) = loan {
+                        loan.fee = Some(e.fee_to_supplier + e.fee_to_treasury);
+                    }
+                }
+                _ => {
+                    return Err(EventDecodeError::Invalid {
+                        protocol,
+                        event: format!("{}::{}", event.type_.module, event.type_.name),
+                        reason: "not a flash loan event".to_string(),
+                    });
+                }
+            }
+        }
+        Ok(loans)
+    }
+
+    // Pools the transaction flash swaps on. The pool is the call's second
+    // argument, after the global config.
+    pub fn flash_swap_pools(&self, tx_resp: &SuiTransactionBlockResponse) -> Vec<String> {
+        let Some(ptb) = ptb::programmable(tx_resp) else {
+            return vec![];
+        };
+        ptb.commands
+            .iter()
+            .filter_map(|command| {
+                let SuiCommand::MoveCall(call) = command else {
+                    return None;
+                };
+                if call.module != "pool"
+                    || call.function != "flash_swap"
+                    || !self.flash_swap_packages.contains(&call.package)
+                {
+                    return None;
+                }
+                let SuiArgument::Input(idx) = call.arguments.get(1)? else {
+                    return None;
+                };
+                let pool = ptb.inputs.get(*idx as usize)?.object()?;
+                Some(pool.to_string())
+            })
+            .collect()
+    }
+}
+
+// A flash swap lends the coin it pays out and is repaid in the other one,
+// its loan is read off the pool's swap event. Coin amounts fit in a `u64`,
+// swaps that report more are left out.
+pub fn flash_swap_loans(swaps: &[Swap], pools: &[String]) -> Vec<Loan> {
+    pools
+        .iter()
+        .filter_map(|pool| {
+            let swap = swaps.iter().find(|swap| swap.pool == *pool)?;
+            Some(Loan {
+                lender: FlashLender::Cetus,
+                pool: Some(pool.clone()),
+                asset: (!swap.out_token.is_empty()).then(|| swap.out_token.clone()),
+                amount: u64::try_from(swap.out_amount).ok()?,
+                fee: None,
+            })
+        })
+        .collect()
+}
+
+// What the transaction left `who` with once the loans were repaid, gas
+// included.
+pub fn net(tx_resp: &SuiTransactionBlockResponse, who: SuiAddress) -> Vec<CoinAmount> {
+    let Some(balance_changes) = &tx_resp.balance_changes else {
+        return vec![];
+    };
+    balance_changes
+        .iter()
+        .filter(|c| c.owner.get_owner_address().ok() == Some(who))
+        .map(|c| CoinAmount {
+            coin_type: c.coin_type.to_string(),
+            amount: c.amount,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::flash_swap_loans;
+    use crate::{Dex, FlashLender, Swap};
+
+    fn swap(pool: &str, out_token: &str) -> Swap {
+        Swap {
+            pool: pool.to_string(),
+            dex: Dex::Cetus,
+            a2b: true,
+            in_amount: 1_000,
+            out_amount: 2_000,
+            in_token: String::new(),
+            out_token: out_token.to_string(),
+            before_sqrt_price: "0".to_string(),
+            after_sqrt_price: "0".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_flash_swap_loans() {
+        let swaps = [swap("0xa", "0x2::sui::SUI"), swap("0xb", "")];
+        let loans = flash_swap_loans(&swaps, &["0xb".to_string(), "0xc".to_string()]);
+        assert_eq!(loans.len(), 1);
+        assert_eq!(loans[0].lender, FlashLender::Cetus);
+        assert_eq!(loans[0].pool.as_deref(), Some("0xb"));
+        assert_eq!(loans[0].asset, None);
+        assert_eq!(loans[0].amount, 2_000);
+
+        let mut huge = swap("0xb", "");
+        huge.out_amount = u64::MAX as i128 + 1;
+        assert!(flash_swap_loans(&[huge], &["0xb".to_string()]).is_empty());
+    }
+}
@@ -0,0 +1,339 @@
+use anyhow::{Context, Result};
+use mini_macro::here as h;
+use move_core_types::language_storage::StructTag;
+use serde_json::Value;
+use std::{collections::HashMap, str::FromStr};
+use sui_sdk::rpc_types::{
+    EventFilter, SuiCommand, SuiObjectDataFilter, SuiObjectDataOptions, SuiObjectResponseQuery,
+    SuiParsedData, SuiTransactionBlockEffectsAPI, SuiTransactionBlockResponse,
+};
+use sui_types::{
+    SUI_FRAMEWORK_PACKAGE_ID,
+    base_types::{ObjectID, SuiAddress},
+    digests::TransactionDigest,
+    gas_coin::GAS,
+    parse_sui_struct_tag,
+};
+
+use crate::{
+    KioskListing, NftTrade, TransactionKind, TxType,
+    errors::EventDecodeError,
+    event_match::EventPattern,
+    events::{DecodeMode, KioskItemDelisted, KioskItemEvent, parse},
+    ptb,
+    source::{TransactionSource, newest_first, search_events},
+};
+
+const PROTOCOL: &str = "Kiosk";
+// the transfer policy rules Mysten publishes: royalty, lock, personal kiosk
+// and floor price
+const KIOSK_RULES: &str = "0x434b5bd8f6a7b05fede0ff46c6e511d71ea326ed38056e3bcd681d2d7c2a7879";
+
+// Listing, delisting and buying items in Kiosks, from the events of the Sui
+// framework.
+pub struct KioskDecoder {
+    listed: EventPattern,
+    delisted: EventPattern,
+    purchased: EventPattern,
+}
+
+impl Default for KioskDecoder {
+    fn default() -> Self {
+        let pattern = |s: &str| EventPattern::from_str(s).expect("valid kiosk event");
+        Self {
+            listed: pattern("0x2::kiosk::ItemListed<_>"),
+            delisted: pattern("0x2::kiosk::ItemDelisted<_>"),
+            purchased: pattern("0x2::kiosk::ItemPurchased<_>"),
+        }
+    }
+}
+
+// Whether the transaction did nothing but buy and settle the TransferPolicy:
+// its calls go to the kiosk, transfer policy, coin and transfer modules and
+// the standard rules. Any other call, a swap say, may have spent SUI of its
+// own. Coins sent on, a marketplace fee or a tip alike, are not told apart.
+fn only_purchase(tx_resp: &SuiTransactionBlockResponse) -> bool {
+    let Some(ptb) = ptb::programmable(tx_resp) else {
+        return false;
+    };
+    let Ok(rules) = ObjectID::from_str(KIOSK_RULES) else {
+        return false;
+    };
+    ptb.commands.iter().all(|command| match command {
+        SuiCommand::MoveCall(call) if call.package == SUI_FRAMEWORK_PACKAGE_ID => matches!(
+            call.module.as_str(),
+            "kiosk" | "transfer_policy" | "coin" | "transfer"
+        ),
+        SuiCommand::MoveCall(call) => call.package == rules,
+        _ => true,
+    })
+}
+
+// What `buyer` paid for an item besides its price, as (royalty, marketplace
+// fee). SUI sent to other addresses is the marketplace's cut; the rest went
+// into balances held by objects, the TransferPolicy's.
+fn extra_payments(
+    tx_resp: &SuiTransactionBlockResponse,
+    buyer: SuiAddress,
+    price: u64,
+) -> Option<(u64, u64)> {
+    let balance_changes = tx_resp.balance_changes.as_ref()?;
+    let gas = tx_resp.effects.as_ref()?.gas_cost_summary().net_gas_usage();
+    let sui = GAS::type_tag();
+
+    let mut spent = -(gas as i128);
+    let mut fee = 0;
+    for change in balance_changes.iter().filter(|c| c.coin_type == sui) {
+        match change.owner.get_owner_address() {
+            Ok(owner) if owner == buyer => spent -= change.amount,
+            Ok(_) if change.amount > 0 => fee += change.amount,
+            _ => {}
+        }
+    }
+    let royalty = spent - price as i128 - fee;
+    Some((u64::try_from(royalty).ok()?, u64::try_from(fee).ok()?))
+}
+
+impl KioskDecoder {
+    pub fn decode(
+        &self,
+        tx_resp: &SuiTransactionBlockResponse,
+        mode: DecodeMode,
+    ) -> Result<Option<TxType>, EventDecodeError> {
+        let Some(events) = &tx_resp.events else {
+            return Ok(None);
+        };
+
+        let mut tx_types = vec![];
+        for event in &events.data {
+            let item_type = |params: Vec<_>| params.first().map(ToString::to_string);
+            if let Some(params) = self.listed.matches(&event.type_) {
+                let e: KioskItemEvent = parse(PROTOCOL, event, mode)?;
+                tx_types.push(TxType::NftListed(KioskListing {
+                    kiosk: e.kiosk.to_string(),
+                    item: e.id.to_string(),
+                    item_type: item_type(params).unwrap_or_default(),
+                    price: Some(e.price),
+                    seller: event.sender.to_string(),
+                }));
+            } else if let Some(params) = self.delisted.matches(&event.type_) {
+                let e: KioskItemDelisted = parse(PROTOCOL, event, mode)?;
+                tx_types.push(TxType::NftDelisted(KioskListing {
+                    kiosk: e.kiosk.to_string(),
+                    item: e.id.to_string(),
+                    item_type: item_type(params).unwrap_or_default(),
+                    price: None,
+                    seller: event.sender.to_string(),
+                }));
+            } else if let Some(params) = self.purchased.matches(&event.type_) {
+                let e: KioskItemEvent = parse(PROTOCOL, event, mode)?;
+                tx_types.push(TxType::NftTrade(NftTrade {
+                    kiosk: e.kiosk.to_string(),
+                    item: e.id.to_string(),
+                    item_type: item_type(params).unwrap_or_default(),
+                    price: e.price,
+                    royalty: None,
+                    marketplace_fee: None,
+                    buyer: event.sender.to_string(),
+                    seller: None,
+                }));
+            }
+        }
+
+        // a sweep pays all royalties and fees at once, they can't be told
+        // apart per item, and other calls muddle the buyer's SUI
+        let mut trades = tx_types.iter_mut().filter_map(|t| match t {
+            TxType::NftTrade(trade) => Some(trade),
+            _ => None,
+        });
+        let only_purchase = only_purchase(tx_resp);
+        if let (Some(trade), None) = (trades.next(), trades.next()) {
+            let buyer = SuiAddress::from_str(&trade.buyer)
+                .ok()
+                .filter(|_| only_purchase);
+            if let Some((royalty, fee)) =
+                buyer.and_then(|buyer| extra_payments(tx_resp, buyer, trade.price))
+            {
+                trade.royalty = Some(royalty);
+                trade.marketplace_fee = Some(fee);
+            }
+        }
+
+        Ok(match tx_types.len() {
+            0 => None,
+            1 => tx_types.pop(),
+            _ => Some(TxType::Batch(tx_types)),
+        })
+    }
+}
+
+fn trades_mut(tx_type: &mut TxType) -> Vec<&mut NftTrade> {
+    match tx_type {
+        TxType::NftTrade(trade) => vec![trade],
+        TxType::Batch(tx_types) => tx_types.iter_mut().flat_map(trades_mut).collect(),
+        _ => vec![],
+    }
+}
+
+// The kiosks `who` owns, the ones its `KioskOwnerCap`s are for. Personal
+// kiosks wrap their cap and are not found.
+pub async fn owned_kiosks(
+    source: &dyn TransactionSource,
+    who: SuiAddress,
+) -> Result<Vec<ObjectID>> {
+    let cap = parse_sui_struct_tag("0x2::kiosk::KioskOwnerCap").context(h!())?;
+    let query = SuiObjectResponseQuery::new(
+        Some(SuiObjectDataFilter::StructType(cap.clone())),
+        Some(SuiObjectDataOptions::new().with_type().with_content()),
+    );
+
+    let mut kiosks = vec![];
+    let mut cursor = None;
+    loop {
+        let page = source
+            .get_owned_objects(who, &query, cursor, None)
+            .await
+            .context(h!())?;
+        for data in page.data.iter().filter_map(|object| object.data.as_ref()) {
+            let tag = data
+                .object_type()
+                .ok()
+                .and_then(|t| StructTag::try_from(t).ok());
+            if tag.as_ref() != Some(&cap) {
+                continue;
+            }
+            let Some(SuiParsedData::MoveObject(content)) = &data.content else {
+                continue;
+            };
+            let fields = content.fields.clone().to_json_value();
+            if let Some(kiosk) = fields.get("for").and_then(Value::as_str) {
+                kiosks.push(ObjectID::from_str(kiosk).context(h!())?);
+            }
+        }
+        if !page.has_next_page {
+            break;
+        }
+        cursor = page.next_cursor;
+    }
+    Ok(kiosks)
+}
+
+// Transactions buying items out of `kiosks`, newest first. The buyer sends
+// them, so the seller's own transaction history doesn't have them.
+pub async fn find_sales(
+    source: &dyn TransactionSource,
+    kiosks: &[ObjectID],
+    since_ms: Option<u64>,
+) -> Result<Vec<TransactionDigest>> {
+    let purchased = EventPattern::from_str("0x2::kiosk::ItemPurchased<_>").context(h!())?;
+    let mut found = vec![];
+    for kiosk in kiosks {
+        let query = EventFilter::MoveEventField {
+            path: "/kiosk".to_string(),
+            value: Value::String(kiosk.to_string()),
+        };
+        found.extend(
+            search_events(source, &query, since_ms, |event| {
+                if purchased.matches(&event.type_).is_none() {
+                    return Ok(false);
+                }
+                let e: KioskItemEvent = parse(PROTOCOL, event, DecodeMode::Json)?;
+                Ok(e.kiosk == *kiosk)
+            })
+            .await
+            .context(h!())?,
+        );
+    }
+    Ok(newest_first(found))
+}
+
+// Sets `seller` on the trades in `txs` bought out of one of its `kiosks`.
+pub fn set_seller(txs: &mut [TransactionKind], kiosks: &[ObjectID], seller: SuiAddress) {
+    let kiosks = kiosks.iter().map(ToString::to_string).collect::<Vec<_>>();
+    for tx in txs {
+        for trade in trades_mut(&mut tx.tx_type) {
+            if kiosks.contains(&trade.kiosk) {
+                trade.seller = Some(seller.to_string());
+            }
+        }
+    }
+}
+
+// Fills in the sellers of the trades in `txs` with the owners of the kiosks
+// bought from. That is the owner the kiosk has now, not at the time of the
+// trade: a kiosk whose `KioskOwnerCap` changed hands since reports its new
+// owner.
+pub async fn resolve_sellers(
+    source: &dyn TransactionSource,
+    txs: &mut [TransactionKind],
+) -> Result<()> {
+    let options = SuiObjectDataOptions::new().with_content();
+    let mut owners: HashMap<String, Option<String>> = HashMap::new();
+    for tx in txs {
+        for trade in trades_mut(&mut tx.tx_type) {
+            if trade.seller.is_some() {
+                continue;
+            }
+            if !owners.contains_key(&trade.kiosk) {
+                let kiosk = source
+                    .get_object(trade.kiosk.parse().context(h!())?, &options)
+                    .await
+                    .context(h!())?;
+                let owner = match kiosk.data.and_then(|data| data.content) {
+                    Some(SuiParsedData::MoveObject(content)) => content
+                        .fields
+                        .to_json_value()
+                        .get("owner")
+                        .and_then(|owner| owner.as_str())
+                        .map(str::to_string),
+                    _ => None,
+                };
+                owners.insert(trade.kiosk.clone(), owner);
+            }
+            trade.seller = owners[&trade.kiosk].clone();
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+    use sui_sdk::rpc_types::SuiTransactionBlockResponse;
+    use sui_types::base_types::SuiAddress;
+
+    use super::{extra_payments, only_purchase};
+
+    #[test]
+    fn test_extra_payments() {
+        let tx_resp: SuiTransactionBlockResponse =
+            serde_json::from_str(include_str!("../fixtures/decode/kiosk_purchase.tx.json"))
+                .unwrap();
+        let buyer = SuiAddress::from_str(
+            "0x62310ee294108c13f3496ce6895f12f3c2cf3994c74c2911501535e23ccc74ff",
+        )
+        .unwrap();
+        assert_eq!(
+            extra_payments(&tx_resp, buyer, 10_000_000_000),
+            Some((500_000_000, 200_000_000))
+        );
+        // a price above what the buyer paid doesn't fit its balance changes
+        assert_eq!(extra_payments(&tx_resp, buyer, 11_000_000_000), None);
+    }
+
+    #[test]
+    fn test_only_purchase() {
+        let json = include_str!("../fixtures/decode/kiosk_purchase.tx.json");
+        let tx_resp: SuiTransactionBlockResponse = serde_json::from_str(json).unwrap();
+        assert!(only_purchase(&tx_resp));
+
+        // the royalty paid through a router instead, which may swap as well
+        let router = "0x1eabed72c53feb3805120a081dc15963c204dc8d091542592abaf7a35689b2fb";
+        let json = json.replace(
+            "0x434b5bd8f6a7b05fede0ff46c6e511d71ea326ed38056e3bcd681d2d7c2a7879",
+            router,
+        );
+        let tx_resp: SuiTransactionBlockResponse = serde_json::from_str(&json).unwrap();
+        assert!(!only_purchase(&tx_resp));
+    }
+}
@@ -0,0 +1,545 @@
+use std::str::FromStr;
+use sui_sdk::rpc_types::SuiEvent;
+
+use crate::{
+    Lending, LendingProtocol, TxType,
+    errors::EventDecodeError,
+    event_match::EventPattern,
+    events::{
+        DecodeMode, NaviLendingEvent, NaviWithdrawEvent, ScallopBorrowEvent, ScallopBorrowEventV2,
+        ScallopCollateralDepositEvent, ScallopCollateralWithdrawEvent, ScallopMintEvent,
+        ScallopRedeemEvent, ScallopRepayEvent, SuilendBorrowEvent, SuilendClaimRewardEvent,
+        SuilendCollateralEvent, SuilendMintEvent, SuilendRedeemEvent, SuilendRepayEvent, parse,
+    },
+    liquidity::coin_type,
+    pricing::normalize_coin_type,
+};
+
+pub(crate) const SCALLOP: &str =
+    "0xefe8b36d5b2e43728cc323298626b83177803521d195cfb11e15b910e892fddf";
+pub(crate) const NAVI: &str = "0xd899cf7d2b5db716bd2cf55599fb0d5ee38a3061e7b6bb6eebf73fa5bc4c81ca";
+pub(crate) const SUILEND: &str =
+    "0xf95b06141ed4a174f239417323bde3f209b972f5930d8521ea38a52aff3a6ddf";
+
+// Events of each market, as emitted by its original package.
+const EVENTS: &[(LendingProtocol, &str, &[&str])] = &[
+    (
+        LendingProtocol::Scallop,
+        SCALLOP,
+        &[
+            "deposit_collateral::CollateralDepositEvent",
+            "withdraw_collateral::CollateralWithdrawEvent",
+            "borrow::BorrowEvent",
+            "borrow::BorrowEventV2",
+            "repay::RepayEvent",
+            "mint::MintEvent",
+            "redeem::RedeemEvent",
+        ],
+    ),
+    (
+        LendingProtocol::Navi,
+        NAVI,
+        &[
+            "lending::DepositEvent",
+            "lending::WithdrawEvent",
+            "lending::BorrowEvent",
+            "lending::RepayEvent",
+        ],
+    ),
+    (
+        LendingProtocol::Suilend,
+        SUILEND,
+        &[
+            "lending_market::MintEvent",
+            "lending_market::RedeemEvent",
+            "lending_market::DepositEvent",
+            "lending_market::WithdrawEvent",
+            "lending_market::BorrowEvent",
+            "lending_market::RepayEvent",
+            "lending_market::ClaimRewardEvent",
+        ],
+    ),
+];
+
+// Navi events name the asset by its reserve index. Reserves missing here
+// fail to decode rather than leave the asset out.
+const NAVI_RESERVES: &[(u8, &str)] = &[
+    (0, "0x2::sui::SUI"),
+    (
+        1,
+        "0x5d4b302506645c37ff133b98c4b50a5ae14841659738d6d733d59d0d217a93bf::coin::COIN",
+    ),
+    (
+        2,
+        "0xc060006111016b8a020ad5b33834984a437aaa7d3c74c18e09a95d48aceab08c::coin::COIN",
+    ),
+    (
+        3,
+        "0xaf8cd5edc19c4512f4259f0bee101a40d41ebed738ade5874359610ef8eeced5::coin::COIN",
+    ),
+    (
+        4,
+        "0x6864a6f921804860930db6ddbe2e16acdf8504495ea7481637a1c8b9a8fe54b::cetus::CETUS",
+    ),
+    (
+        5,
+        "0x549e8b69270defbfafd4f94e17ec44cdbdd99820b33bda2278dea3b9a32d3f55::cert::CERT",
+    ),
+    (
+        6,
+        "0xbde4ba4c2e274a60ce15c1cfff9e5c42e41654ac8b6d906a57efa4bd3c29f47d::hasui::HASUI",
+    ),
+    (
+        7,
+        "0xa99b8952d4f7d947ea77fe0ecdcc9e5fc0bcab2841d6e2a5aa00c3044e5544b5::navx::NAVX",
+    ),
+    (
+        10,
+        "0xdba34672e30cb065b1f93e3ab55318768fd6fef66c15942c9f7cb846e2f900e7::usdc::USDC",
+    ),
+];
+
+pub(crate) fn navi_asset(
+    event: &SuiEvent,
+    field: &str,
+    reserve: u8,
+) -> Result<String, EventDecodeError> {
+    NAVI_RESERVES
+        .iter()
+        .find(|(id, _)| *id == reserve)
+        .map(|(_, coin)| normalize_coin_type(coin))
+        .ok_or_else(|| EventDecodeError::InvalidField {
+            protocol: format!("{:?}", LendingProtocol::Navi),
+            event: format!("{}::{}", event.type_.module, event.type_.name),
+            field: field.to_string(),
+            reason: format!("unknown reserve {reserve}"),
+        })
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LendingAction {
+    Deposit,
+    Withdraw,
+    Borrow,
+    Repay,
+    ClaimRewards,
+}
+
+impl LendingAction {
+    fn into_tx_type(self, lending: Lending) -> TxType {
+        match self {
+            LendingAction::Deposit => TxType::Deposit(lending),
+            LendingAction::Withdraw => TxType::Withdraw(lending),
+            LendingAction::Borrow => TxType::Borrow(lending),
+            LendingAction::Repay => TxType::Repay(lending),
+            LendingAction::ClaimRewards => TxType::ClaimRewards(lending),
+        }
+    }
+}
+
+// One decoded event. Suilend reports collateral moves in cTokens, next to the
+// mint or redeem that converts them, and `ctokens` marks such amounts.
+#[derive(Debug, Clone)]
+pub struct LendingEvent {
+    pub action: LendingAction,
+    pub lending: Lending,
+    pub ctokens: bool,
+}
+
+fn lending(
+    protocol: LendingProtocol,
+    market: Option<String>,
+    account: Option<String>,
+    asset: Option<String>,
+    amount: u64,
+) -> Lending {
+    Lending {
+        protocol,
+        market,
+        account,
+        asset,
+        amount,
+    }
+}
+
+pub struct LendingDecoder {
+    patterns: Vec<(LendingProtocol, EventPattern)>,
+}
+
+impl Default for LendingDecoder {
+    fn default() -> Self {
+        let patterns = EVENTS
+            .iter()
+            .flat_map(|(protocol, package, events)| {
+                events.iter().map(move |event| {
+                    let pattern = EventPattern::from_str(&format!("{package}::{event}"))
+                        .expect("valid lending event");
+                    (*protocol, pattern)
+                })
+            })
+            .collect();
+        Self { patterns }
+    }
+}
+
+impl LendingDecoder {
+    pub fn decode(
+        &self,
+        events: &[SuiEvent],
+        mode: DecodeMode,
+    ) -> Result<Option<TxType>, EventDecodeError> {
+        let decoded = events
+            .iter()
+            .filter_map(|event| {
+                let (protocol, _) = self
+                    .patterns
+                    .iter()
+                    .find(|(_, pattern)| pattern.matches(&event.type_).is_some())?;
+                Some(decode_lending_event(*protocol, event, mode))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(fold(decoded))
+    }
+}
+
+pub fn decode_lending_event(
+    protocol: LendingProtocol,
+    event: &SuiEvent,
+    mode: DecodeMode,
+) -> Result<LendingEvent, EventDecodeError> {
+    let name = format!("{protocol:?}");
+    let p = name.as_str();
+    let plain = |action, lending| LendingEvent {
+        action,
+        lending,
+        ctokens: false,
+    };
+
+    let event_name = event.type_.name.as_str();
+    let decoded = match (protocol, event_name) {
+        (LendingProtocol::Scallop, "CollateralDepositEvent") => {
+            let e: ScallopCollateralDepositEvent = parse(p, event, mode)?;
+            plain(
+                LendingAction::Deposit,
+                lending(
+                    protocol,
+                    None,
+                    Some(e.obligation.to_string()),
+                    Some(coin_type(&e.deposit_asset.name)),
+                    e.deposit_amount,
+                ),
+            )
+        }
+        (LendingProtocol::Scallop, "CollateralWithdrawEvent") => {
+            let e: ScallopCollateralWithdrawEvent = parse(p, event, mode)?;
+            plain(
+                LendingAction::Withdraw,
+                lending(
+                    protocol,
+                    None,
+                    Some(e.obligation.to_string()),
+                    Some(coin_type(&e.withdraw_asset.name)),
+                    e.withdraw_amount,
+                ),
+            )
+        }
+        (LendingProtocol::Scallop, "BorrowEvent") => {
+            let e: ScallopBorrowEvent = parse(p, event, mode)?;
+            plain(
+                LendingAction::Borrow,
+                lending(
+                    protocol,
+                    None,
+                    Some(e.obligation.to_string()),
+                    Some(coin_type(&e.asset.name)),
+                    e.amount,
+                ),
+            )
+        }
+        (LendingProtocol::Scallop, "BorrowEventV2") => {
+            let e: ScallopBorrowEventV2 = parse(p, event, mode)?;
+            plain(
+                LendingAction::Borrow,
+                lending(
+                    protocol,
+                    None,
+                    Some(e.obligation.to_string()),
+                    Some(coin_type(&e.asset.name)),
+                    e.amount,
+                ),
+            )
+        }
+        (LendingProtocol::Scallop, "RepayEvent") => {
+            let e: ScallopRepayEvent = parse(p, event, mode)?;
+            plain(
+                LendingAction::Repay,
+                lending(
+                    protocol,
+                    None,
+                    Some(e.obligation.to_string()),
+                    Some(coin_type(&e.asset.name)),
+                    e.amount,
+                ),
+            )
+        }
+        // supplying and redeeming sCoins happen outside of any obligation
+        (LendingProtocol::Scallop, "MintEvent") => {
+            let e: ScallopMintEvent = parse(p, event, mode)?;
+            plain(
+                LendingAction::Deposit,
+                lending(
+                    protocol,
+                    None,
+                    None,
+                    Some(coin_type(&e.deposit_asset.name)),
+                    e.deposit_amount,
+                ),
+            )
+        }
+        (LendingProtocol::Scallop, "RedeemEvent") => {
+            let e: ScallopRedeemEvent = parse(p, event, mode)?;
+            plain(
+                LendingAction::Withdraw,
+                lending(
+                    protocol,
+                    None,
+                    None,
+                    Some(coin_type(&e.withdraw_asset.name)),
+                    e.withdraw_amount,
+                ),
+            )
+        }
+        (LendingProtocol::Navi, "DepositEvent" | "BorrowEvent" | "RepayEvent") => {
+            let e: NaviLendingEvent = parse(p, event, mode)?;
+            let action = match event_name {
+                "DepositEvent" => LendingAction::Deposit,
+                "BorrowEvent" => LendingAction::Borrow,
+                _ => LendingAction::Repay,
+            };
+            plain(
+                action,
+                lending(
+                    protocol,
+                    None,
+                    Some(e.sender.to_string()),
+                    Some(navi_asset(event, "reserve", e.reserve)?),
+                    e.amount,
+                ),
+            )
+        }
+        (LendingProtocol::Navi, "WithdrawEvent") => {
+            let e: NaviWithdrawEvent = parse(p, event, mode)?;
+            plain(
+                LendingAction::Withdraw,
+                lending(
+                    protocol,
+                    None,
+                    Some(e.sender.to_string()),
+                    Some(navi_asset(event, "reserve", e.reserve)?),
+                    e.amount,
+                ),
+            )
+        }
+        (LendingProtocol::Suilend, "MintEvent") => {
+            let e: SuilendMintEvent = parse(p, event, mode)?;
+            plain(
+                LendingAction::Deposit,
+                lending(
+                    protocol,
+                    Some(e.lending_market_id.to_string()),
+                    None,
+                    Some(coin_type(&e.coin_type.name)),
+                    e.liquidity_amount,
+                ),
+            )
+        }
+        (LendingProtocol::Suilend, "RedeemEvent") => {
+            let e: SuilendRedeemEvent = parse(p, event, mode)?;
+            plain(
+                LendingAction::Withdraw,
+                lending(
+                    protocol,
+                    Some(e.lending_market_id.to_string()),
+                    None,
+                    Some(coin_type(&e.coin_type.name)),
+                    e.liquidity_amount,
+                ),
+            )
+        }
+        (LendingProtocol::Suilend, "DepositEvent" | "WithdrawEvent") => {
+            let e: SuilendCollateralEvent = parse(p, event, mode)?;
+            let action = if event_name == "DepositEvent" {
+                LendingAction::Deposit
+            } else {
+                LendingAction::Withdraw
+            };
+            LendingEvent {
+                action,
+                lending: lending(
+                    protocol,
+                    Some(e.lending_market_id.to_string()),
+                    Some(e.obligation_id.to_string()),
+                    Some(coin_type(&e.coin_type.name)),
+                    e.ctoken_amount,
+                ),
+                ctokens: true,
+            }
+        }
+        (LendingProtocol::Suilend, "BorrowEvent") => {
+            let e: SuilendBorrowEvent = parse(p, event, mode)?;
+            plain(
+                LendingAction::Borrow,
+                lending(
+                    protocol,
+                    Some(e.lending_market_id.to_string()),
+                    Some(e.obligation_id.to_string()),
+                    Some(coin_type(&e.coin_type.name)),
+                    e.liquidity_amount,
+                ),
+            )
+        }
+        (LendingProtocol::Suilend, "RepayEvent") => {
+            let e: SuilendRepayEvent = parse(p, event, mode)?;
+            plain(
+                LendingAction::Repay,
+                lending(
+                    protocol,
+                    Some(e.lending_market_id.to_string()),
+                    Some(e.obligation_id.to_string()),
+                    Some(coin_type(&e.coin_type.name)),
+                    e.liquidity_amount,
+                ),
+            )
+        }
+        (LendingProtocol::Suilend, "ClaimRewardEvent") => {
+            let e: SuilendClaimRewardEvent = parse(p, event, mode)?;
+            plain(
+                LendingAction::ClaimRewards,
+                lending(
+                    protocol,
+                    Some(e.lending_market_id.to_string()),
+                    Some(e.obligation_id.to_string()),
+                    Some(coin_type(&e.coin_type.name)),
+                    e.liquidity_amount,
+                ),
+            )
+        }
+        _ => {
+            return Err(EventDecodeError::Invalid {
+                protocol: name,
+                event: format!("{}::{}", event.type_.module, event.type_.name),
+                reason: "not a lending event".to_string(),
+            });
+        }
+    };
+    Ok(decoded)
+}
+
+// The cTokens moved by a Suilend deposit or withdrawal, next to the mint or
+// redeem of the same asset.
+fn pairs_with(a: &LendingEvent, b: &LendingEvent) -> bool {
+    a.ctokens != b.ctokens
+        && a.action == b.action
+        && a.lending.protocol == b.lending.protocol
+        && a.lending.market == b.lending.market
+        && a.lending.asset == b.lending.asset
+}
+
+// Folds the lending events of one transaction into a `TxType`, in the order
+// they were emitted.
+pub fn fold(events: Vec<LendingEvent>) -> Option<TxType> {
+    let mut folded: Vec<LendingEvent> = vec![];
+    for event in events {
+        match folded.iter_mut().find(|e| pairs_with(e, &event)) {
+            Some(e) => {
+                e.lending.account = e.lending.account.take().or(event.lending.account);
+                if e.ctokens {
+                    e.lending.amount = event.lending.amount;
+                    e.ctokens = false;
+                }
+            }
+            None => folded.push(event),
+        }
+    }
+
+    let mut tx_types = folded
+        .into_iter()
+        .map(|e| e.action.into_tx_type(e.lending))
+        .collect::<Vec<_>>();
+    match tx_types.len() {
+        0 => None,
+        1 => tx_types.pop(),
+        _ => Some(TxType::Batch(tx_types)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use sui_sdk::rpc_types::SuiEvent;
+
+    use super::{LendingAction, LendingEvent, NAVI, fold, lending, navi_asset};
+    use crate::{LendingProtocol, TxType, errors::EventDecodeError};
+
+    fn suilend(
+        action: LendingAction,
+        account: Option<&str>,
+        amount: u64,
+        ctokens: bool,
+    ) -> LendingEvent {
+        LendingEvent {
+            action,
+            lending: lending(
+                LendingProtocol::Suilend,
+                Some("0x1".to_string()),
+                account.map(str::to_string),
+                Some("0x2::sui::SUI".to_string()),
+                amount,
+            ),
+            ctokens,
+        }
+    }
+
+    #[test]
+    fn test_fold_suilend_deposit() {
+        let tx_type = fold(vec![
+            suilend(LendingAction::Deposit, None, 1_000, false),
+            suilend(LendingAction::Deposit, Some("0xob"), 980, true),
+            suilend(LendingAction::Borrow, Some("0xob"), 300, false),
+        ]);
+        let Some(TxType::Batch(tx_types)) = tx_type else {
+            panic!("unexpected {tx_type:?}");
+        };
+        let TxType::Deposit(deposit) = &tx_types[0] else {
+            panic!("unexpected {:?}", tx_types[0]);
+        };
+        assert_eq!(deposit.amount, 1_000);
+        assert_eq!(deposit.account.as_deref(), Some("0xob"));
+        assert!(matches!(tx_types[1], TxType::Borrow(_)));
+
+        assert!(fold(vec![]).is_none());
+    }
+
+    #[test]
+    fn test_navi_asset() {
+        let event: SuiEvent = serde_json::from_value(json!({
+            "id": {
+                "txDigest": "CRNzhTtGj6R7JS1wWfjq5H6Xy84dQTVroDfg2dnp2nSu",
+                "eventSeq": "0"
+            },
+            "packageId": NAVI,
+            "transactionModule": "lending",
+            "sender": "0x62310ee294108c13f3496ce6895f12f3c2cf3994c74c2911501535e23ccc74ff",
+            "type": format!("{NAVI}::lending::DepositEvent"),
+            "parsedJson": {},
+            "bcsEncoding": "base64",
+            "bcs": ""
+        }))
+        .unwrap();
+        assert_eq!(navi_asset(&event, "reserve", 0).unwrap(), "0x2::sui::SUI");
+        assert!(matches!(
+            navi_asset(&event, "reserve", 200),
+            Err(EventDecodeError::InvalidField { field, .. }) if field == "reserve"
+        ));
+    }
+}
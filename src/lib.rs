@@ -1,8 +1,36 @@
+pub mod arbitrage;
+pub mod bridge;
+pub mod config;
+pub mod decode;
+pub mod deepbook;
 pub mod errors;
+pub mod event_match;
+pub mod events;
+#[cfg(any(test, feature = "fake-rpc"))]
+pub mod fake_rpc;
 pub mod fetcher;
+pub mod flash;
+pub mod kiosk;
+pub mod lending;
+pub mod liquidation;
+pub mod liquidity;
+pub mod lst;
+pub mod network;
 pub mod objects;
+pub mod orders;
+pub mod position;
+pub mod pricing;
+pub mod ptb;
+pub mod replay;
+pub mod report;
+pub mod rpc;
+pub mod sink;
+pub mod source;
+pub mod staking;
 pub mod transfer;
 
+use alloy_primitives::{Address, B256, U256};
+use bigdecimal::BigDecimal;
 use serde::{Deserialize, Serialize};
 use sui_types::digests::TransactionDigest;
 
@@ -20,6 +48,47 @@ pub enum TxType {
     Transfer(TransferEvent),
     SelfTransfer(TransferEvent),
     Swap(Swap),
+    CoinManagement(CoinManagement),
+    ObjectTransfer(ObjectTransfer),
+    ObjectChanges(ObjectChanges),
+    MoveCall(MoveCall),
+    OpenPosition(Liquidity),
+    ClosePosition(Liquidity),
+    AddLiquidity(Liquidity),
+    RemoveLiquidity(Liquidity),
+    CollectFee(Liquidity),
+    CollectReward(Liquidity),
+    // several positions touched by one transaction, e.g. closing one and
+    // opening another
+    Rebalance(Vec<TxType>),
+    Stake(Stake),
+    Unstake(Unstake),
+    LiquidStake(LiquidStake),
+    LiquidUnstake(LiquidUnstake),
+    Deposit(Lending),
+    Withdraw(Lending),
+    Borrow(Lending),
+    Repay(Lending),
+    ClaimRewards(Lending),
+    // several actions in one transaction, e.g. depositing collateral and
+    // borrowing against it, or an order that partly fills and rests on the book
+    Batch(Vec<TxType>),
+    Liquidation(Liquidation),
+    OrderPlaced(Order),
+    OrderCancelled(Order),
+    OrderFilled(Fill),
+    BalanceManagerDeposit(BalanceManagerTransfer),
+    BalanceManagerWithdraw(BalanceManagerTransfer),
+    FlashLoan(FlashLoan),
+    KeeperOrderCreated(KeeperOrder),
+    KeeperOrderFilled(KeeperFill),
+    KeeperOrderCancelled(OrderPayout),
+    KeeperOrderClaimed(OrderPayout),
+    NftListed(KioskListing),
+    NftDelisted(KioskListing),
+    NftTrade(NftTrade),
+    BridgeOut(BridgeTransfer),
+    BridgeIn(BridgeTransfer),
     Unknown,
 }
 
@@ -37,6 +106,396 @@ pub struct Swap {
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CoinManagement {
+    pub owner: String,
+    pub splits: usize,
+    pub merges: usize,
+}
+
+// Objects a PTB transferred, when the response has no object changes to
+// decode `ObjectChanges` from.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ObjectTransfer {
+    pub sender: String,
+    pub recipients: Vec<String>,
+    pub objects: usize,
+}
+
+// NFTs and other non-coin objects a transaction created, moved, wrapped or
+// deleted, read from its object changes.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ObjectChanges {
+    pub sender: String,
+    pub changes: Vec<ObjectMovement>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ObjectMovement {
+    pub object_id: String,
+    pub object_type: String,
+    // the version after the transaction
+    pub version: u64,
+    pub kind: ObjectChangeKind,
+    // an address or object id, `shared` or `immutable`. Only known for
+    // objects the sender owned.
+    pub previous_owner: Option<String>,
+    // none once wrapped or deleted
+    pub new_owner: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum ObjectChangeKind {
+    Created,
+    Transferred,
+    Mutated,
+    Wrapped,
+    Deleted,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MoveCall {
+    pub protocol: String,
+    pub package: String,
+    pub module: String,
+    pub function: String,
+}
+
+// What one transaction did to a CLMM position. All the position's events in
+// the transaction are folded in, so closing a position also reports the
+// liquidity removed and the fees and rewards collected on the way.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Liquidity {
+    pub dex: Dex,
+    pub pool: String,
+    pub position: String,
+    pub tick_lower: Option<i32>,
+    pub tick_upper: Option<i32>,
+    // positive when liquidity was added, negative when it was removed
+    pub liquidity_delta: i128,
+    // tokens put into and taken out of the position by liquidity changes,
+    // apart as a transaction can do both on one position
+    pub added_a: u64,
+    pub added_b: u64,
+    pub removed_a: u64,
+    pub removed_b: u64,
+    pub fee_a: u64,
+    pub fee_b: u64,
+    pub rewards: Vec<Reward>,
+    // price of the pool after the change, when the events report it
+    pub sqrt_price: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Reward {
+    // not every reward event names its coin
+    pub coin_type: Option<String>,
+    pub amount: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Stake {
+    pub validator: String,
+    pub staker: String,
+    // the validator's staking pool
+    pub pool_id: String,
+    pub principal: u64,
+    // the `StakedSui` object the stake is held in
+    pub staked_sui: Option<String>,
+    // the stake earns rewards from this epoch on
+    pub activation_epoch: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Unstake {
+    pub validator: String,
+    pub staker: String,
+    pub pool_id: String,
+    pub principal: u64,
+    pub rewards: u64,
+    pub staked_sui: Option<String>,
+    pub activation_epoch: u64,
+    pub unstaking_epoch: u64,
+}
+
+// SUI deposited into a liquid staking protocol for its token.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LiquidStake {
+    pub protocol: Lst,
+    pub staker: String,
+    pub lst_type: String,
+    pub sui_amount: u64,
+    pub lst_amount: u64,
+    // SUI per LST token, fees included
+    pub exchange_rate: Option<BigDecimal>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LiquidUnstake {
+    pub protocol: Lst,
+    pub staker: String,
+    pub lst_type: String,
+    pub lst_amount: u64,
+    // unknown until a delayed redemption is paid out
+    pub sui_amount: Option<u64>,
+    pub exchange_rate: Option<BigDecimal>,
+    pub redemption: Option<Redemption>,
+}
+
+// An unstake paid out after the transaction rather than in it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Redemption {
+    // the object the SUI is claimed with, when the protocol hands one out
+    pub ticket: Option<String>,
+    pub claimable_epoch: Option<u64>,
+    pub claimable_at_ms: Option<u64>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum Lst {
+    // afSUI
+    Aftermath,
+    // haSUI
+    Haedal,
+    // vSUI
+    Volo,
+    // sSUI and the other SpringSui LSTs
+    SpringSui,
+}
+
+// One action on a lending market. `amount` is in the underlying asset,
+// not in the protocol's receipt tokens.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Lending {
+    pub protocol: LendingProtocol,
+    // the lending market, for protocols that run several
+    pub market: Option<String>,
+    // the obligation (Scallop, Suilend) or the account (Navi) acted on, the
+    // spool account for Scallop's staking rewards
+    pub account: Option<String>,
+    pub asset: Option<String>,
+    pub amount: u64,
+}
+
+// A lending position liquidated by someone else. Amounts are in the
+// underlying assets, except Suilend's collateral which it reports in cTokens.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Liquidation {
+    pub protocol: LendingProtocol,
+    pub market: Option<String>,
+    // the liquidated obligation (Scallop, Suilend) or account (Navi)
+    pub account: String,
+    pub liquidator: String,
+    pub debt_asset: Option<String>,
+    pub repaid: u64,
+    pub collateral_asset: Option<String>,
+    pub seized: u64,
+    // collateral taken on top of the repaid debt's worth, when the protocol
+    // reports it or the prices it liquidated at tell
+    pub penalty: Option<u64>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum LendingProtocol {
+    Scallop,
+    Navi,
+    Suilend,
+}
+
+// A DeepBook limit order. Prices are in quote units per base unit, scaled by
+// 1e9, and quantities in base units.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Order {
+    pub pool: String,
+    pub balance_manager: String,
+    pub trader: String,
+    // u128, too wide for most JSON readers
+    pub order_id: String,
+    pub client_order_id: u64,
+    pub is_bid: bool,
+    pub price: u64,
+    // placed, or cancelled for a cancellation
+    pub quantity: u64,
+    // only set when placing
+    pub expire_timestamp_ms: Option<u64>,
+}
+
+// One match between a taker order and a resting maker order, from the side
+// of the wallet's balance manager.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Fill {
+    pub pool: String,
+    pub balance_manager: String,
+    pub side: Side,
+    pub order_id: String,
+    pub client_order_id: u64,
+    pub is_bid: bool,
+    pub price: u64,
+    pub base_quantity: u64,
+    pub quote_quantity: u64,
+    pub fee: u64,
+    // the fee is paid in DEEP, otherwise in the token the order gives
+    pub fee_in_deep: bool,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum Side {
+    Maker,
+    Taker,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BalanceManagerTransfer {
+    pub balance_manager: String,
+    pub asset: String,
+    pub amount: u64,
+}
+
+// Money borrowed and paid back within one transaction, usually for an
+// arbitrage. The swaps in between are the intermediate legs, `net` is what
+// the wallet's balances changed by once everything was repaid.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FlashLoan {
+    pub loans: Vec<Loan>,
+    pub swaps: Vec<Swap>,
+    pub net: Vec<CoinAmount>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Loan {
+    pub lender: FlashLender,
+    // the pool lent from, for DeepBook and flash swaps
+    pub pool: Option<String>,
+    pub asset: Option<String>,
+    pub amount: u64,
+    // paid on top of `amount`, when the lender reports it
+    pub fee: Option<u64>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum FlashLender {
+    DeepBook,
+    Navi,
+    // a Cetus flash swap, repaid in the pool's other coin
+    Cetus,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CoinAmount {
+    pub coin_type: String,
+    pub amount: i128,
+}
+
+// A limit order, executed later by the protocol's keepers. `order` is
+// the order object, fills and payouts name it too.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KeeperOrder {
+    pub protocol: KeeperProtocol,
+    pub order: String,
+    pub owner: String,
+    pub pay_coin: String,
+    pub target_coin: String,
+    pub pay_amount: u64,
+    // limit orders: target coin per pay coin, scaled by 2^64, as a u128
+    pub price: Option<String>,
+    pub expire_timestamp_ms: Option<u64>,
+}
+
+// Part of an order executed by a keeper, who sends the transaction.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KeeperFill {
+    pub protocol: KeeperProtocol,
+    pub order: String,
+    // not every fill event names the order's owner
+    pub owner: Option<String>,
+    pub keeper: String,
+    pub pay_coin: String,
+    pub target_coin: String,
+    pub pay_amount: u64,
+    pub target_amount: u64,
+}
+
+// Coins paid out of an order: the unspent pay coins of a cancelled order,
+// or the target coins claimed from a filled one.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OrderPayout {
+    pub protocol: KeeperProtocol,
+    pub order: String,
+    pub owner: String,
+    pub coin_type: String,
+    pub amount: u64,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum KeeperProtocol {
+    CetusLimitOrder,
+}
+
+// An item put up for sale in a Kiosk, or taken off it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KioskListing {
+    pub kiosk: String,
+    pub item: String,
+    pub item_type: String,
+    // not reported on delisting
+    pub price: Option<u64>,
+    pub seller: String,
+}
+
+// An item bought from a Kiosk. Amounts are in MIST.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NftTrade {
+    pub kiosk: String,
+    pub item: String,
+    pub item_type: String,
+    // what the seller gets
+    pub price: u64,
+    // paid into the item's TransferPolicy on top of the price. Like the fee,
+    // it is read off the buyer's balance changes, so only known when one item
+    // was bought.
+    pub royalty: Option<u64>,
+    // paid to marketplace addresses on top of the price
+    pub marketplace_fee: Option<u64>,
+    pub buyer: String,
+    // the kiosk's owner, which the purchase doesn't name
+    pub seller: Option<String>,
+}
+
+// Coins sent over a bridge, from Sui or to it. The two sides of a transfer
+// share the bridge, the source chain and the sequence number.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BridgeTransfer {
+    pub bridge: Bridge,
+    // chain ids as the bridge numbers them, see `bridge::chain_name`
+    pub source_chain: u16,
+    pub destination_chain: u16,
+    // the sender when bridging out, the recipient when bridging in
+    pub sui_address: String,
+    // the recipient when bridging out; an inbound transfer doesn't name its
+    // sender
+    pub foreign_address: Option<ForeignAddress>,
+    pub coin_type: Option<String>,
+    // in the coin's units. None for a Wormhole transfer of a coin whose
+    // decimals are unknown, its message only has the amount at 8 decimals.
+    pub amount: Option<U256>,
+    pub sequence: u64,
+    // Wormhole only
+    pub nonce: Option<u32>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum Bridge {
+    SuiBridge,
+    Wormhole,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum ForeignAddress {
+    Evm(Address),
+    // e.g. Solana, as the 32 bytes Wormhole has it
+    Other(B256),
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
 pub enum Dex {
     Cetus,
     Magma,
@@ -45,11 +504,487 @@ pub enum Dex {
 
 #[cfg(test)]
 mod tests {
+    use bigdecimal::BigDecimal;
+    use serde_json::json;
+    use std::str::FromStr;
+    use sui_sdk::rpc_types::{
+        SuiObjectResponse, SuiTransactionBlockResponse, SuiTransactionBlockResponseOptions,
+    };
+    use sui_types::{base_types::ObjectID, digests::TransactionDigest};
     use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
-    use super::fetcher::ActivityFetcher;
+    use super::{
+        Side, TxType,
+        arbitrage::{ArbitrageAnalyzer, TradeKind},
+        fake_rpc::FakeRpcServer,
+        fetcher::ActivityFetcher,
+        orders::order_history,
+        pricing::StaticPrices,
+        source::RecordedSource,
+    };
+
+    const USER: &str = "0x62310ee294108c13f3496ce6895f12f3c2cf3994c74c2911501535e23ccc74ff";
+    const SUI: &str = "0x2::sui::SUI";
+    const USDC: &str =
+        "0xdba34672e30cb065b1f93e3ab55318768fd6fef66c15942c9f7cb846e2f900e7::usdc::USDC";
+
+    // a Cetus pool object, with the type its coins are read off
+    fn cetus_pool(id: &str, coin_a: &str, coin_b: &str) -> SuiObjectResponse {
+        const CETUS: &str = "0x1eabed72c53feb3805120a081dc15963c204dc8d091542592abaf7a35689b2fb";
+        let pool_type = format!("{CETUS}::pool::Pool<{coin_a}, {coin_b}>");
+        serde_json::from_value(json!({
+            "data": {
+                "objectId": id,
+                "version": "1",
+                "digest": "8RBsoeyoRwajj86MZfZE6gMDJQVYGYcdSfx1zxqxNHbr",
+                "type": pool_type,
+                "owner": {"Shared": {"initial_shared_version": 1}}
+            }
+        }))
+        .unwrap()
+    }
+
+    // the transaction of a decoder fixture
+    fn fixture(name: &str) -> SuiTransactionBlockResponse {
+        let path = format!(
+            "{}/fixtures/decode/{name}.tx.json",
+            env!("CARGO_MANIFEST_DIR")
+        );
+        serde_json::from_str(&std::fs::read_to_string(path).unwrap()).unwrap()
+    }
+
+    // an object of `object_type` owned by `owner`, with the Move `fields`
+    // besides its id
+    fn owned_object(
+        object_type: &str,
+        owner: &str,
+        mut fields: serde_json::Value,
+    ) -> SuiObjectResponse {
+        let id = ObjectID::random();
+        fields["id"] = json!({"id": id});
+        serde_json::from_value(json!({
+            "data": {
+                "objectId": id,
+                "version": "1",
+                "digest": "8RBsoeyoRwajj86MZfZE6gMDJQVYGYcdSfx1zxqxNHbr",
+                "type": object_type,
+                "owner": {"AddressOwner": owner},
+                "content": {
+                    "dataType": "moveObject",
+                    "type": object_type,
+                    "hasPublicTransfer": true,
+                    "fields": fields
+                }
+            }
+        }))
+        .unwrap()
+    }
+
+    fn transfer_tx(
+        digest: &str,
+        timestamp_ms: u64,
+        balance_changes: serde_json::Value,
+    ) -> SuiTransactionBlockResponse {
+        serde_json::from_value(json!({
+            "digest": digest,
+            "effects": {
+                "messageVersion": "v1",
+                "status": {"status": "success"},
+                "executedEpoch": "800",
+                "gasUsed": {
+                    "computationCost": "750000",
+                    "storageCost": "988000",
+                    "storageRebate": "978120",
+                    "nonRefundableStorageFee": "9880"
+                },
+                "transactionDigest": digest,
+                "gasObject": {
+                    "owner": {"AddressOwner": USER},
+                    "reference": {
+                        "objectId": "0x1a2b0b4bd6a9c5c2bbfa0ba5e54a0a0ddb0b3b3e0b0e0f61a8e4a3a3a5b4c3d2",
+                        "version": 100,
+                        "digest": "8RBsoeyoRwajj86MZfZE6gMDJQVYGYcdSfx1zxqxNHbr"
+                    }
+                }
+            },
+            "events": [],
+            "balanceChanges": balance_changes,
+            "timestampMs": timestamp_ms.to_string(),
+            "checkpoint": (timestamp_ms / 1000).to_string()
+        }))
+        .unwrap()
+    }
+
+    fn txs() -> Vec<SuiTransactionBlockResponse> {
+        vec![
+            transfer_tx(
+                "CRNzhTtGj6R7JS1wWfjq5H6Xy84dQTVroDfg2dnp2nSu",
+                1751968900000,
+                json!([{"owner": {"AddressOwner": USER}, "coinType": "0x2::sui::SUI", "amount": "783072"}]),
+            ),
+            transfer_tx(
+                "6SXMXiBmc9huz8xEb8BEh8rhGJPpCqy29FpwTioK4DbB",
+                1751969000000,
+                json!([
+                    {"owner": {"AddressOwner": "0x935029ca5219502a47ac9b69f556ccf6e2198b5e7815cf50f68846f723739cbd"}, "coinType": "0x2::sui::SUI", "amount": "-34940000000000"},
+                    {"owner": {"AddressOwner": USER}, "coinType": "0x2::sui::SUI", "amount": "34939940000000"}
+                ]),
+            ),
+        ]
+    }
+
+    #[tokio::test]
+    async fn test_collect_txs_from_fake_rpc() {
+        let server = FakeRpcServer::start(txs()).await.unwrap();
+        let fetcher = ActivityFetcher::builder(USER)
+            .rpc_url(&server.url())
+            .old_first(true)
+            .build()
+            .await
+            .unwrap();
+
+        let txs = fetcher.collect_txs(false).await.unwrap();
+        assert_eq!(txs.len(), 2);
+        assert!(matches!(txs[0].tx_type, TxType::SelfTransfer(_)));
+        match &txs[1].tx_type {
+            TxType::Transfer(transfer) => assert_eq!(transfer.receiver, USER),
+            other => panic!("unexpected {other:?}"),
+        }
+
+        // the sender of the second transfer received nothing
+        let sender = ActivityFetcher::builder(
+            "0x935029ca5219502a47ac9b69f556ccf6e2198b5e7815cf50f68846f723739cbd",
+        )
+        .rpc_url(&server.url())
+        .build()
+        .await
+        .unwrap();
+        assert!(sender.collect_txs(false).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_record_and_replay() {
+        let archive =
+            std::env::temp_dir().join(format!("sui_txs_replay_{}.jsonl.gz", std::process::id()));
+
+        let recorded = {
+            let fetcher = ActivityFetcher::builder(USER)
+                .source(RecordedSource::from_txs(txs()))
+                .record(&archive)
+                .build()
+                .await
+                .unwrap();
+            let txs = fetcher.collect_txs(false).await.unwrap();
+            fetcher.finish().unwrap();
+            txs
+        };
+
+        let fetcher = ActivityFetcher::builder(USER)
+            .replay(&archive)
+            .build()
+            .await
+            .unwrap();
+        let replayed = fetcher.collect_txs(false).await.unwrap();
+        std::fs::remove_file(&archive).unwrap();
+
+        assert_eq!(
+            serde_json::to_string(&recorded).unwrap(),
+            serde_json::to_string(&replayed).unwrap()
+        );
+        assert!(fetcher.collect_txs(true).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_record_rpc_responses() {
+        let archive =
+            std::env::temp_dir().join(format!("sui_txs_rpc_{}.jsonl.gz", std::process::id()));
+        let server = FakeRpcServer::start(txs()).await.unwrap();
+
+        let recorded = {
+            let fetcher = ActivityFetcher::builder(USER)
+                .rpc_url(&server.url())
+                .record(&archive)
+                .build()
+                .await
+                .unwrap();
+            let txs = fetcher.collect_txs(false).await.unwrap();
+            fetcher.finish().unwrap();
+            txs
+        };
+        drop(server);
+
+        let fetcher = ActivityFetcher::builder(USER)
+            .replay(&archive)
+            .build()
+            .await
+            .unwrap();
+        let replayed = fetcher.collect_txs(false).await.unwrap();
+        std::fs::remove_file(&archive).unwrap();
+
+        assert_eq!(recorded.len(), 2);
+        assert_eq!(
+            serde_json::to_string(&recorded).unwrap(),
+            serde_json::to_string(&replayed).unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_collect_txs_from_recorded_source() {
+        let fetcher = ActivityFetcher::builder(USER)
+            .source(RecordedSource::from_txs(txs()))
+            .time_range(Some(1751968950), None)
+            .build()
+            .await
+            .unwrap();
+
+        let txs = fetcher.collect_txs(false).await.unwrap();
+        assert_eq!(txs.len(), 1);
+        assert_eq!(txs[0].event_timestamp_ms, 1751969000000);
+    }
+
+    #[tokio::test]
+    async fn test_response_options_without_effects() {
+        let options = SuiTransactionBlockResponseOptions::new()
+            .with_events()
+            .with_balance_changes();
+        let built = ActivityFetcher::builder(USER)
+            .source(RecordedSource::from_txs(txs()))
+            .response_options(options)
+            .build()
+            .await;
+        assert!(built.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_collect_txs_skips_undecodable() {
+        let mut all = txs();
+        let mut broken = all[0].clone();
+        broken.digest =
+            TransactionDigest::from_str("9LH2pVnsFpPXE3pDAmAkVLPjSGYsiEHoBiW2kWJi3nDm").unwrap();
+        broken.effects = None;
+        all.push(broken);
+        let fetcher = ActivityFetcher::builder(USER)
+            .source(RecordedSource::from_txs(all))
+            .build()
+            .await
+            .unwrap();
+
+        let txs = fetcher.collect_txs(false).await.unwrap();
+        assert_eq!(txs.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_collect_liquidations() {
+        const SUILEND: &str = "0xf95b06141ed4a174f239417323bde3f209b972f5930d8521ea38a52aff3a6ddf";
+        let obligation = "0xced53433aed265eeadad5a5a0bbd32b59fa39200a1ae435c91fb057c04a87331";
+        let cap = owned_object(
+            &format!(
+                "{SUILEND}::lending_market::ObligationOwnerCap<{SUILEND}::suilend::MAIN_POOL>"
+            ),
+            USER,
+            json!({"obligation_id": obligation}),
+        );
+        let mut all = txs();
+        all.push(fixture("suilend_liquidation"));
+        let source = RecordedSource::from_txs(all)
+            .with_objects(vec![cap])
+            .unwrap();
+        let fetcher = ActivityFetcher::builder(USER)
+            .source(source)
+            .build()
+            .await
+            .unwrap();
+
+        let txs = fetcher.collect_liquidations().await.unwrap();
+        assert_eq!(txs.len(), 1);
+        match &txs[0].tx_type {
+            TxType::Liquidation(liquidation) => assert_eq!(liquidation.account, obligation),
+            other => panic!("unexpected {other:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_collect_trades() {
+        const CETUS_COIN: &str =
+            "0x06864a6f921804860930db6ddbe2e16acdf8504495ea7481637a1c8b9a8fe54b::cetus::CETUS";
+        let flash_loan = fixture("navi_flash_loan_arbitrage");
+        let cycle = fixture("cetus_cycle_arbitrage");
+        let (flash_loan_digest, cycle_digest) = (flash_loan.digest, cycle.digest);
+        let source = RecordedSource::from_txs(vec![flash_loan, cycle])
+            .with_objects(vec![
+                cetus_pool(
+                    "0xd572c0c81aa62bede626acee48eacfb83ad07e468ee4a2b8ed163c767a1bf58f",
+                    USDC,
+                    SUI,
+                ),
+                cetus_pool(
+                    "0xb59d0a63d0848d375c3e05297b1e0ff418b300acc168a7ac94bcba26d3dbf278",
+                    USDC,
+                    SUI,
+                ),
+                cetus_pool(
+                    "0xaf56fbf03d7b882c9115e773af722bf3427882bf2f402d950b1ce95809dd4507",
+                    USDC,
+                    SUI,
+                ),
+                cetus_pool(
+                    "0x3f0ae19119e5f31add0e823019ce90b4be35113f0253fade1d58c6f65250691b",
+                    CETUS_COIN,
+                    SUI,
+                ),
+                cetus_pool(
+                    "0x73c84862a07cd41a858e9642413d02de2d8446f620fc550214bc9739da77d5e5",
+                    CETUS_COIN,
+                    USDC,
+                ),
+            ])
+            .unwrap();
+        let fetcher = ActivityFetcher::builder(USER)
+            .source(source)
+            .build()
+            .await
+            .unwrap();
+
+        let prices = StaticPrices::new([(USDC.to_string(), 1.into())].into());
+        let mut analyzer = ArbitrageAnalyzer::new(&prices).decimals(USDC, 6);
+        let trades = fetcher.collect_trades(&mut analyzer).await.unwrap();
+        assert_eq!(trades.len(), 2);
+        assert!(trades.iter().all(|t| t.kind == TradeKind::Arbitrage));
+        let trade = |digest: TransactionDigest| {
+            trades
+                .iter()
+                .find(|t| t.tx_hash == digest.to_string())
+                .unwrap()
+        };
+
+        let flash_loan = trade(flash_loan_digest);
+        assert_eq!(flash_loan.path, [USDC, SUI, USDC]);
+        // the swaps made 2.4 USDC, 0.4 of which went to the flash loan's fees
+        assert_eq!(flash_loan.profit, Some(2_000_000));
+        assert_eq!(flash_loan.profit_usd, Some(2.into()));
+
+        let cycle = trade(cycle_digest);
+        assert_eq!(cycle.swaps, 3);
+        assert_eq!(cycle.path, [USDC, SUI, CETUS_COIN, USDC]);
+        assert_eq!(cycle.profit, Some(250_000));
+        assert_eq!(cycle.profit_usd, Some(BigDecimal::from(25) / 100));
+    }
+
+    #[tokio::test]
+    async fn test_collect_orders() {
+        let fill = fixture("cetus_limit_order_fill");
+        let fill_digest = fill.digest;
+        let fetcher = ActivityFetcher::builder(USER)
+            .source(RecordedSource::from_txs(vec![
+                fixture("cetus_limit_order_open"),
+                fill,
+            ]))
+            .old_first(true)
+            .build()
+            .await
+            .unwrap();
+
+        // the keeper sent the fill, only its events lead to it
+        let sent = fetcher.collect_txs(true).await.unwrap();
+        assert_eq!(sent.len(), 1);
+        assert!(matches!(sent[0].tx_type, TxType::KeeperOrderCreated(_)));
+
+        let txs = fetcher.collect_orders().await.unwrap();
+        assert_eq!(txs.len(), 2);
+        assert!(matches!(txs[0].tx_type, TxType::KeeperOrderCreated(_)));
+        assert!(matches!(txs[1].tx_type, TxType::KeeperOrderFilled(_)));
+        assert_eq!(txs[1].tx_hash, fill_digest);
+
+        let history = order_history(&txs);
+        assert_eq!(history.len(), 1);
+        assert_eq!(history.values().next().unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_collect_maker_fills() {
+        // the taker sent the fill, the maker only shows up in its event
+        const MAKER: &str = "0x9a5f14de49c1d2048de81f61540c984b1bf570253e44d7bcd71557cc44aec56d";
+        let balance_manager = ObjectID::from_str(
+            "0x2328632b682066f402f03e18e837591166b5dc65172fc5824696d5f0e7e90a9e",
+        )
+        .unwrap();
+        let fetcher = ActivityFetcher::builder(MAKER)
+            .source(RecordedSource::from_txs(vec![fixture(
+                "deepbook_partial_fill",
+            )]))
+            .balance_managers(vec![balance_manager])
+            .build()
+            .await
+            .unwrap();
+
+        let txs = fetcher.collect_fills().await.unwrap();
+        assert_eq!(txs.len(), 1);
+        let TxType::Batch(tx_types) = &txs[0].tx_type else {
+            panic!("unexpected {:?}", txs[0].tx_type);
+        };
+        match &tx_types[0] {
+            TxType::OrderFilled(fill) => {
+                assert_eq!(fill.side, Side::Maker);
+                assert_eq!(fill.balance_manager, balance_manager.to_string());
+            }
+            other => panic!("unexpected {other:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_collect_nft_sales() {
+        // the buyer sent the purchase, the seller only holds the kiosk's cap
+        const SELLER: &str = "0xa6b7ec81b24d0e09b6a1a7d9f2e672bdb5e773d9f48642ef705f8b3ff4a1780d";
+        let kiosk = "0xc13ddd4c12e94edf17b67d92dda0e46bb125e59a7321f4ad62252e44378e2f24";
+        let cap = owned_object("0x2::kiosk::KioskOwnerCap", SELLER, json!({"for": kiosk}));
+        let source = RecordedSource::from_txs(vec![fixture("kiosk_purchase")])
+            .with_objects(vec![cap])
+            .unwrap();
+        let fetcher = ActivityFetcher::builder(SELLER)
+            .source(source)
+            .build()
+            .await
+            .unwrap();
+
+        let txs = fetcher.collect_nft_trades().await.unwrap();
+        assert_eq!(txs.len(), 1);
+        match &txs[0].tx_type {
+            TxType::NftTrade(trade) => {
+                assert_eq!(trade.kiosk, kiosk);
+                assert_eq!(trade.buyer, USER);
+                assert_eq!(trade.seller.as_deref(), Some(SELLER));
+            }
+            other => panic!("unexpected {other:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_collect_bridge_transfers() {
+        // a relayer claimed the incoming transfer, the wallet only received
+        // the coins
+        let fetcher = ActivityFetcher::builder(USER)
+            .source(RecordedSource::from_txs(vec![
+                fixture("sui_bridge_eth_out"),
+                fixture("sui_bridge_eth_in"),
+            ]))
+            .build()
+            .await
+            .unwrap();
+
+        let txs = fetcher.collect_bridge_transfers().await.unwrap();
+        assert_eq!(txs.len(), 2);
+        match &txs[0].tx_type {
+            TxType::BridgeIn(transfer) => {
+                assert_eq!(transfer.sui_address, USER);
+                assert_eq!(transfer.sequence, 8765);
+            }
+            other => panic!("unexpected {other:?}"),
+        }
+        assert!(matches!(txs[1].tx_type, TxType::BridgeOut(_)));
+    }
 
     #[tokio::test]
+    #[ignore = "reads mainnet"]
     async fn test_log_sui_tx_resp() {
         tracing_subscriber::registry()
             .with(
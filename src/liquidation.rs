@@ -0,0 +1,454 @@
+use anyhow::{Context, Result};
+use bigdecimal::{BigDecimal, ToPrimitive, Zero};
+use mini_macro::here as h;
+use move_core_types::{language_storage::StructTag, u256::U256};
+use serde_json::Value;
+use std::{
+    collections::{HashMap, HashSet},
+    str::FromStr,
+};
+use sui_sdk::rpc_types::{
+    EventFilter, SuiEvent, SuiObjectDataFilter, SuiObjectDataOptions, SuiObjectResponse,
+    SuiObjectResponseQuery, SuiParsedData, SuiTransactionBlockResponseOptions,
+    SuiTransactionBlockResponseQuery, TransactionFilter,
+};
+use sui_types::{
+    base_types::{ObjectID, SuiAddress},
+    digests::TransactionDigest,
+    parse_sui_struct_tag,
+};
+
+use crate::{
+    LendingProtocol, Liquidation, TxType,
+    config::{CoinConfig, coin_decimals, default_coins},
+    errors::EventDecodeError,
+    event_match::EventPattern,
+    events::{
+        DecodeMode, NaviLiquidationEvent, ScallopLiquidateEvent, ScallopLiquidateEventV2,
+        SuilendLiquidateEvent, parse,
+    },
+    lending::{NAVI, SCALLOP, SUILEND, navi_asset},
+    liquidity::coin_type,
+    pricing::normalize_coin_type,
+    source::{TransactionSource, before, newest_first, search_events},
+};
+
+const EVENTS: &[(LendingProtocol, &str, &str)] = &[
+    (
+        LendingProtocol::Scallop,
+        SCALLOP,
+        "liquidate::LiquidateEvent",
+    ),
+    (
+        LendingProtocol::Scallop,
+        SCALLOP,
+        "liquidate::LiquidateEventV2",
+    ),
+    (LendingProtocol::Navi, NAVI, "lending::LiquidationEvent"),
+    (
+        LendingProtocol::Suilend,
+        SUILEND,
+        "lending_market::LiquidateEvent",
+    ),
+];
+
+// The objects a wallet holds for its lending accounts, and where they keep
+// the account's id. Navi accounts are the wallet's address itself, or the id
+// of an `AccountCap` it holds.
+const ACCOUNT_OBJECTS: &[(LendingProtocol, &str, &str, &[&str])] = &[
+    (
+        LendingProtocol::Scallop,
+        SCALLOP,
+        "obligation::ObligationKey",
+        &["ownership", "of"],
+    ),
+    (
+        LendingProtocol::Suilend,
+        SUILEND,
+        "lending_market::ObligationOwnerCap",
+        &["obligation_id"],
+    ),
+    (
+        LendingProtocol::Navi,
+        NAVI,
+        "account::AccountCap",
+        &["id", "id"],
+    ),
+];
+
+// The lending accounts of a wallet: the obligation objects of Scallop and
+// Suilend, and the addresses Navi keeps its accounts under.
+#[derive(Debug, Clone, Default)]
+pub struct LendingAccounts {
+    pub obligations: HashSet<String>,
+    pub navi: HashSet<String>,
+}
+
+// Collateral taken on top of the repaid debt's worth at the prices the
+// protocol liquidated at, in collateral units. The prices are per whole
+// coin and on one scale, the decimals of both coins must be in `decimals`.
+fn penalty(
+    repaid: u64,
+    debt_asset: &str,
+    debt_price: BigDecimal,
+    seized: u64,
+    collateral_asset: &str,
+    collateral_price: BigDecimal,
+    decimals: &HashMap<String, u32>,
+) -> Option<u64> {
+    if collateral_price.is_zero() {
+        return None;
+    }
+    let decimals_of = |coin: &str| decimals.get(&normalize_coin_type(coin)).copied();
+    let debt_decimals = decimals_of(debt_asset)?;
+    let collateral_decimals = decimals_of(collateral_asset)?;
+    let scale = |decimals: u32| BigDecimal::from(10u64.pow(decimals));
+    let repaid_worth = BigDecimal::from(repaid) * debt_price * scale(collateral_decimals)
+        / (collateral_price * scale(debt_decimals));
+    let penalty = BigDecimal::from(seized) - repaid_worth;
+    if penalty <= BigDecimal::zero() {
+        return Some(0);
+    }
+    penalty.with_scale(0).to_u64()
+}
+
+pub struct LiquidationDecoder {
+    patterns: Vec<(LendingProtocol, EventPattern)>,
+    decimals: HashMap<String, u32>,
+}
+
+impl Default for LiquidationDecoder {
+    fn default() -> Self {
+        let patterns = EVENTS
+            .iter()
+            .map(|(protocol, package, event)| {
+                let pattern = EventPattern::from_str(&format!("{package}::{event}"))
+                    .expect("valid liquidation event");
+                (*protocol, pattern)
+            })
+            .collect();
+        Self {
+            patterns,
+            decimals: coin_decimals(&default_coins()),
+        }
+    }
+}
+
+impl LiquidationDecoder {
+    // The coins whose decimals penalties can be worked out with, in place of
+    // `default_coins`.
+    pub fn with_coins(mut self, coins: &[CoinConfig]) -> Self {
+        self.decimals = coin_decimals(coins);
+        self
+    }
+
+    pub fn decode(
+        &self,
+        events: &[SuiEvent],
+        mode: DecodeMode,
+    ) -> Result<Option<TxType>, EventDecodeError> {
+        for event in events {
+            if let Some(liquidation) = self.decode_event(event, mode)? {
+                return Ok(Some(TxType::Liquidation(liquidation)));
+            }
+        }
+        Ok(None)
+    }
+
+    fn decode_event(
+        &self,
+        event: &SuiEvent,
+        mode: DecodeMode,
+    ) -> Result<Option<Liquidation>, EventDecodeError> {
+        let Some((protocol, _)) = self
+            .patterns
+            .iter()
+            .find(|(_, pattern)| pattern.matches(&event.type_).is_some())
+        else {
+            return Ok(None);
+        };
+        decode_liquidation_event(*protocol, event, mode, &self.decimals).map(Some)
+    }
+}
+
+pub fn decode_liquidation_event(
+    protocol: LendingProtocol,
+    event: &SuiEvent,
+    mode: DecodeMode,
+    decimals: &HashMap<String, u32>,
+) -> Result<Liquidation, EventDecodeError> {
+    let name = format!("{protocol:?}");
+    let p = name.as_str();
+
+    let liquidation = match (protocol, event.type_.name.as_str()) {
+        (LendingProtocol::Scallop, "LiquidateEvent") => {
+            let e: ScallopLiquidateEvent = parse(p, event, mode)?;
+            Liquidation {
+                protocol,
+                market: None,
+                account: e.obligation.to_string(),
+                liquidator: e.liquidator.to_string(),
+                debt_asset: Some(coin_type(&e.debt_type.name)),
+                repaid: e.repay_on_behalf,
+                collateral_asset: Some(coin_type(&e.collateral_type.name)),
+                seized: e.liq_amount,
+                penalty: None,
+            }
+        }
+        (LendingProtocol::Scallop, "LiquidateEventV2") => {
+            let e: ScallopLiquidateEventV2 = parse(p, event, mode)?;
+            let debt_asset = coin_type(&e.debt_type.name);
+            let collateral_asset = coin_type(&e.collateral_type.name);
+            Liquidation {
+                protocol,
+                market: None,
+                account: e.obligation.to_string(),
+                liquidator: e.liquidator.to_string(),
+                penalty: penalty(
+                    e.repay_on_behalf,
+                    &debt_asset,
+                    BigDecimal::from(e.debt_price.value),
+                    e.liq_amount,
+                    &collateral_asset,
+                    BigDecimal::from(e.collateral_price.value),
+                    decimals,
+                ),
+                debt_asset: Some(debt_asset),
+                repaid: e.repay_on_behalf,
+                collateral_asset: Some(collateral_asset),
+                seized: e.liq_amount,
+            }
+        }
+        (LendingProtocol::Navi, "LiquidationEvent") => {
+            let e: NaviLiquidationEvent = parse(p, event, mode)?;
+            let debt_asset = navi_asset(event, "debt_asset", e.debt_asset)?;
+            let collateral_asset = navi_asset(event, "collateral_asset", e.collateral_asset)?;
+            let price = |price: U256| BigDecimal::from_str(&price.to_string()).ok();
+            Liquidation {
+                protocol,
+                market: None,
+                account: e.user.to_string(),
+                liquidator: e.sender.to_string(),
+                penalty: price(e.debt_price).zip(price(e.collateral_price)).and_then(
+                    |(debt_price, collateral_price)| {
+                        penalty(
+                            e.debt_amount,
+                            &debt_asset,
+                            debt_price,
+                            e.collateral_amount,
+                            &collateral_asset,
+                            collateral_price,
+                            decimals,
+                        )
+                    },
+                ),
+                debt_asset: Some(debt_asset),
+                repaid: e.debt_amount,
+                collateral_asset: Some(collateral_asset),
+                seized: e.collateral_amount,
+            }
+        }
+        // the liquidator is not named, it is whoever sent the transaction
+        (LendingProtocol::Suilend, "LiquidateEvent") => {
+            let e: SuilendLiquidateEvent = parse(p, event, mode)?;
+            Liquidation {
+                protocol,
+                market: Some(e.lending_market_id.to_string()),
+                account: e.obligation_id.to_string(),
+                liquidator: event.sender.to_string(),
+                debt_asset: Some(coin_type(&e.repay_coin_type.name)),
+                repaid: e.repay_amount,
+                collateral_asset: Some(coin_type(&e.withdraw_coin_type.name)),
+                seized: e.withdraw_amount,
+                penalty: Some(e.protocol_fee_amount + e.liquidator_bonus_amount),
+            }
+        }
+        _ => {
+            return Err(EventDecodeError::Invalid {
+                protocol: name,
+                event: format!("{}::{}", event.type_.module, event.type_.name),
+                reason: "not a liquidation event".to_string(),
+            });
+        }
+    };
+    Ok(liquidation)
+}
+
+// `path` in the fields of a Move object, looking through the `fields`
+// wrapper nested structs may come in.
+fn field<'a>(mut value: &'a Value, path: &[&str]) -> Option<&'a Value> {
+    for key in path {
+        value = match value.get(key) {
+            Some(v) => v,
+            None => value.get("fields")?.get(key)?,
+        };
+    }
+    Some(value)
+}
+
+fn account_id(
+    object: &SuiObjectResponse,
+    patterns: &[(LendingProtocol, EventPattern, &[&str])],
+) -> Option<(LendingProtocol, String)> {
+    let data = object.data.as_ref()?;
+    let tag = StructTag::try_from(data.object_type().ok()?).ok()?;
+    let (protocol, _, path) = patterns
+        .iter()
+        .find(|(_, pattern, _)| pattern.matches(&tag).is_some())?;
+    let Some(SuiParsedData::MoveObject(content)) = &data.content else {
+        return None;
+    };
+    let fields = content.fields.clone().to_json_value();
+    let id = ObjectID::from_str(field(&fields, path)?.as_str()?).ok()?;
+    Some((*protocol, id.to_string()))
+}
+
+// The lending accounts of `who`: the obligations behind the keys and caps it
+// holds, and for Navi its own address and its account caps.
+pub async fn lending_accounts(
+    source: &dyn TransactionSource,
+    who: SuiAddress,
+) -> Result<LendingAccounts> {
+    let patterns = ACCOUNT_OBJECTS
+        .iter()
+        .map(|(protocol, package, object, path)| {
+            let pattern = EventPattern::from_str(&format!("{package}::{object}")).context(h!())?;
+            Ok((*protocol, pattern, *path))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    let filter = ACCOUNT_OBJECTS
+        .iter()
+        .map(|(_, package, object, _)| {
+            Ok(SuiObjectDataFilter::StructType(
+                parse_sui_struct_tag(&format!("{package}::{object}")).context(h!())?,
+            ))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    let query = SuiObjectResponseQuery::new(
+        Some(SuiObjectDataFilter::MatchAny(filter)),
+        Some(SuiObjectDataOptions::new().with_type().with_content()),
+    );
+
+    let mut accounts = LendingAccounts::default();
+    accounts.navi.insert(who.to_string());
+    let mut cursor = None;
+    loop {
+        let page = source
+            .get_owned_objects(who, &query, cursor, None)
+            .await
+            .context(h!())?;
+        for (protocol, id) in page.data.iter().filter_map(|o| account_id(o, &patterns)) {
+            match protocol {
+                LendingProtocol::Navi => accounts.navi.insert(id),
+                _ => accounts.obligations.insert(id),
+            };
+        }
+        if !page.has_next_page {
+            break;
+        }
+        cursor = page.next_cursor;
+    }
+    Ok(accounts)
+}
+
+// Transactions liquidating one of `accounts`, newest first. They are sent by
+// the liquidator, so a wallet's own transaction history doesn't have them:
+// obligations are followed through the transactions that changed them, and
+// Navi accounts through the events naming them as `user`.
+pub async fn find_liquidations(
+    source: &dyn TransactionSource,
+    accounts: &LendingAccounts,
+    since_ms: Option<u64>,
+) -> Result<Vec<TransactionDigest>> {
+    let decoder = LiquidationDecoder::default();
+    let liquidates = |event: &SuiEvent, account: &str| -> Result<bool> {
+        let liquidation = decoder
+            .decode_event(event, DecodeMode::Json)
+            .context(h!())?;
+        Ok(liquidation.is_some_and(|l| l.account == account))
+    };
+    let mut found: Vec<(u64, TransactionDigest)> = vec![];
+
+    let options = SuiTransactionBlockResponseOptions::new().with_events();
+    for obligation in &accounts.obligations {
+        let filter =
+            TransactionFilter::ChangedObject(ObjectID::from_str(obligation).context(h!())?);
+        let query = SuiTransactionBlockResponseQuery::new(Some(filter), Some(options.clone()));
+        let mut cursor = None;
+        'txs: loop {
+            let page = source
+                .query_transaction_blocks(&query, cursor, None, true)
+                .await
+                .context(h!())?;
+            for tx in &page.data {
+                if before(since_ms, tx.timestamp_ms) {
+                    break 'txs;
+                }
+                let events = tx.events.as_ref().map_or(&[][..], |e| &e.data[..]);
+                for event in events {
+                    if liquidates(event, obligation)? {
+                        found.push((tx.timestamp_ms.unwrap_or_default(), tx.digest));
+                        break;
+                    }
+                }
+            }
+            if !page.has_next_page {
+                break;
+            }
+            cursor = page.next_cursor;
+        }
+    }
+
+    for account in &accounts.navi {
+        let query = EventFilter::MoveEventField {
+            path: "/user".to_string(),
+            value: Value::String(account.clone()),
+        };
+        found.extend(
+            search_events(source, &query, since_ms, |event| liquidates(event, account))
+                .await
+                .context(h!())?,
+        );
+    }
+    Ok(newest_first(found))
+}
+
+#[cfg(test)]
+mod tests {
+    use bigdecimal::BigDecimal;
+    use serde_json::json;
+
+    use super::{field, penalty};
+    use crate::config::{coin_decimals, default_coins};
+
+    const SUI: &str = "0x2::sui::SUI";
+    const USDC: &str =
+        "0xdba34672e30cb065b1f93e3ab55318768fd6fef66c15942c9f7cb846e2f900e7::usdc::USDC";
+
+    #[test]
+    fn test_penalty() {
+        let decimals = coin_decimals(&default_coins());
+        // 100 USDC repaid is worth 50 SUI at 2 USD a SUI
+        let seized = |sui: u64| penalty(100_000_000, USDC, 1.into(), sui, SUI, 2.into(), &decimals);
+        assert_eq!(seized(52_500_000_000), Some(2_500_000_000));
+        assert_eq!(seized(49_000_000_000), Some(0));
+        assert_eq!(
+            penalty(1, "0x1::coin::COIN", 1.into(), 2, SUI, 1.into(), &decimals),
+            None
+        );
+        assert_eq!(
+            penalty(1, USDC, 1.into(), 2, SUI, BigDecimal::from(0), &decimals),
+            None
+        );
+    }
+
+    #[test]
+    fn test_field() {
+        let flat = json!({"id": {"id": "0x1"}, "ownership": {"of": "0xab"}});
+        assert_eq!(field(&flat, &["ownership", "of"]), Some(&json!("0xab")));
+
+        let wrapped = json!({"ownership": {"type": "0x1::o::Ownership", "fields": {"of": "0xab"}}});
+        assert_eq!(field(&wrapped, &["ownership", "of"]), Some(&json!("0xab")));
+        assert_eq!(field(&wrapped, &["obligation_id"]), None);
+    }
+}
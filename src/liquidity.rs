@@ -0,0 +1,355 @@
+use move_core_types::language_storage::TypeTag;
+use std::str::FromStr;
+use sui_sdk::rpc_types::SuiEvent;
+use sui_types::base_types::ObjectID;
+
+use crate::{
+    Dex, Liquidity, Reward, TxType,
+    errors::EventDecodeError,
+    events::{
+        BluefinFeeCollectedEvent, BluefinLiquidityEvent, BluefinPositionEvent,
+        BluefinRewardCollectedEvent, CetusClosePositionEvent, CetusCollectFeeEvent,
+        CetusCollectRewardEvent, CetusCollectRewardV2Event, CetusLiquidityEvent,
+        CetusOpenPositionEvent, DecodeMode, I32, parse,
+    },
+};
+
+const CETUS_EVENTS: &[&str] = &[
+    "pool::OpenPositionEvent",
+    "pool::ClosePositionEvent",
+    "pool::AddLiquidityEvent",
+    "pool::RemoveLiquidityEvent",
+    "pool::CollectFeeEvent",
+    "pool::CollectRewardEvent",
+    "pool::CollectRewardV2Event",
+];
+
+const BLUEFIN_EVENTS: &[&str] = &[
+    "events::PositionOpened",
+    "events::PositionClosed",
+    "events::LiquidityProvided",
+    "events::LiquidityRemoved",
+    "events::UserFeeCollected",
+    "events::UserRewardCollected",
+];
+
+// The position events a dex emits, as `module::Name`.
+pub fn lp_events(dex: Dex) -> &'static [&'static str] {
+    match dex {
+        // Magma is a Cetus fork and emits the same events
+        Dex::Cetus | Dex::Magma => CETUS_EVENTS,
+        Dex::Bluefin => BLUEFIN_EVENTS,
+    }
+}
+
+// Ordered by how much they say about the transaction: a transaction that
+// opens a position and adds liquidity to it is reported as `Open`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum LpAction {
+    Open,
+    Close,
+    Add,
+    Remove,
+    CollectFee,
+    CollectReward,
+}
+
+impl LpAction {
+    fn into_tx_type(self, liquidity: Liquidity) -> TxType {
+        match self {
+            LpAction::Open => TxType::OpenPosition(liquidity),
+            LpAction::Close => TxType::ClosePosition(liquidity),
+            LpAction::Add => TxType::AddLiquidity(liquidity),
+            LpAction::Remove => TxType::RemoveLiquidity(liquidity),
+            LpAction::CollectFee => TxType::CollectFee(liquidity),
+            LpAction::CollectReward => TxType::CollectReward(liquidity),
+        }
+    }
+}
+
+fn position(dex: Dex, pool: ObjectID, position: ObjectID) -> Liquidity {
+    Liquidity {
+        dex,
+        pool: pool.to_string(),
+        position: position.to_string(),
+        tick_lower: None,
+        tick_upper: None,
+        liquidity_delta: 0,
+        added_a: 0,
+        added_b: 0,
+        removed_a: 0,
+        removed_b: 0,
+        fee_a: 0,
+        fee_b: 0,
+        rewards: vec![],
+        sqrt_price: None,
+    }
+}
+
+fn with_ticks(mut liquidity: Liquidity, lower: I32, upper: I32) -> Liquidity {
+    liquidity.tick_lower = Some(lower.value());
+    liquidity.tick_upper = Some(upper.value());
+    liquidity
+}
+
+// `TypeName`s come without the `0x` prefix and with the long address form.
+pub(crate) fn coin_type(name: &str) -> String {
+    let prefixed = if name.starts_with("0x") {
+        name.to_string()
+    } else {
+        format!("0x{name}")
+    };
+    TypeTag::from_str(&prefixed)
+        .map(|t| t.to_string())
+        .unwrap_or(prefixed)
+}
+
+pub fn decode_lp_event(
+    dex: Dex,
+    event: &SuiEvent,
+    mode: DecodeMode,
+) -> Result<(LpAction, Liquidity), EventDecodeError> {
+    let protocol = format!("{dex:?}");
+    let p = protocol.as_str();
+    let name = event.type_.name.as_str();
+
+    let decoded = match dex {
+        Dex::Cetus | Dex::Magma => match name {
+            "OpenPositionEvent" => {
+                let e: CetusOpenPositionEvent = parse(p, event, mode)?;
+                let lp = position(dex, e.pool, e.position);
+                (LpAction::Open, with_ticks(lp, e.tick_lower, e.tick_upper))
+            }
+            "ClosePositionEvent" => {
+                let e: CetusClosePositionEvent = parse(p, event, mode)?;
+                (LpAction::Close, position(dex, e.pool, e.position))
+            }
+            "AddLiquidityEvent" | "RemoveLiquidityEvent" => {
+                let e: CetusLiquidityEvent = parse(p, event, mode)?;
+                let mut lp = with_ticks(
+                    position(dex, e.pool, e.position),
+                    e.tick_lower,
+                    e.tick_upper,
+                );
+                if name == "AddLiquidityEvent" {
+                    lp.liquidity_delta = e.liquidity as i128;
+                    (lp.added_a, lp.added_b) = (e.amount_a, e.amount_b);
+                    (LpAction::Add, lp)
+                } else {
+                    lp.liquidity_delta = -(e.liquidity as i128);
+                    (lp.removed_a, lp.removed_b) = (e.amount_a, e.amount_b);
+                    (LpAction::Remove, lp)
+                }
+            }
+            "CollectFeeEvent" => {
+                let e: CetusCollectFeeEvent = parse(p, event, mode)?;
+                let mut lp = position(dex, e.pool, e.position);
+                lp.fee_a = e.amount_a;
+                lp.fee_b = e.amount_b;
+                (LpAction::CollectFee, lp)
+            }
+            "CollectRewardEvent" => {
+                let e: CetusCollectRewardEvent = parse(p, event, mode)?;
+                let mut lp = position(dex, e.pool, e.position);
+                lp.rewards.push(Reward {
+                    coin_type: None,
+                    amount: e.amount,
+                });
+                (LpAction::CollectReward, lp)
+            }
+            "CollectRewardV2Event" => {
+                let e: CetusCollectRewardV2Event = parse(p, event, mode)?;
+                let mut lp = position(dex, e.pool, e.position);
+                lp.rewards.push(Reward {
+                    coin_type: Some(coin_type(&e.reward_type.name)),
+                    amount: e.amount,
+                });
+                (LpAction::CollectReward, lp)
+            }
+            _ => return Err(not_lp_event(p, event)),
+        },
+        Dex::Bluefin => match name {
+            "PositionOpened" | "PositionClosed" => {
+                let e: BluefinPositionEvent = parse(p, event, mode)?;
+                let lp = position(dex, e.pool_id, e.position_id);
+                let action = if name == "PositionOpened" {
+                    LpAction::Open
+                } else {
+                    LpAction::Close
+                };
+                (action, with_ticks(lp, e.tick_lower, e.tick_upper))
+            }
+            "LiquidityProvided" | "LiquidityRemoved" => {
+                let e: BluefinLiquidityEvent = parse(p, event, mode)?;
+                let mut lp = with_ticks(
+                    position(dex, e.pool_id, e.position_id),
+                    e.lower_tick,
+                    e.upper_tick,
+                );
+                lp.sqrt_price = Some(e.current_sqrt_price.to_string());
+                if name == "LiquidityProvided" {
+                    lp.liquidity_delta = e.liquidity as i128;
+                    (lp.added_a, lp.added_b) = (e.coin_a_amount, e.coin_b_amount);
+                    (LpAction::Add, lp)
+                } else {
+                    lp.liquidity_delta = -(e.liquidity as i128);
+                    (lp.removed_a, lp.removed_b) = (e.coin_a_amount, e.coin_b_amount);
+                    (LpAction::Remove, lp)
+                }
+            }
+            "UserFeeCollected" => {
+                let e: BluefinFeeCollectedEvent = parse(p, event, mode)?;
+                let mut lp = position(dex, e.pool_id, e.position_id);
+                lp.fee_a = e.coin_a_amount;
+                lp.fee_b = e.coin_b_amount;
+                (LpAction::CollectFee, lp)
+            }
+            "UserRewardCollected" => {
+                let e: BluefinRewardCollectedEvent = parse(p, event, mode)?;
+                let mut lp = position(dex, e.pool_id, e.position_id);
+                lp.rewards.push(Reward {
+                    coin_type: Some(coin_type(&e.reward_type)),
+                    amount: e.reward_amount,
+                });
+                (LpAction::CollectReward, lp)
+            }
+            _ => return Err(not_lp_event(p, event)),
+        },
+    };
+    Ok(decoded)
+}
+
+fn not_lp_event(protocol: &str, event: &SuiEvent) -> EventDecodeError {
+    EventDecodeError::Invalid {
+        protocol: protocol.to_string(),
+        event: format!("{}::{}", event.type_.module, event.type_.name),
+        reason: "not a position event".to_string(),
+    }
+}
+
+fn merge(into: &mut Liquidity, other: Liquidity) {
+    into.tick_lower = into.tick_lower.or(other.tick_lower);
+    into.tick_upper = into.tick_upper.or(other.tick_upper);
+    into.liquidity_delta += other.liquidity_delta;
+    into.added_a += other.added_a;
+    into.added_b += other.added_b;
+    into.removed_a += other.removed_a;
+    into.removed_b += other.removed_b;
+    into.fee_a += other.fee_a;
+    into.fee_b += other.fee_b;
+    into.rewards.extend(other.rewards);
+    // the last change leaves the pool at its price
+    if other.sqrt_price.is_some() {
+        into.sqrt_price = other.sqrt_price;
+    }
+}
+
+// Folds the decoded position events of one transaction into a `TxType`, one
+// entry per position in the order they first appear.
+pub fn fold(events: Vec<(LpAction, Liquidity)>) -> Option<TxType> {
+    let mut positions: Vec<(LpAction, Liquidity)> = vec![];
+    for (action, liquidity) in events {
+        match positions
+            .iter_mut()
+            .find(|(_, p)| p.position == liquidity.position)
+        {
+            Some((a, p)) => {
+                *a = (*a).min(action);
+                merge(p, liquidity);
+            }
+            None => positions.push((action, liquidity)),
+        }
+    }
+
+    let mut tx_types = positions
+        .into_iter()
+        .map(|(action, liquidity)| action.into_tx_type(liquidity))
+        .collect::<Vec<_>>();
+    match tx_types.len() {
+        0 => None,
+        1 => tx_types.pop(),
+        _ => Some(TxType::Rebalance(tx_types)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use sui_types::base_types::ObjectID;
+
+    use super::{LpAction, coin_type, fold, position};
+    use crate::{Dex, Liquidity, Reward, TxType};
+
+    fn lp(id: u8) -> Liquidity {
+        position(Dex::Cetus, ObjectID::ZERO, ObjectID::from_single_byte(id))
+    }
+
+    #[test]
+    fn test_fold_close() {
+        let mut remove = lp(1);
+        remove.liquidity_delta = -500;
+        remove.removed_a = 10;
+        let mut fee = lp(1);
+        fee.fee_b = 3;
+        let mut reward = lp(1);
+        reward.rewards.push(Reward {
+            coin_type: None,
+            amount: 7,
+        });
+
+        let tx_type = fold(vec![
+            (LpAction::CollectFee, fee),
+            (LpAction::CollectReward, reward),
+            (LpAction::Remove, remove),
+            (LpAction::Close, lp(1)),
+        ]);
+        let Some(TxType::ClosePosition(closed)) = tx_type else {
+            panic!("unexpected {tx_type:?}");
+        };
+        assert_eq!(closed.liquidity_delta, -500);
+        assert_eq!((closed.removed_a, closed.fee_b), (10, 3));
+        assert_eq!(closed.rewards.len(), 1);
+    }
+
+    #[test]
+    fn test_fold_remove_and_add() {
+        let mut remove = lp(1);
+        remove.liquidity_delta = -500;
+        (remove.removed_a, remove.removed_b) = (10, 20);
+        let mut add = lp(1);
+        add.liquidity_delta = 200;
+        (add.added_a, add.added_b) = (4, 8);
+
+        let tx_type = fold(vec![(LpAction::Remove, remove), (LpAction::Add, add)]);
+        let Some(TxType::AddLiquidity(lp)) = tx_type else {
+            panic!("unexpected {tx_type:?}");
+        };
+        assert_eq!(lp.liquidity_delta, -300);
+        assert_eq!((lp.added_a, lp.added_b), (4, 8));
+        assert_eq!((lp.removed_a, lp.removed_b), (10, 20));
+    }
+
+    #[test]
+    fn test_fold_rebalance() {
+        let tx_type = fold(vec![
+            (LpAction::Close, lp(1)),
+            (LpAction::Open, lp(2)),
+            (LpAction::Add, lp(2)),
+        ]);
+        let Some(TxType::Rebalance(tx_types)) = tx_type else {
+            panic!("unexpected {tx_type:?}");
+        };
+        assert!(matches!(tx_types[0], TxType::ClosePosition(_)));
+        assert!(matches!(tx_types[1], TxType::OpenPosition(_)));
+
+        assert!(fold(vec![]).is_none());
+    }
+
+    #[test]
+    fn test_coin_type() {
+        assert_eq!(
+            coin_type("0000000000000000000000000000000000000000000000000000000000000002::sui::SUI"),
+            "0x2::sui::SUI"
+        );
+        assert_eq!(coin_type("0x2::sui::SUI"), "0x2::sui::SUI");
+    }
+}
@@ -0,0 +1,278 @@
+use bigdecimal::{BigDecimal, RoundingMode};
+use std::str::FromStr;
+use sui_sdk::rpc_types::SuiEvent;
+
+use crate::{
+    LiquidStake, LiquidUnstake, Lst, Redemption, TxType,
+    errors::EventDecodeError,
+    event_match::EventPattern,
+    events::{
+        AftermathStakedEvent, AftermathUnstakeRequestedEvent, AftermathUnstakedEvent, DecodeMode,
+        HaedalInstantUnstakedEvent, HaedalNormalUnstakedEvent, HaedalStakedEvent,
+        SpringSuiMintEvent, SpringSuiRedeemEvent, VoloStakedEvent, VoloUnstakedEvent, parse,
+    },
+    liquidity::coin_type,
+    pricing::normalize_coin_type,
+};
+
+const AFTERMATH: &str = "0x7f6ce7ade63857c4fd16ef7783fed2dfc4d7fb7e40615abdb653030b76aef0c6";
+const HAEDAL: &str = "0xbde4ba4c2e274a60ce15c1cfff9e5c42e41654ac8b6d906a57efa4bd3c29f47d";
+const VOLO: &str = "0x549e8b69270defbfafd4f94e17ec44cdbdd99820b33bda2278dea3b9a32d3f55";
+const SPRING_SUI: &str = "0xb0575765166030556a6eafd3b1b970eba8183ff748860680245b9edd41c716e7";
+
+const AFSUI: &str =
+    "0xf325ce1300e8dac124071d3152c5c5ee6174914f8bc2161e88329cf579246efc::afsui::AFSUI";
+const HASUI: &str =
+    "0xbde4ba4c2e274a60ce15c1cfff9e5c42e41654ac8b6d906a57efa4bd3c29f47d::hasui::HASUI";
+const VSUI: &str = "0x549e8b69270defbfafd4f94e17ec44cdbdd99820b33bda2278dea3b9a32d3f55::cert::CERT";
+
+// Mint and redeem events of each protocol, as emitted by its original
+// package.
+const EVENTS: &[(Lst, &str, &[&str])] = &[
+    (
+        Lst::Aftermath,
+        AFTERMATH,
+        &[
+            "events::StakedEvent",
+            "events::UnstakeRequestedEvent",
+            "events::UnstakedEvent",
+        ],
+    ),
+    (
+        Lst::Haedal,
+        HAEDAL,
+        &[
+            "staking::UserStaked",
+            "staking::UserNormalUnstaked",
+            "staking::UserInstantUnstaked",
+        ],
+    ),
+    (
+        Lst::Volo,
+        VOLO,
+        &["native_pool::StakedEvent", "native_pool::UnstakedEvent"],
+    ),
+    (
+        Lst::SpringSui,
+        SPRING_SUI,
+        &["liquid_staking::MintEvent", "liquid_staking::RedeemEvent"],
+    ),
+];
+
+// SUI per LST token.
+fn exchange_rate(sui_amount: u64, lst_amount: u64) -> Option<BigDecimal> {
+    if lst_amount == 0 {
+        return None;
+    }
+    let rate = BigDecimal::from(sui_amount) / BigDecimal::from(lst_amount);
+    Some(rate.with_scale_round(9, RoundingMode::HalfEven))
+}
+
+// Minting and redeeming liquid staking tokens.
+pub struct LstDecoder {
+    patterns: Vec<(Lst, EventPattern)>,
+}
+
+impl Default for LstDecoder {
+    fn default() -> Self {
+        let patterns = EVENTS
+            .iter()
+            .flat_map(|(lst, package, events)| {
+                events.iter().map(move |event| {
+                    let pattern = EventPattern::from_str(&format!("{package}::{event}"))
+                        .expect("valid LST event");
+                    (*lst, pattern)
+                })
+            })
+            .collect();
+        Self { patterns }
+    }
+}
+
+impl LstDecoder {
+    pub fn decode(
+        &self,
+        events: &[SuiEvent],
+        mode: DecodeMode,
+    ) -> Result<Option<TxType>, EventDecodeError> {
+        for event in events {
+            let Some((lst, _)) = self
+                .patterns
+                .iter()
+                .find(|(_, pattern)| pattern.matches(&event.type_).is_some())
+            else {
+                continue;
+            };
+            return decode_lst_event(*lst, event, mode).map(Some);
+        }
+        Ok(None)
+    }
+}
+
+pub fn decode_lst_event(
+    lst: Lst,
+    event: &SuiEvent,
+    mode: DecodeMode,
+) -> Result<TxType, EventDecodeError> {
+    let protocol = format!("{lst:?}");
+    let p = protocol.as_str();
+    let name = event.type_.name.as_str();
+
+    let tx_type = match (lst, name) {
+        (Lst::Aftermath, "StakedEvent") => {
+            let e: AftermathStakedEvent = parse(p, event, mode)?;
+            TxType::LiquidStake(LiquidStake {
+                protocol: lst,
+                staker: e.staker.to_string(),
+                lst_type: normalize_coin_type(AFSUI),
+                sui_amount: e.sui_amount,
+                lst_amount: e.afsui_amount,
+                exchange_rate: exchange_rate(e.sui_amount, e.afsui_amount),
+            })
+        }
+        (Lst::Aftermath, "UnstakeRequestedEvent") => {
+            let e: AftermathUnstakeRequestedEvent = parse(p, event, mode)?;
+            TxType::LiquidUnstake(LiquidUnstake {
+                protocol: lst,
+                staker: e.requester.to_string(),
+                lst_type: normalize_coin_type(AFSUI),
+                lst_amount: e.provided_afsui_amount,
+                sui_amount: None,
+                exchange_rate: None,
+                // paid out by the vault when the epoch changes
+                redemption: Some(Redemption {
+                    ticket: None,
+                    claimable_epoch: Some(e.epoch + 1),
+                    claimable_at_ms: None,
+                }),
+            })
+        }
+        (Lst::Aftermath, "UnstakedEvent") => {
+            let e: AftermathUnstakedEvent = parse(p, event, mode)?;
+            TxType::LiquidUnstake(LiquidUnstake {
+                protocol: lst,
+                staker: e.requester.to_string(),
+                lst_type: normalize_coin_type(AFSUI),
+                lst_amount: e.provided_afsui_amount,
+                sui_amount: Some(e.returned_sui_amount),
+                exchange_rate: exchange_rate(e.returned_sui_amount, e.provided_afsui_amount),
+                redemption: None,
+            })
+        }
+        (Lst::Haedal, "UserStaked") => {
+            let e: HaedalStakedEvent = parse(p, event, mode)?;
+            TxType::LiquidStake(LiquidStake {
+                protocol: lst,
+                staker: e.owner.to_string(),
+                lst_type: normalize_coin_type(HASUI),
+                sui_amount: e.sui_amount,
+                lst_amount: e.st_amount,
+                exchange_rate: exchange_rate(e.sui_amount, e.st_amount),
+            })
+        }
+        (Lst::Haedal, "UserNormalUnstaked") => {
+            let e: HaedalNormalUnstakedEvent = parse(p, event, mode)?;
+            TxType::LiquidUnstake(LiquidUnstake {
+                protocol: lst,
+                staker: e.owner.to_string(),
+                lst_type: normalize_coin_type(HASUI),
+                lst_amount: e.st_amount,
+                sui_amount: None,
+                exchange_rate: None,
+                redemption: Some(Redemption {
+                    ticket: Some(e.ticket_id.to_string()),
+                    claimable_epoch: None,
+                    claimable_at_ms: Some(e.unstake_timestamp_ms),
+                }),
+            })
+        }
+        (Lst::Haedal, "UserInstantUnstaked") => {
+            let e: HaedalInstantUnstakedEvent = parse(p, event, mode)?;
+            TxType::LiquidUnstake(LiquidUnstake {
+                protocol: lst,
+                staker: e.owner.to_string(),
+                lst_type: normalize_coin_type(HASUI),
+                lst_amount: e.st_amount,
+                sui_amount: Some(e.sui_amount),
+                exchange_rate: exchange_rate(e.sui_amount, e.st_amount),
+                redemption: None,
+            })
+        }
+        (Lst::Volo, "StakedEvent") => {
+            let e: VoloStakedEvent = parse(p, event, mode)?;
+            TxType::LiquidStake(LiquidStake {
+                protocol: lst,
+                staker: e.staker.to_string(),
+                lst_type: normalize_coin_type(VSUI),
+                sui_amount: e.sui_amount,
+                lst_amount: e.cert_amount,
+                exchange_rate: exchange_rate(e.sui_amount, e.cert_amount),
+            })
+        }
+        (Lst::Volo, "UnstakedEvent") => {
+            let e: VoloUnstakedEvent = parse(p, event, mode)?;
+            TxType::LiquidUnstake(LiquidUnstake {
+                protocol: lst,
+                staker: e.staker.to_string(),
+                lst_type: normalize_coin_type(VSUI),
+                lst_amount: e.cert_amount,
+                sui_amount: Some(e.sui_amount),
+                exchange_rate: exchange_rate(e.sui_amount, e.cert_amount),
+                redemption: None,
+            })
+        }
+        // SpringSui events don't name the user, the event sender is them
+        (Lst::SpringSui, "MintEvent") => {
+            let e: SpringSuiMintEvent = parse(p, event, mode)?;
+            TxType::LiquidStake(LiquidStake {
+                protocol: lst,
+                staker: event.sender.to_string(),
+                lst_type: coin_type(&e.typename.name),
+                sui_amount: e.sui_amount_in,
+                lst_amount: e.lst_amount_out,
+                exchange_rate: exchange_rate(e.sui_amount_in, e.lst_amount_out),
+            })
+        }
+        (Lst::SpringSui, "RedeemEvent") => {
+            let e: SpringSuiRedeemEvent = parse(p, event, mode)?;
+            TxType::LiquidUnstake(LiquidUnstake {
+                protocol: lst,
+                staker: event.sender.to_string(),
+                lst_type: coin_type(&e.typename.name),
+                lst_amount: e.lst_amount_in,
+                sui_amount: Some(e.sui_amount_out),
+                exchange_rate: exchange_rate(e.sui_amount_out, e.lst_amount_in),
+                redemption: None,
+            })
+        }
+        _ => {
+            return Err(EventDecodeError::Invalid {
+                protocol,
+                event: format!("{}::{}", event.type_.module, event.type_.name),
+                reason: "not a mint or redeem event".to_string(),
+            });
+        }
+    };
+    Ok(tx_type)
+}
+
+#[cfg(test)]
+mod tests {
+    use bigdecimal::BigDecimal;
+    use std::str::FromStr;
+
+    use super::exchange_rate;
+
+    #[test]
+    fn test_exchange_rate() {
+        assert_eq!(
+            exchange_rate(1_050_000_000, 1_000_000_000),
+            BigDecimal::from_str("1.050000000").ok()
+        );
+        assert_eq!(
+            exchange_rate(2, 3),
+            BigDecimal::from_str("0.666666667").ok()
+        );
+        assert_eq!(exchange_rate(1, 0), None);
+    }
+}
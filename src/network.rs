@@ -0,0 +1,49 @@
+use serde::{Deserialize, Serialize};
+use sui_sdk::{SUI_DEVNET_URL, SUI_LOCAL_NETWORK_URL, SUI_MAINNET_URL, SUI_TESTNET_URL};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Network {
+    Mainnet,
+    Testnet,
+    Devnet,
+    // `sui start` on the default port
+    Localnet,
+    Custom(String),
+}
+
+impl Network {
+    pub fn rpc_url(&self) -> &str {
+        match self {
+            Network::Mainnet => SUI_MAINNET_URL,
+            Network::Testnet => SUI_TESTNET_URL,
+            Network::Devnet => SUI_DEVNET_URL,
+            Network::Localnet => SUI_LOCAL_NETWORK_URL,
+            Network::Custom(url) => url,
+        }
+    }
+
+    pub fn from_name(name: &str) -> Self {
+        match name {
+            "mainnet" => Network::Mainnet,
+            "testnet" => Network::Testnet,
+            "devnet" => Network::Devnet,
+            "localnet" => Network::Localnet,
+            url => Network::Custom(url.to_string()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Network;
+
+    #[test]
+    fn test_network_rpc_url() {
+        assert_eq!(Network::Localnet.rpc_url(), "http://127.0.0.1:9000");
+        assert_eq!(Network::from_name("testnet"), Network::Testnet);
+        assert_eq!(
+            Network::from_name("https://rpc.example.com").rpc_url(),
+            "https://rpc.example.com"
+        );
+    }
+}
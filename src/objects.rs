@@ -1 +1,171 @@
 // TODO: multi fetch objects
+use move_core_types::language_storage::StructTag;
+use sui_sdk::rpc_types::{ObjectChange, SuiCallArg, SuiObjectArg, SuiTransactionBlockResponse};
+use sui_types::{SUI_FRAMEWORK_ADDRESS, base_types::ObjectID, object::Owner};
+
+use crate::{ObjectChangeKind, ObjectChanges, ObjectMovement, TxType, ptb};
+
+fn is_coin(tag: &StructTag) -> bool {
+    tag.address == SUI_FRAMEWORK_ADDRESS
+        && tag.module.as_str() == "coin"
+        && tag.name.as_str() == "Coin"
+}
+
+fn owner_name(owner: &Owner) -> String {
+    match owner {
+        Owner::AddressOwner(address) | Owner::ObjectOwner(address) => address.to_string(),
+        Owner::Shared { .. } => "shared".to_string(),
+        Owner::Immutable => "immutable".to_string(),
+        _ => owner.to_string(),
+    }
+}
+
+// Objects the sender passed in by value or reference; they were its own
+// before the transaction.
+fn owned_inputs(tx_resp: &SuiTransactionBlockResponse) -> Vec<ObjectID> {
+    let Some(ptb) = ptb::programmable(tx_resp) else {
+        return vec![];
+    };
+    ptb.inputs
+        .iter()
+        .filter_map(|input| match input {
+            SuiCallArg::Object(SuiObjectArg::ImmOrOwnedObject { object_id, .. }) => {
+                Some(*object_id)
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+// What happened to the non-coin objects of the transaction, from its
+// `object_changes`. The previous owner is only known for objects the sender
+// owned.
+pub fn movements(tx_resp: &SuiTransactionBlockResponse) -> Vec<ObjectMovement> {
+    let Some(changes) = &tx_resp.object_changes else {
+        return vec![];
+    };
+    let owned = owned_inputs(tx_resp);
+    let previous_owner = |id: &ObjectID, sender: String| owned.contains(id).then_some(sender);
+
+    changes
+        .iter()
+        .filter_map(|change| {
+            let (kind, object_type, object_id, version, previous_owner, new_owner) = match change {
+                ObjectChange::Created {
+                    owner,
+                    object_type,
+                    object_id,
+                    version,
+                    ..
+                } => (
+                    ObjectChangeKind::Created,
+                    object_type,
+                    object_id,
+                    version,
+                    None,
+                    Some(owner_name(owner)),
+                ),
+                ObjectChange::Transferred {
+                    sender,
+                    recipient,
+                    object_type,
+                    object_id,
+                    version,
+                    ..
+                } => (
+                    ObjectChangeKind::Transferred,
+                    object_type,
+                    object_id,
+                    version,
+                    Some(sender.to_string()),
+                    Some(owner_name(recipient)),
+                ),
+                ObjectChange::Mutated {
+                    sender,
+                    owner,
+                    object_type,
+                    object_id,
+                    version,
+                    ..
+                } => (
+                    ObjectChangeKind::Mutated,
+                    object_type,
+                    object_id,
+                    version,
+                    previous_owner(object_id, sender.to_string()),
+                    Some(owner_name(owner)),
+                ),
+                ObjectChange::Wrapped {
+                    sender,
+                    object_type,
+                    object_id,
+                    version,
+                } => (
+                    ObjectChangeKind::Wrapped,
+                    object_type,
+                    object_id,
+                    version,
+                    previous_owner(object_id, sender.to_string()),
+                    None,
+                ),
+                ObjectChange::Deleted {
+                    sender,
+                    object_type,
+                    object_id,
+                    version,
+                } => (
+                    ObjectChangeKind::Deleted,
+                    object_type,
+                    object_id,
+                    version,
+                    previous_owner(object_id, sender.to_string()),
+                    None,
+                ),
+                ObjectChange::Published { .. } => return None,
+            };
+            if is_coin(object_type) {
+                return None;
+            }
+            Some(ObjectMovement {
+                object_id: object_id.to_string(),
+                object_type: object_type.to_string(),
+                version: version.value(),
+                kind,
+                previous_owner,
+                new_owner,
+            })
+        })
+        .collect()
+}
+
+// The transaction as object movements, when a non-coin object changed hands
+// or came or went. Objects merely mutated in place don't count.
+pub fn decode(tx_resp: &SuiTransactionBlockResponse) -> Option<TxType> {
+    let sender = ptb::sender(tx_resp)?.to_string();
+    let changes = movements(tx_resp);
+    let moved = changes.iter().any(|c| match c.kind {
+        ObjectChangeKind::Mutated => c.previous_owner.is_some() && c.previous_owner != c.new_owner,
+        _ => true,
+    });
+    if !moved {
+        return None;
+    }
+    Some(TxType::ObjectChanges(ObjectChanges { sender, changes }))
+}
+
+#[cfg(test)]
+mod tests {
+    use sui_types::parse_sui_struct_tag;
+
+    use super::is_coin;
+
+    #[test]
+    fn test_is_coin() {
+        let coin = parse_sui_struct_tag("0x2::coin::Coin<0x2::sui::SUI>").unwrap();
+        assert!(is_coin(&coin));
+        let metadata = parse_sui_struct_tag("0x2::coin::CoinMetadata<0x2::sui::SUI>").unwrap();
+        assert!(!is_coin(&metadata));
+        let nft = parse_sui_struct_tag("0xab::nft::Nft").unwrap();
+        assert!(!is_coin(&nft));
+    }
+}
@@ -0,0 +1,294 @@
+use anyhow::{Context, Result};
+use mini_macro::here as h;
+use serde_json::Value;
+use std::{
+    collections::{BTreeMap, HashSet},
+    str::FromStr,
+};
+use sui_sdk::rpc_types::{EventFilter, SuiEvent};
+use sui_types::digests::TransactionDigest;
+
+use crate::{
+    KeeperFill, KeeperOrder, KeeperProtocol, OrderPayout, TransactionKind, TxType,
+    errors::EventDecodeError,
+    event_match::EventPattern,
+    events::{
+        CetusCancelLimitOrderEvent, CetusClaimTargetCoinEvent, CetusFlowLimitOrderEvent,
+        CetusOpenLimitOrderEvent, DecodeMode, parse,
+    },
+    liquidity::coin_type,
+    source::{TransactionSource, newest_first, search_events},
+};
+
+const CETUS_LIMIT_ORDER: &str =
+    "0x533fab9a116080e2cb1c87f1832c1bf4231ab4c32318ced041e75cc28604bba9";
+
+const EVENTS: &[(KeeperProtocol, &str, &str)] = &[
+    (
+        KeeperProtocol::CetusLimitOrder,
+        CETUS_LIMIT_ORDER,
+        "limit_order::OpenLimitOrderEvent",
+    ),
+    (
+        KeeperProtocol::CetusLimitOrder,
+        CETUS_LIMIT_ORDER,
+        "limit_order::FlowLimitOrderEvent",
+    ),
+    (
+        KeeperProtocol::CetusLimitOrder,
+        CETUS_LIMIT_ORDER,
+        "limit_order::CancelLimitOrderEvent",
+    ),
+    (
+        KeeperProtocol::CetusLimitOrder,
+        CETUS_LIMIT_ORDER,
+        "limit_order::ClaimTargetCoinEvent",
+    ),
+];
+
+// Limit orders: creation, keeper fills, cancellation and claims.
+pub struct OrderDecoder {
+    patterns: Vec<(KeeperProtocol, EventPattern)>,
+}
+
+impl Default for OrderDecoder {
+    fn default() -> Self {
+        let patterns = EVENTS
+            .iter()
+            .map(|(protocol, package, event)| {
+                let pattern = EventPattern::from_str(&format!("{package}::{event}"))
+                    .expect("valid order event");
+                (*protocol, pattern)
+            })
+            .collect();
+        Self { patterns }
+    }
+}
+
+impl OrderDecoder {
+    pub fn decode(
+        &self,
+        events: &[SuiEvent],
+        mode: DecodeMode,
+    ) -> Result<Option<TxType>, EventDecodeError> {
+        let mut tx_types = vec![];
+        for event in events {
+            let Some((protocol, _)) = self
+                .patterns
+                .iter()
+                .find(|(_, pattern)| pattern.matches(&event.type_).is_some())
+            else {
+                continue;
+            };
+            tx_types.extend(decode_order_event(*protocol, event, mode)?);
+        }
+        Ok(match tx_types.len() {
+            0 => None,
+            1 => tx_types.pop(),
+            _ => Some(TxType::Batch(tx_types)),
+        })
+    }
+}
+
+pub fn decode_order_event(
+    protocol: KeeperProtocol,
+    event: &SuiEvent,
+    mode: DecodeMode,
+) -> Result<Vec<TxType>, EventDecodeError> {
+    let name = format!("{protocol:?}");
+    let p = name.as_str();
+
+    let tx_types = match (protocol, event.type_.name.as_str()) {
+        (KeeperProtocol::CetusLimitOrder, "OpenLimitOrderEvent") => {
+            let e: CetusOpenLimitOrderEvent = parse(p, event, mode)?;
+            vec![TxType::KeeperOrderCreated(KeeperOrder {
+                protocol,
+                order: e.order_id.to_string(),
+                owner: e.owner.to_string(),
+                pay_coin: coin_type(&e.pay_coin_type.name),
+                target_coin: coin_type(&e.target_coin_type.name),
+                pay_amount: e.pay_amount,
+                price: Some(e.price.to_string()),
+                expire_timestamp_ms: Some(e.expire_ts),
+            })]
+        }
+        (KeeperProtocol::CetusLimitOrder, "FlowLimitOrderEvent") => {
+            let e: CetusFlowLimitOrderEvent = parse(p, event, mode)?;
+            vec![TxType::KeeperOrderFilled(KeeperFill {
+                protocol,
+                order: e.order_id.to_string(),
+                owner: None,
+                keeper: event.sender.to_string(),
+                pay_coin: coin_type(&e.pay_coin_type.name),
+                target_coin: coin_type(&e.target_coin_type.name),
+                pay_amount: e.pay_amount,
+                target_amount: e.target_amount,
+            })]
+        }
+        (KeeperProtocol::CetusLimitOrder, "CancelLimitOrderEvent") => {
+            let e: CetusCancelLimitOrderEvent = parse(p, event, mode)?;
+            let payout = |coin: &str, amount| OrderPayout {
+                protocol,
+                order: e.order_id.to_string(),
+                owner: e.owner.to_string(),
+                coin_type: coin_type(coin),
+                amount,
+            };
+            let mut tx_types = vec![TxType::KeeperOrderCancelled(payout(
+                &e.pay_coin_type.name,
+                e.pay_amount,
+            ))];
+            if e.target_amount > 0 {
+                tx_types.push(TxType::KeeperOrderClaimed(payout(
+                    &e.target_coin_type.name,
+                    e.target_amount,
+                )));
+            }
+            tx_types
+        }
+        (KeeperProtocol::CetusLimitOrder, "ClaimTargetCoinEvent") => {
+            let e: CetusClaimTargetCoinEvent = parse(p, event, mode)?;
+            vec![TxType::KeeperOrderClaimed(OrderPayout {
+                protocol,
+                order: e.order_id.to_string(),
+                owner: e.owner.to_string(),
+                coin_type: coin_type(&e.target_coin_type.name),
+                amount: e.target_amount,
+            })]
+        }
+        _ => {
+            return Err(EventDecodeError::Invalid {
+                protocol: name,
+                event: format!("{}::{}", event.type_.module, event.type_.name),
+                reason: "not an order event".to_string(),
+            });
+        }
+    };
+    Ok(tx_types)
+}
+
+// The orders a decoded transaction touches.
+pub fn order_ids(tx_type: &TxType) -> Vec<&str> {
+    match tx_type {
+        TxType::KeeperOrderCreated(order) => vec![order.order.as_str()],
+        TxType::KeeperOrderFilled(fill) => vec![fill.order.as_str()],
+        TxType::KeeperOrderCancelled(payout) | TxType::KeeperOrderClaimed(payout) => {
+            vec![payout.order.as_str()]
+        }
+        TxType::Batch(tx_types) => {
+            let mut ids = tx_types.iter().flat_map(order_ids).collect::<Vec<_>>();
+            ids.dedup();
+            ids
+        }
+        _ => vec![],
+    }
+}
+
+// `txs` grouped by the orders they touch, each order's transactions in the
+// order of `txs`.
+pub fn order_history(txs: &[TransactionKind]) -> BTreeMap<String, Vec<&TransactionKind>> {
+    let mut history: BTreeMap<String, Vec<&TransactionKind>> = BTreeMap::new();
+    for tx in txs {
+        for id in order_ids(&tx.tx_type) {
+            history.entry(id.to_string()).or_default().push(tx);
+        }
+    }
+    history
+}
+
+// Keeper transactions filling one of `orders`, newest first. The wallet
+// never sends these, so they are looked up by the order they name.
+pub async fn find_fills(
+    source: &dyn TransactionSource,
+    orders: &HashSet<String>,
+    since_ms: Option<u64>,
+) -> Result<Vec<TransactionDigest>> {
+    let decoder = OrderDecoder::default();
+    let mut found = vec![];
+    for order in orders {
+        let query = EventFilter::MoveEventField {
+            path: "/order_id".to_string(),
+            value: Value::String(order.clone()),
+        };
+        found.extend(
+            search_events(source, &query, since_ms, |event| {
+                let tx_type = decoder
+                    .decode(std::slice::from_ref(event), DecodeMode::Json)
+                    .context(h!())?;
+                Ok(matches!(
+                    tx_type,
+                    Some(TxType::KeeperOrderFilled(fill)) if fill.order == *order
+                ))
+            })
+            .await
+            .context(h!())?,
+        );
+    }
+    Ok(newest_first(found))
+}
+
+#[cfg(test)]
+mod tests {
+    use sui_types::digests::TransactionDigest;
+
+    use super::order_history;
+    use crate::{KeeperFill, KeeperProtocol, OrderPayout, TransactionKind, TxType};
+
+    fn tx(timestamp_ms: u64, tx_type: TxType) -> TransactionKind {
+        TransactionKind {
+            tx_type,
+            tx_hash: TransactionDigest::random(),
+            event_timestamp_ms: timestamp_ms,
+        }
+    }
+
+    fn fill(order: &str) -> TxType {
+        TxType::KeeperOrderFilled(KeeperFill {
+            protocol: KeeperProtocol::CetusLimitOrder,
+            order: order.to_string(),
+            owner: None,
+            keeper: "0xk".to_string(),
+            pay_coin: "0x2::sui::SUI".to_string(),
+            target_coin: "0x2::sui::SUI".to_string(),
+            pay_amount: 1,
+            target_amount: 1,
+        })
+    }
+
+    fn payout(order: &str) -> OrderPayout {
+        OrderPayout {
+            protocol: KeeperProtocol::CetusLimitOrder,
+            order: order.to_string(),
+            owner: "0x1".to_string(),
+            coin_type: "0x2::sui::SUI".to_string(),
+            amount: 1,
+        }
+    }
+
+    #[test]
+    fn test_order_history() {
+        let txs = [
+            tx(1, fill("0xa")),
+            tx(2, fill("0xb")),
+            // a cancellation paying out both coins counts once
+            tx(
+                3,
+                TxType::Batch(vec![
+                    TxType::KeeperOrderCancelled(payout("0xa")),
+                    TxType::KeeperOrderClaimed(payout("0xa")),
+                ]),
+            ),
+            tx(4, TxType::Unknown),
+        ];
+        let history = order_history(&txs);
+        assert_eq!(history.len(), 2);
+        let timestamps = |order: &str| {
+            history[order]
+                .iter()
+                .map(|tx| tx.event_timestamp_ms)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(timestamps("0xa"), [1, 3]);
+        assert_eq!(timestamps("0xb"), [2]);
+    }
+}
@@ -0,0 +1,418 @@
+use alloy_primitives::aliases::{I24, U160};
+use anyhow::{Context, Result};
+use bigdecimal::BigDecimal;
+use mini_macro::here as h;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use sui_types::digests::TransactionDigest;
+use uniswap_v3_sdk::prelude::{get_amount_0_delta, get_amount_1_delta, get_sqrt_ratio_at_tick};
+
+use crate::{Dex, Liquidity, TransactionKind, TxType};
+
+// Sui CLMMs keep sqrt prices as Q64.64, the uniswap math works on Q64.96.
+fn to_x96(sqrt_price_x64: u128) -> U160 {
+    U160::from(sqrt_price_x64) << 32
+}
+
+fn sqrt_ratio_at_tick(tick: i32) -> Result<U160> {
+    let tick = I24::try_from(tick).context(h!())?;
+    get_sqrt_ratio_at_tick(tick).context(h!())
+}
+
+// Price of token a in token b, both in base units, from a Q64.64 sqrt price.
+pub fn price_from_sqrt(sqrt_price_x64: u128) -> BigDecimal {
+    let sqrt = BigDecimal::from(sqrt_price_x64);
+    let q128 = BigDecimal::from(1u128 << 64) * BigDecimal::from(1u128 << 64);
+    &sqrt * &sqrt / q128
+}
+
+// Tokens `liquidity` between two ticks is made of at a given sqrt price.
+pub fn amounts_for_liquidity(
+    liquidity: u128,
+    tick_lower: i32,
+    tick_upper: i32,
+    sqrt_price_x64: u128,
+) -> Result<(u128, u128)> {
+    let lower = sqrt_ratio_at_tick(tick_lower)?;
+    let upper = sqrt_ratio_at_tick(tick_upper)?;
+    let current = to_x96(sqrt_price_x64);
+
+    let (amount_a, amount_b) = if current <= lower {
+        (
+            get_amount_0_delta(lower, upper, liquidity, false)?,
+            Default::default(),
+        )
+    } else if current < upper {
+        (
+            get_amount_0_delta(current, upper, liquidity, false)?,
+            get_amount_1_delta(lower, current, liquidity, false)?,
+        )
+    } else {
+        (
+            Default::default(),
+            get_amount_1_delta(lower, upper, liquidity, false)?,
+        )
+    };
+    Ok((
+        u128::try_from(amount_a).context(h!())?,
+        u128::try_from(amount_b).context(h!())?,
+    ))
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PositionState {
+    pub position: String,
+    pub dex: Dex,
+    pub pool: String,
+    pub tick_lower: Option<i32>,
+    pub tick_upper: Option<i32>,
+    // `None` when the position was opened before the tracked history
+    pub opened_at: Option<u64>,
+    pub closed_at: Option<u64>,
+    pub liquidity: u128,
+    pub deposited_a: u128,
+    pub deposited_b: u128,
+    pub withdrawn_a: u128,
+    pub withdrawn_b: u128,
+    pub fees_a: u128,
+    pub fees_b: u128,
+    // coin type -> amount, `unknown` for rewards that do not name their coin
+    pub rewards: BTreeMap<String, u128>,
+    pub txs: Vec<TransactionDigest>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Valuation {
+    pub sqrt_price: u128,
+    // what the remaining liquidity is worth at `sqrt_price`
+    pub amount_a: u128,
+    pub amount_b: u128,
+    // withdrawals, fees and the remaining liquidity minus the deposits,
+    // valued in token b. Rewards are left out.
+    pub pnl_in_b: BigDecimal,
+}
+
+impl PositionState {
+    fn new(liquidity: &Liquidity) -> Self {
+        Self {
+            position: liquidity.position.clone(),
+            dex: liquidity.dex,
+            pool: liquidity.pool.clone(),
+            tick_lower: None,
+            tick_upper: None,
+            opened_at: None,
+            closed_at: None,
+            liquidity: 0,
+            deposited_a: 0,
+            deposited_b: 0,
+            withdrawn_a: 0,
+            withdrawn_b: 0,
+            fees_a: 0,
+            fees_b: 0,
+            rewards: BTreeMap::new(),
+            txs: vec![],
+        }
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.closed_at.is_none()
+    }
+
+    fn apply(&mut self, tx: &TransactionKind, liquidity: &Liquidity) {
+        self.tick_lower = self.tick_lower.or(liquidity.tick_lower);
+        self.tick_upper = self.tick_upper.or(liquidity.tick_upper);
+
+        let delta = liquidity.liquidity_delta.unsigned_abs();
+        if liquidity.liquidity_delta >= 0 {
+            self.liquidity += delta;
+        } else {
+            // liquidity added before the tracked history is unknown
+            self.liquidity = self.liquidity.saturating_sub(delta);
+        }
+        self.deposited_a += liquidity.added_a as u128;
+        self.deposited_b += liquidity.added_b as u128;
+        self.withdrawn_a += liquidity.removed_a as u128;
+        self.withdrawn_b += liquidity.removed_b as u128;
+        self.fees_a += liquidity.fee_a as u128;
+        self.fees_b += liquidity.fee_b as u128;
+        for reward in &liquidity.rewards {
+            let coin_type = reward.coin_type.as_deref().unwrap_or("unknown");
+            *self.rewards.entry(coin_type.to_string()).or_default() += reward.amount as u128;
+        }
+        if self.txs.last() != Some(&tx.tx_hash) {
+            self.txs.push(tx.tx_hash);
+        }
+    }
+
+    // Tokens the remaining liquidity holds at `sqrt_price`, a Q64.64 sqrt
+    // price as found in swap events.
+    pub fn amounts_at(&self, sqrt_price: u128) -> Result<(u128, u128)> {
+        if self.liquidity == 0 {
+            return Ok((0, 0));
+        }
+        let (Some(lower), Some(upper)) = (self.tick_lower, self.tick_upper) else {
+            anyhow::bail!("tick range of {} is unknown", self.position);
+        };
+        amounts_for_liquidity(self.liquidity, lower, upper, sqrt_price)
+    }
+
+    pub fn value_at(&self, sqrt_price: u128) -> Result<Valuation> {
+        let (amount_a, amount_b) = self.amounts_at(sqrt_price)?;
+        let net_a = BigDecimal::from(amount_a + self.withdrawn_a + self.fees_a)
+            - BigDecimal::from(self.deposited_a);
+        let net_b = BigDecimal::from(amount_b + self.withdrawn_b + self.fees_b)
+            - BigDecimal::from(self.deposited_b);
+        Ok(Valuation {
+            sqrt_price,
+            amount_a,
+            amount_b,
+            pnl_in_b: net_a * price_from_sqrt(sqrt_price) + net_b,
+        })
+    }
+}
+
+// Follows CLMM positions across the decoded transactions of a wallet. The
+// last swap or liquidity change seen on each pool provides the price
+// positions are valued at.
+#[derive(Debug, Default)]
+pub struct PositionTracker {
+    positions: BTreeMap<String, PositionState>,
+    sqrt_prices: HashMap<String, u128>,
+}
+
+impl PositionTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Tracks `txs` oldest first, whatever order they were fetched in.
+    pub fn from_txs(txs: &[TransactionKind]) -> Self {
+        let mut txs = txs.iter().collect::<Vec<_>>();
+        txs.sort_by_key(|tx| tx.event_timestamp_ms);
+
+        let mut tracker = Self::new();
+        for tx in txs {
+            tracker.observe(tx);
+        }
+        tracker
+    }
+
+    pub fn observe(&mut self, tx: &TransactionKind) {
+        self.observe_type(tx, &tx.tx_type);
+    }
+
+    fn observe_type(&mut self, tx: &TransactionKind, tx_type: &TxType) {
+        let liquidity = match tx_type {
+            TxType::Swap(swap) => {
+                if let Ok(sqrt_price) = swap.after_sqrt_price.parse() {
+                    self.sqrt_prices.insert(swap.pool.clone(), sqrt_price);
+                }
+                return;
+            }
+            TxType::Rebalance(tx_types) => {
+                for tx_type in tx_types {
+                    self.observe_type(tx, tx_type);
+                }
+                return;
+            }
+            TxType::OpenPosition(l)
+            | TxType::ClosePosition(l)
+            | TxType::AddLiquidity(l)
+            | TxType::RemoveLiquidity(l)
+            | TxType::CollectFee(l)
+            | TxType::CollectReward(l) => l,
+            _ => return,
+        };
+
+        if let Some(sqrt_price) = &liquidity.sqrt_price {
+            if let Ok(sqrt_price) = sqrt_price.parse() {
+                self.sqrt_prices.insert(liquidity.pool.clone(), sqrt_price);
+            }
+        }
+        let state = self
+            .positions
+            .entry(liquidity.position.clone())
+            .or_insert_with(|| PositionState::new(liquidity));
+        state.apply(tx, liquidity);
+        match tx_type {
+            TxType::OpenPosition(_) => state.opened_at = Some(tx.event_timestamp_ms),
+            TxType::ClosePosition(_) => state.closed_at = Some(tx.event_timestamp_ms),
+            _ => {}
+        }
+    }
+
+    pub fn positions(&self) -> impl Iterator<Item = &PositionState> {
+        self.positions.values()
+    }
+
+    pub fn position(&self, id: &str) -> Option<&PositionState> {
+        self.positions.get(id)
+    }
+
+    // Last sqrt price seen in a swap or a liquidity change on `pool`.
+    pub fn sqrt_price(&self, pool: &str) -> Option<u128> {
+        self.sqrt_prices.get(pool).copied()
+    }
+
+    // Values a position at the last price seen on its pool.
+    pub fn valuation(&self, id: &str) -> Result<Valuation> {
+        let state = self
+            .position(id)
+            .with_context(|| format!("position {id} is not tracked"))?;
+        let sqrt_price = self
+            .sqrt_price(&state.pool)
+            .with_context(|| format!("no price seen for pool {}", state.pool))?;
+        state.value_at(sqrt_price)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bigdecimal::BigDecimal;
+    use sui_types::digests::TransactionDigest;
+
+    use super::{PositionTracker, amounts_for_liquidity, price_from_sqrt};
+    use crate::{Dex, Liquidity, Reward, Swap, TransactionKind, TxType};
+
+    const POOL: &str = "0xaf56fbf03d7b882c9115e773af722bf3427882bf2f402d950b1ce95809dd4507";
+    const POSITION: &str = "0x3b1012a6e39b65d1c3b3ea44448aaf4b7f47548de9d85a73ed7481cbd47ea613";
+    // tick 0, a price of 1
+    const SQRT_PRICE_ONE: u128 = 1 << 64;
+
+    fn tx(timestamp_ms: u64, tx_type: TxType) -> TransactionKind {
+        TransactionKind {
+            tx_type,
+            tx_hash: TransactionDigest::random(),
+            event_timestamp_ms: timestamp_ms,
+        }
+    }
+
+    fn liquidity(delta: i128, amount_a: u64, amount_b: u64) -> Liquidity {
+        Liquidity {
+            dex: Dex::Cetus,
+            pool: POOL.to_string(),
+            position: POSITION.to_string(),
+            tick_lower: Some(-100),
+            tick_upper: Some(100),
+            liquidity_delta: delta,
+            added_a: if delta >= 0 { amount_a } else { 0 },
+            added_b: if delta >= 0 { amount_b } else { 0 },
+            removed_a: if delta < 0 { amount_a } else { 0 },
+            removed_b: if delta < 0 { amount_b } else { 0 },
+            fee_a: 0,
+            fee_b: 0,
+            rewards: vec![],
+            sqrt_price: None,
+        }
+    }
+
+    fn swap(after_sqrt_price: u128) -> TxType {
+        TxType::Swap(Swap {
+            pool: POOL.to_string(),
+            dex: Dex::Cetus,
+            a2b: true,
+            in_amount: 1,
+            out_amount: 1,
+            in_token: "0x2::sui::SUI".to_string(),
+            out_token: "0x2::sui::SUI".to_string(),
+            before_sqrt_price: after_sqrt_price.to_string(),
+            after_sqrt_price: after_sqrt_price.to_string(),
+        })
+    }
+
+    #[test]
+    fn test_amounts_for_liquidity() {
+        let liquidity = 1_000_000_000_000;
+        let (a, b) = amounts_for_liquidity(liquidity, -100, 100, SQRT_PRICE_ONE).unwrap();
+        // symmetric range around the price: about L * (1 - 1.0001^-50) of each
+        assert!(a.abs_diff(b) < a / 1000, "{a} {b}");
+        assert!((4_980_000_000..5_000_000_000).contains(&a), "{a}");
+
+        // out of range the position holds a single token
+        let below = SQRT_PRICE_ONE / 2;
+        let above = SQRT_PRICE_ONE * 2;
+        assert_eq!(
+            amounts_for_liquidity(liquidity, -100, 100, below)
+                .unwrap()
+                .1,
+            0
+        );
+        assert_eq!(
+            amounts_for_liquidity(liquidity, -100, 100, above)
+                .unwrap()
+                .0,
+            0
+        );
+    }
+
+    #[test]
+    fn test_price_from_sqrt() {
+        assert_eq!(price_from_sqrt(SQRT_PRICE_ONE), BigDecimal::from(1));
+        assert_eq!(price_from_sqrt(SQRT_PRICE_ONE * 3), BigDecimal::from(9));
+    }
+
+    #[test]
+    fn test_position_lifecycle() {
+        let (a, b) = amounts_for_liquidity(1_000_000_000_000, -100, 100, SQRT_PRICE_ONE).unwrap();
+        let mut fees = liquidity(0, 0, 0);
+        fees.fee_a = 1_000;
+        fees.fee_b = 2_000;
+        let mut reward = liquidity(0, 0, 0);
+        reward.rewards.push(Reward {
+            coin_type: None,
+            amount: 5,
+        });
+
+        let txs = vec![
+            // fetched newest first
+            tx(
+                4,
+                TxType::ClosePosition(liquidity(-1_000_000_000_000, a as u64, b as u64)),
+            ),
+            tx(
+                3,
+                TxType::Rebalance(vec![
+                    TxType::CollectFee(fees),
+                    TxType::CollectReward(reward),
+                ]),
+            ),
+            tx(2, swap(SQRT_PRICE_ONE)),
+            tx(
+                1,
+                TxType::OpenPosition(liquidity(1_000_000_000_000, a as u64, b as u64)),
+            ),
+        ];
+        let tracker = PositionTracker::from_txs(&txs[1..]);
+        let state = tracker.position(POSITION).unwrap();
+        assert!(state.is_open());
+        assert_eq!(state.opened_at, Some(1));
+        assert_eq!((state.deposited_a, state.deposited_b), (a, b));
+        assert_eq!((state.fees_a, state.fees_b), (1_000, 2_000));
+        assert_eq!(state.rewards["unknown"], 5);
+
+        // unchanged price: the pnl is the fees
+        let valuation = tracker.valuation(POSITION).unwrap();
+        assert_eq!((valuation.amount_a, valuation.amount_b), (a, b));
+        assert_eq!(valuation.pnl_in_b, BigDecimal::from(3_000));
+
+        let tracker = PositionTracker::from_txs(&txs);
+        let state = tracker.position(POSITION).unwrap();
+        assert_eq!(state.closed_at, Some(4));
+        assert_eq!(state.liquidity, 0);
+        assert_eq!((state.withdrawn_a, state.withdrawn_b), (a, b));
+        assert_eq!(state.txs.len(), 3);
+        assert_eq!(
+            state.value_at(SQRT_PRICE_ONE).unwrap().pnl_in_b,
+            BigDecimal::from(3_000)
+        );
+    }
+
+    #[test]
+    fn test_price_from_liquidity_event() {
+        let mut open = liquidity(1_000_000_000_000, 1, 1);
+        open.sqrt_price = Some(SQRT_PRICE_ONE.to_string());
+        let tracker = PositionTracker::from_txs(&[tx(1, TxType::OpenPosition(open))]);
+        assert_eq!(tracker.sqrt_price(POOL), Some(SQRT_PRICE_ONE));
+        assert!(tracker.valuation(POSITION).is_ok());
+    }
+}
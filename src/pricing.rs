@@ -0,0 +1,73 @@
+use bigdecimal::BigDecimal;
+use move_core_types::language_storage::TypeTag;
+use std::{collections::BTreeMap, str::FromStr};
+
+use crate::config::PricingConfig;
+
+// Short form of a coin type, so `0x2::sui::SUI` and its long form are the
+// same key.
+pub fn normalize_coin_type(coin_type: &str) -> String {
+    TypeTag::from_str(coin_type)
+        .map(|t| t.to_string())
+        .unwrap_or_else(|_| coin_type.to_string())
+}
+
+pub trait PriceSource {
+    // USD price of one whole `coin_type` token at `timestamp_ms`.
+    fn usd_price(&self, coin_type: &str, timestamp_ms: u64) -> Option<BigDecimal>;
+}
+
+// Fixed prices, whatever the time.
+#[derive(Debug, Clone, Default)]
+pub struct StaticPrices {
+    prices: BTreeMap<String, BigDecimal>,
+}
+
+impl StaticPrices {
+    pub fn new(prices: BTreeMap<String, BigDecimal>) -> Self {
+        Self {
+            prices: prices
+                .into_iter()
+                .map(|(coin_type, price)| (normalize_coin_type(&coin_type), price))
+                .collect(),
+        }
+    }
+
+    // The static prices of a `[[pricing]]` list, if it has any.
+    pub fn from_config(pricing: &[PricingConfig]) -> Option<Self> {
+        pricing
+            .first()
+            .map(|PricingConfig::Static { prices }| Self::new(prices.clone()))
+    }
+}
+
+impl PriceSource for StaticPrices {
+    fn usd_price(&self, coin_type: &str, _timestamp_ms: u64) -> Option<BigDecimal> {
+        self.prices.get(&normalize_coin_type(coin_type)).cloned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bigdecimal::BigDecimal;
+    use std::str::FromStr;
+
+    use super::{PriceSource, StaticPrices};
+    use crate::config::PricingConfig;
+
+    #[test]
+    fn test_static_prices() {
+        let pricing = vec![PricingConfig::Static {
+            prices: [(
+                "0x2::sui::SUI".to_string(),
+                BigDecimal::from_str("3.5").unwrap(),
+            )]
+            .into(),
+        }];
+        let prices = StaticPrices::from_config(&pricing).unwrap();
+
+        let long = "0x0000000000000000000000000000000000000000000000000000000000000002::sui::SUI";
+        assert_eq!(prices.usd_price(long, 0), BigDecimal::from_str("3.5").ok());
+        assert_eq!(prices.usd_price("0x2::coin::COIN", 0), None);
+    }
+}
@@ -0,0 +1,126 @@
+use serde_json::Value;
+use std::collections::BTreeSet;
+use sui_sdk::rpc_types::{
+    SuiArgument, SuiCallArg, SuiCommand, SuiProgrammableTransactionBlock,
+    SuiTransactionBlockDataAPI, SuiTransactionBlockKind, SuiTransactionBlockResponse,
+};
+use sui_types::base_types::{ObjectID, SuiAddress};
+
+use crate::{CoinManagement, MoveCall, ObjectTransfer, TxType};
+
+pub fn programmable(
+    tx_resp: &SuiTransactionBlockResponse,
+) -> Option<&SuiProgrammableTransactionBlock> {
+    match tx_resp.transaction.as_ref()?.data.transaction() {
+        SuiTransactionBlockKind::ProgrammableTransaction(ptb) => Some(ptb),
+        _ => None,
+    }
+}
+
+pub fn sender(tx_resp: &SuiTransactionBlockResponse) -> Option<SuiAddress> {
+    tx_resp.transaction.as_ref().map(|tx| *tx.data.sender())
+}
+
+// The address a pure input holds, e.g. the recipient of `TransferObjects`.
+fn pure_address(ptb: &SuiProgrammableTransactionBlock, arg: &SuiArgument) -> Option<String> {
+    let SuiArgument::Input(idx) = arg else {
+        return None;
+    };
+    let input = ptb.inputs.get(*idx as usize)?;
+    if !matches!(input, SuiCallArg::Pure(_)) {
+        return None;
+    }
+    let value = serde_json::to_value(input).ok()?;
+    value
+        .get("value")
+        .and_then(Value::as_str)
+        .map(str::to_string)
+}
+
+// Classifies a transaction from its PTB commands alone. Used when it emitted
+// no events, or none that a decoder recognises. `known_packages` maps package
+// ids to the protocol name reported for calls into them.
+pub fn classify(
+    tx_resp: &SuiTransactionBlockResponse,
+    known_packages: &[(ObjectID, String)],
+) -> Option<TxType> {
+    let ptb = programmable(tx_resp)?;
+    let sender = sender(tx_resp)?;
+
+    let mut splits = 0;
+    let mut merges = 0;
+    let mut recipients = BTreeSet::new();
+    let mut transferred = 0;
+    // recipients computed by an earlier command, their address is unknown
+    let mut unknown_recipients = 0;
+    let mut calls = vec![];
+    for command in &ptb.commands {
+        match command {
+            SuiCommand::SplitCoins(_, amounts) => splits += amounts.len(),
+            SuiCommand::MergeCoins(_, coins) => merges += coins.len(),
+            SuiCommand::TransferObjects(objects, recipient) => {
+                transferred += objects.len();
+                match pure_address(ptb, recipient) {
+                    Some(recipient) => {
+                        recipients.insert(recipient);
+                    }
+                    None => unknown_recipients += 1,
+                }
+            }
+            SuiCommand::MoveCall(call) => calls.push(call),
+            _ => {}
+        }
+    }
+
+    let known_call = calls.iter().find_map(|call| {
+        known_packages
+            .iter()
+            .find(|(package, _)| *package == call.package)
+            .map(|(_, protocol)| MoveCall {
+                protocol: protocol.clone(),
+                package: call.package.to_string(),
+                module: call.module.clone(),
+                function: call.function.clone(),
+            })
+    });
+    if let Some(call) = known_call {
+        return Some(TxType::MoveCall(call));
+    }
+    if !calls.is_empty() {
+        return None;
+    }
+
+    let sender_str = sender.to_string();
+    recipients.remove(&sender_str);
+    if recipients.is_empty() {
+        if splits + merges > 0 && unknown_recipients == 0 {
+            return Some(TxType::CoinManagement(CoinManagement {
+                owner: sender_str,
+                splits,
+                merges,
+            }));
+        }
+        return None;
+    }
+
+    // with the object changes fetched `objects::decode` tells what moved, and
+    // runs first. This only covers responses without them.
+    if tx_resp.object_changes.is_some() {
+        return None;
+    }
+    // Coins moving to someone else show up in the balance changes and are
+    // decoded as a regular transfer; only gas left means plain objects moved.
+    let only_gas = tx_resp.balance_changes.as_ref().is_some_and(|changes| {
+        changes
+            .iter()
+            .all(|c| c.owner.get_owner_address().ok() == Some(sender))
+    });
+    if only_gas {
+        return Some(TxType::ObjectTransfer(ObjectTransfer {
+            sender: sender_str,
+            recipients: recipients.into_iter().collect(),
+            objects: transferred,
+        }));
+    }
+    None
+}
@@ -0,0 +1,279 @@
+use anyhow::{Context, Result, anyhow};
+use flate2::{Compression, read::GzDecoder, write::GzEncoder};
+use futures::future::BoxFuture;
+use mini_macro::here as h;
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+use serde_json::{Value, json};
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{BufRead, BufReader, BufWriter, Write},
+    path::Path,
+    sync::Mutex,
+};
+use sui_sdk::rpc_types::{
+    EventFilter, EventPage, ObjectsPage, SuiObjectDataOptions, SuiObjectResponse,
+    SuiObjectResponseQuery, SuiTransactionBlockResponse, SuiTransactionBlockResponseOptions,
+    SuiTransactionBlockResponseQuery, TransactionBlocksPage,
+};
+use sui_types::{
+    base_types::{ObjectID, SuiAddress},
+    digests::TransactionDigest,
+    event::EventID,
+};
+
+use crate::source::TransactionSource;
+
+// One line of the archive. `result` is the JSON-RPC result exactly as the
+// node sent it, so the replay deserializes the very same bytes, fields the
+// SDK types don't know included. Sources that don't speak JSON-RPC only have
+// their typed results to record.
+#[derive(Debug, Serialize, Deserialize)]
+struct Entry {
+    method: String,
+    params: Value,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    result: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+fn key(method: &str, params: &Value) -> String {
+    format!("{method}{params}")
+}
+
+// Wraps a source and appends every call it serves to a gzip compressed
+// JSON lines archive.
+pub struct RecordingSource {
+    inner: Box<dyn TransactionSource>,
+    // taken by `finish`
+    writer: Mutex<Option<GzEncoder<BufWriter<File>>>>,
+}
+
+impl RecordingSource {
+    pub fn create(inner: Box<dyn TransactionSource>, path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let file =
+            File::create(path).with_context(|| format!("create archive {}", path.display()))?;
+        Ok(Self {
+            inner,
+            writer: Mutex::new(Some(GzEncoder::new(
+                BufWriter::new(file),
+                Compression::default(),
+            ))),
+        })
+    }
+
+    async fn record<T: Serialize + DeserializeOwned>(
+        &self,
+        method: &str,
+        params: Value,
+        call: BoxFuture<'_, Result<T>>,
+    ) -> Result<T> {
+        let raw = match self.inner.call_raw(method, params.clone()) {
+            Some(raw_call) => raw_call.await,
+            None => call
+                .await
+                .and_then(|value| serde_json::to_string(&value).context(h!())),
+        };
+        let entry = Entry {
+            method: method.to_string(),
+            params,
+            result: raw.as_ref().ok().cloned(),
+            error: raw.as_ref().err().map(|e| format!("{e:?}")),
+        };
+
+        {
+            let mut writer = self.writer.lock().unwrap();
+            let writer = writer.as_mut().context("archive already finished")?;
+            serde_json::to_writer(&mut *writer, &entry).context(h!())?;
+            writer.write_all(b"\n").context(h!())?;
+        }
+
+        // decode from the recorded text so recording and replay runs see
+        // exactly the same data
+        serde_json::from_str(&raw?).with_context(|| format!("decode {method}"))
+    }
+}
+
+impl TransactionSource for RecordingSource {
+    fn query_transaction_blocks<'a>(
+        &'a self,
+        query: &'a SuiTransactionBlockResponseQuery,
+        cursor: Option<TransactionDigest>,
+        limit: Option<usize>,
+        descending_order: bool,
+    ) -> BoxFuture<'a, Result<TransactionBlocksPage>> {
+        Box::pin(
+            self.record(
+                "suix_queryTransactionBlocks",
+                json!([query, cursor, limit, descending_order]),
+                self.inner
+                    .query_transaction_blocks(query, cursor, limit, descending_order),
+            ),
+        )
+    }
+
+    fn get_transaction_block<'a>(
+        &'a self,
+        digest: TransactionDigest,
+        options: &'a SuiTransactionBlockResponseOptions,
+    ) -> BoxFuture<'a, Result<SuiTransactionBlockResponse>> {
+        Box::pin(self.record(
+            "sui_getTransactionBlock",
+            json!([digest, options]),
+            self.inner.get_transaction_block(digest, options),
+        ))
+    }
+
+    fn get_object<'a>(
+        &'a self,
+        object_id: ObjectID,
+        options: &'a SuiObjectDataOptions,
+    ) -> BoxFuture<'a, Result<SuiObjectResponse>> {
+        Box::pin(self.record(
+            "sui_getObject",
+            json!([object_id, options]),
+            self.inner.get_object(object_id, options),
+        ))
+    }
+
+    fn query_events<'a>(
+        &'a self,
+        query: &'a EventFilter,
+        cursor: Option<EventID>,
+        limit: Option<usize>,
+        descending_order: bool,
+    ) -> BoxFuture<'a, Result<EventPage>> {
+        Box::pin(
+            self.record(
+                "suix_queryEvents",
+                json!([query, cursor, limit, descending_order]),
+                self.inner
+                    .query_events(query, cursor, limit, descending_order),
+            ),
+        )
+    }
+
+    fn get_owned_objects<'a>(
+        &'a self,
+        address: SuiAddress,
+        query: &'a SuiObjectResponseQuery,
+        cursor: Option<ObjectID>,
+        limit: Option<usize>,
+    ) -> BoxFuture<'a, Result<ObjectsPage>> {
+        Box::pin(self.record(
+            "suix_getOwnedObjects",
+            json!([address, query, cursor, limit]),
+            self.inner.get_owned_objects(address, query, cursor, limit),
+        ))
+    }
+
+    // Completes the gzip stream. Without it the archive is only finalized
+    // when the source is dropped, and errors doing so are lost.
+    fn finish(&self) -> Result<()> {
+        let Some(writer) = self.writer.lock().unwrap().take() else {
+            return Ok(());
+        };
+        writer.finish().context(h!())?.flush().context(h!())?;
+        self.inner.finish()
+    }
+}
+
+// Serves the calls of a recorded archive. A call that was not recorded is an
+// error rather than a trip to the network.
+pub struct ReplaySource {
+    entries: HashMap<String, Entry>,
+}
+
+impl ReplaySource {
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let file = File::open(path).with_context(|| format!("open archive {}", path.display()))?;
+
+        let mut entries = HashMap::new();
+        for (i, line) in BufReader::new(GzDecoder::new(file)).lines().enumerate() {
+            let line = line.with_context(|| format!("read archive {}", path.display()))?;
+            let entry: Entry = serde_json::from_str(&line)
+                .with_context(|| format!("{}:{}: invalid entry", path.display(), i + 1))?;
+            entries
+                .entry(key(&entry.method, &entry.params))
+                .or_insert(entry);
+        }
+        Ok(Self { entries })
+    }
+
+    fn replay<T: DeserializeOwned>(&self, method: &str, params: Value) -> Result<T> {
+        let entry = self
+            .entries
+            .get(&key(method, &params))
+            .with_context(|| format!("{method} {params} is not in the archive"))?;
+        match (&entry.result, &entry.error) {
+            (Some(raw), _) => serde_json::from_str(raw).with_context(|| format!("decode {method}")),
+            (None, Some(error)) => Err(anyhow!("recorded error: {error}")),
+            (None, None) => Err(anyhow!("empty archive entry for {method}")),
+        }
+    }
+}
+
+impl TransactionSource for ReplaySource {
+    fn query_transaction_blocks<'a>(
+        &'a self,
+        query: &'a SuiTransactionBlockResponseQuery,
+        cursor: Option<TransactionDigest>,
+        limit: Option<usize>,
+        descending_order: bool,
+    ) -> BoxFuture<'a, Result<TransactionBlocksPage>> {
+        let res = self.replay(
+            "suix_queryTransactionBlocks",
+            json!([query, cursor, limit, descending_order]),
+        );
+        Box::pin(async move { res })
+    }
+
+    fn get_transaction_block<'a>(
+        &'a self,
+        digest: TransactionDigest,
+        options: &'a SuiTransactionBlockResponseOptions,
+    ) -> BoxFuture<'a, Result<SuiTransactionBlockResponse>> {
+        let res = self.replay("sui_getTransactionBlock", json!([digest, options]));
+        Box::pin(async move { res })
+    }
+
+    fn get_object<'a>(
+        &'a self,
+        object_id: ObjectID,
+        options: &'a SuiObjectDataOptions,
+    ) -> BoxFuture<'a, Result<SuiObjectResponse>> {
+        let res = self.replay("sui_getObject", json!([object_id, options]));
+        Box::pin(async move { res })
+    }
+
+    fn query_events<'a>(
+        &'a self,
+        query: &'a EventFilter,
+        cursor: Option<EventID>,
+        limit: Option<usize>,
+        descending_order: bool,
+    ) -> BoxFuture<'a, Result<EventPage>> {
+        let res = self.replay(
+            "suix_queryEvents",
+            json!([query, cursor, limit, descending_order]),
+        );
+        Box::pin(async move { res })
+    }
+
+    fn get_owned_objects<'a>(
+        &'a self,
+        address: SuiAddress,
+        query: &'a SuiObjectResponseQuery,
+        cursor: Option<ObjectID>,
+        limit: Option<usize>,
+    ) -> BoxFuture<'a, Result<ObjectsPage>> {
+        let res = self.replay(
+            "suix_getOwnedObjects",
+            json!([address, query, cursor, limit]),
+        );
+        Box::pin(async move { res })
+    }
+}
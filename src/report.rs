@@ -0,0 +1,531 @@
+use anyhow::{Context, Result};
+use bigdecimal::{BigDecimal, Zero};
+use mini_macro::here as h;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use sui_sdk::rpc_types::{SuiObjectDataOptions, SuiParsedData};
+
+use crate::{
+    Dex, TransactionKind,
+    position::{PositionState, PositionTracker},
+    pricing::{PriceSource, normalize_coin_type},
+    source::TransactionSource,
+};
+
+const YEAR_MS: u64 = 365 * 24 * 60 * 60 * 1000;
+const DAY_MS: u64 = 24 * 60 * 60 * 1000;
+
+// Bounds of a report, in milliseconds. Open ends reach the first or last
+// transaction.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TimeWindow {
+    pub from_ms: Option<u64>,
+    pub to_ms: Option<u64>,
+}
+
+impl TimeWindow {
+    pub fn between(from_ms: u64, to_ms: u64) -> Self {
+        Self {
+            from_ms: Some(from_ms),
+            to_ms: Some(to_ms),
+        }
+    }
+
+    pub fn last_days(days: u64, now_ms: u64) -> Self {
+        Self::between(now_ms.saturating_sub(days * DAY_MS), now_ms)
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PositionReport {
+    pub position: String,
+    pub pool: String,
+    pub dex: Dex,
+    pub open: bool,
+    // deposits at the prices of the time they were made
+    pub deposited_usd: BigDecimal,
+    // the deposited tokens, had they been held instead
+    pub hodl_usd: BigDecimal,
+    // withdrawals plus what the position still holds
+    pub lp_usd: BigDecimal,
+    // `lp_usd - hodl_usd`, negative for a loss
+    pub impermanent_loss_usd: BigDecimal,
+    pub impermanent_loss_pct: Option<BigDecimal>,
+    // fees collected inside the window
+    pub fees_usd: BigDecimal,
+    pub rewards_usd: BigDecimal,
+    pub fee_apr_pct: Option<BigDecimal>,
+    // lifetime outcome: `lp_usd` plus all fees and rewards minus deposits
+    pub net_pnl_usd: BigDecimal,
+    // time the position was open inside the window
+    pub active_ms: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PoolReport {
+    pub pool: String,
+    pub positions: usize,
+    pub deposited_usd: BigDecimal,
+    pub hodl_usd: BigDecimal,
+    pub lp_usd: BigDecimal,
+    pub impermanent_loss_usd: BigDecimal,
+    pub fees_usd: BigDecimal,
+    pub rewards_usd: BigDecimal,
+    // capital and time weighted over the pool's positions
+    pub fee_apr_pct: Option<BigDecimal>,
+    pub net_pnl_usd: BigDecimal,
+}
+
+// Values are taken at the prices of the end of the window.
+#[derive(Debug, Clone, Serialize)]
+pub struct LpReport {
+    pub window: TimeWindow,
+    pub positions: Vec<PositionReport>,
+    pub pools: Vec<PoolReport>,
+    // positions that could not be valued, with the reason
+    pub skipped: Vec<(String, String)>,
+}
+
+// Compares providing liquidity against holding the deposited tokens. Pools
+// must be registered with their coin types, and every coin with its
+// decimals, for their positions to be valued. Open positions are valued at
+// the last price the wallet's transactions saw on their pool, or at the one
+// registered with `sqrt_price` when they saw none.
+pub struct LpReportBuilder<'a> {
+    prices: &'a dyn PriceSource,
+    pools: HashMap<String, (String, String)>,
+    decimals: HashMap<String, u32>,
+    sqrt_prices: HashMap<String, u128>,
+    window: TimeWindow,
+}
+
+// The current sqrt price of a Cetus, Bluefin or Magma pool, read from the
+// pool object.
+pub async fn pool_sqrt_price(source: &dyn TransactionSource, pool: &str) -> Result<u128> {
+    let options = SuiObjectDataOptions::new().with_content();
+    let object = source
+        .get_object(pool.parse().context(h!())?, &options)
+        .await
+        .context(h!())?;
+    let Some(SuiParsedData::MoveObject(content)) = object.data.and_then(|data| data.content) else {
+        anyhow::bail!("pool {pool} has no content");
+    };
+    let fields = content.fields.to_json_value();
+    let sqrt_price = fields
+        .get("current_sqrt_price")
+        .and_then(|price| price.as_str())
+        .with_context(|| format!("pool {pool} has no current_sqrt_price"))?;
+    sqrt_price.parse().context(h!())
+}
+
+fn usd(amount: u128, decimals: u32, price: &BigDecimal) -> BigDecimal {
+    BigDecimal::from(amount) / BigDecimal::from(10u128.pow(decimals)) * price
+}
+
+fn pct(part: &BigDecimal, whole: &BigDecimal) -> Option<BigDecimal> {
+    (!whole.is_zero()).then(|| (part / whole * BigDecimal::from(100)).round(4))
+}
+
+impl<'a> LpReportBuilder<'a> {
+    pub fn new(prices: &'a dyn PriceSource) -> Self {
+        Self {
+            prices,
+            pools: HashMap::new(),
+            decimals: HashMap::new(),
+            sqrt_prices: HashMap::new(),
+            window: TimeWindow::default(),
+        }
+    }
+
+    pub fn pool(mut self, pool: &str, coin_a: &str, coin_b: &str) -> Self {
+        self.pools.insert(
+            pool.to_string(),
+            (normalize_coin_type(coin_a), normalize_coin_type(coin_b)),
+        );
+        self
+    }
+
+    pub fn decimals(mut self, coin_type: &str, decimals: u32) -> Self {
+        self.decimals
+            .insert(normalize_coin_type(coin_type), decimals);
+        self
+    }
+
+    pub fn sqrt_price(mut self, pool: &str, sqrt_price: u128) -> Self {
+        self.sqrt_prices.insert(pool.to_string(), sqrt_price);
+        self
+    }
+
+    pub fn window(mut self, window: TimeWindow) -> Self {
+        self.window = window;
+        self
+    }
+
+    pub fn build(&self, txs: &[TransactionKind]) -> LpReport {
+        let first_ms = txs
+            .iter()
+            .map(|tx| tx.event_timestamp_ms)
+            .min()
+            .unwrap_or(0);
+        let last_ms = txs
+            .iter()
+            .map(|tx| tx.event_timestamp_ms)
+            .max()
+            .unwrap_or(0);
+        let from_ms = self.window.from_ms.unwrap_or(first_ms);
+        let to_ms = self.window.to_ms.unwrap_or(last_ms);
+
+        let until = |bound: u64, inclusive: bool| {
+            txs.iter()
+                .filter(|tx| {
+                    tx.event_timestamp_ms < bound || (inclusive && tx.event_timestamp_ms == bound)
+                })
+                .cloned()
+                .collect::<Vec<_>>()
+        };
+        let before = PositionTracker::from_txs(&until(from_ms, false));
+        let after = PositionTracker::from_txs(&until(to_ms, true));
+
+        let mut report = LpReport {
+            window: self.window,
+            positions: vec![],
+            pools: vec![],
+            skipped: vec![],
+        };
+        for state in after.positions() {
+            if state.closed_at.is_some_and(|closed| closed < from_ms) {
+                continue;
+            }
+            let start = before.position(&state.position);
+            match self.position_report(state, start, &after, from_ms, to_ms) {
+                Ok(position) => report.positions.push(position),
+                Err(reason) => report.skipped.push((state.position.clone(), reason)),
+            }
+        }
+        report.pools = pool_reports(&report.positions);
+        report
+    }
+
+    fn price(&self, coin_type: &str, timestamp_ms: u64) -> Result<(u32, BigDecimal), String> {
+        let decimals = *self
+            .decimals
+            .get(coin_type)
+            .ok_or_else(|| format!("decimals of {coin_type} are unknown"))?;
+        let price = self
+            .prices
+            .usd_price(coin_type, timestamp_ms)
+            .ok_or_else(|| format!("no price for {coin_type}"))?;
+        Ok((decimals, price))
+    }
+
+    fn position_report(
+        &self,
+        end: &PositionState,
+        start: Option<&PositionState>,
+        tracker: &PositionTracker,
+        from_ms: u64,
+        to_ms: u64,
+    ) -> Result<PositionReport, String> {
+        let (coin_a, coin_b) = self
+            .pools
+            .get(&end.pool)
+            .ok_or_else(|| format!("coins of pool {} are unknown", end.pool))?;
+        let (decimals_a, price_a) = self.price(coin_a, to_ms)?;
+        let (decimals_b, price_b) = self.price(coin_b, to_ms)?;
+        let value = |a: u128, b: u128| usd(a, decimals_a, &price_a) + usd(b, decimals_b, &price_b);
+
+        let (held_a, held_b) = if end.liquidity == 0 {
+            (0, 0)
+        } else {
+            let sqrt_price = tracker
+                .sqrt_price(&end.pool)
+                .or_else(|| self.sqrt_prices.get(&end.pool).copied())
+                .ok_or_else(|| format!("no price seen for pool {}", end.pool))?;
+            end.amounts_at(sqrt_price).map_err(|e| e.to_string())?
+        };
+
+        let opened_ms = end.opened_at.unwrap_or(from_ms);
+        let (_, open_price_a) = self.price(coin_a, opened_ms)?;
+        let (_, open_price_b) = self.price(coin_b, opened_ms)?;
+        let deposited_usd = usd(end.deposited_a, decimals_a, &open_price_a)
+            + usd(end.deposited_b, decimals_b, &open_price_b);
+
+        let hodl_usd = value(end.deposited_a, end.deposited_b);
+        let lp_usd = value(held_a + end.withdrawn_a, held_b + end.withdrawn_b);
+        let impermanent_loss_usd = &lp_usd - &hodl_usd;
+
+        let (start_fees_a, start_fees_b) = start.map_or((0, 0), |s| (s.fees_a, s.fees_b));
+        let fees_usd = value(end.fees_a - start_fees_a, end.fees_b - start_fees_b);
+        let all_fees_usd = value(end.fees_a, end.fees_b);
+
+        // rewards in coins without a price or decimals are left out
+        let rewards_usd = end
+            .rewards
+            .iter()
+            .filter_map(|(coin_type, amount)| {
+                let (decimals, price) = self.price(&normalize_coin_type(coin_type), to_ms).ok()?;
+                Some(usd(*amount, decimals, &price))
+            })
+            .fold(BigDecimal::zero(), |sum, v| sum + v);
+
+        let active_from = from_ms.max(opened_ms);
+        let active_to = to_ms.min(end.closed_at.unwrap_or(to_ms));
+        let active_ms = active_to.saturating_sub(active_from);
+        let fee_apr_pct = (active_ms > 0)
+            .then(|| {
+                let yearly = &fees_usd * BigDecimal::from(YEAR_MS) / BigDecimal::from(active_ms);
+                pct(&yearly, &deposited_usd)
+            })
+            .flatten();
+
+        Ok(PositionReport {
+            position: end.position.clone(),
+            pool: end.pool.clone(),
+            dex: end.dex,
+            open: end.is_open(),
+            impermanent_loss_pct: pct(&impermanent_loss_usd, &hodl_usd),
+            net_pnl_usd: &lp_usd + all_fees_usd + &rewards_usd - &deposited_usd,
+            deposited_usd,
+            hodl_usd,
+            lp_usd,
+            impermanent_loss_usd,
+            fees_usd,
+            rewards_usd,
+            fee_apr_pct,
+            active_ms,
+        })
+    }
+}
+
+fn pool_reports(positions: &[PositionReport]) -> Vec<PoolReport> {
+    let mut pools: BTreeMap<&str, Vec<&PositionReport>> = BTreeMap::new();
+    for position in positions {
+        pools
+            .entry(position.pool.as_str())
+            .or_default()
+            .push(position);
+    }
+
+    pools
+        .into_iter()
+        .map(|(pool, positions)| {
+            let sum = |f: fn(&PositionReport) -> &BigDecimal| {
+                positions
+                    .iter()
+                    .fold(BigDecimal::zero(), |total, p| total + f(p))
+            };
+            let capital_ms = positions.iter().fold(BigDecimal::zero(), |total, p| {
+                total + &p.deposited_usd * BigDecimal::from(p.active_ms)
+            });
+            let fees_usd = sum(|p| &p.fees_usd);
+            PoolReport {
+                pool: pool.to_string(),
+                positions: positions.len(),
+                deposited_usd: sum(|p| &p.deposited_usd),
+                hodl_usd: sum(|p| &p.hodl_usd),
+                lp_usd: sum(|p| &p.lp_usd),
+                impermanent_loss_usd: sum(|p| &p.impermanent_loss_usd),
+                fee_apr_pct: pct(&(&fees_usd * BigDecimal::from(YEAR_MS)), &capital_ms),
+                fees_usd,
+                rewards_usd: sum(|p| &p.rewards_usd),
+                net_pnl_usd: sum(|p| &p.net_pnl_usd),
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use bigdecimal::{BigDecimal, Zero};
+    use serde_json::json;
+    use sui_sdk::rpc_types::SuiObjectResponse;
+    use sui_types::digests::TransactionDigest;
+
+    use super::{LpReportBuilder, TimeWindow, YEAR_MS, pool_sqrt_price};
+    use crate::{
+        Dex, Liquidity, Swap, TransactionKind, TxType, position::amounts_for_liquidity,
+        pricing::StaticPrices, source::RecordedSource,
+    };
+
+    const POOL: &str = "0xaf56fbf03d7b882c9115e773af722bf3427882bf2f402d950b1ce95809dd4507";
+    const COIN_A: &str = "0x2::sui::SUI";
+    const COIN_B: &str =
+        "0xdba34672e30cb065b1f93e3ab55318768fd6fef66c15942c9f7cb846e2f900e7::usdc::USDC";
+    const SQRT_PRICE_ONE: u128 = 1 << 64;
+    const LIQUIDITY: u128 = 1_000_000_000_000;
+
+    fn tx(timestamp_ms: u64, tx_type: TxType) -> TransactionKind {
+        TransactionKind {
+            tx_type,
+            tx_hash: TransactionDigest::random(),
+            event_timestamp_ms: timestamp_ms,
+        }
+    }
+
+    fn liquidity(delta: i128, amount_a: u64, amount_b: u64, fee_b: u64) -> Liquidity {
+        Liquidity {
+            dex: Dex::Cetus,
+            pool: POOL.to_string(),
+            position: "0x1".to_string(),
+            tick_lower: Some(-100),
+            tick_upper: Some(100),
+            liquidity_delta: delta,
+            added_a: if delta >= 0 { amount_a } else { 0 },
+            added_b: if delta >= 0 { amount_b } else { 0 },
+            removed_a: if delta < 0 { amount_a } else { 0 },
+            removed_b: if delta < 0 { amount_b } else { 0 },
+            fee_a: 0,
+            fee_b,
+            rewards: vec![],
+            sqrt_price: None,
+        }
+    }
+
+    fn swap(after_sqrt_price: u128) -> TxType {
+        TxType::Swap(Swap {
+            pool: POOL.to_string(),
+            dex: Dex::Cetus,
+            a2b: false,
+            in_amount: 1,
+            out_amount: 1,
+            in_token: COIN_B.to_string(),
+            out_token: COIN_A.to_string(),
+            before_sqrt_price: SQRT_PRICE_ONE.to_string(),
+            after_sqrt_price: after_sqrt_price.to_string(),
+        })
+    }
+
+    fn txs() -> Vec<TransactionKind> {
+        let (a, b) = amounts_for_liquidity(LIQUIDITY, -100, 100, SQRT_PRICE_ONE).unwrap();
+        vec![
+            tx(
+                0,
+                TxType::OpenPosition(liquidity(LIQUIDITY as i128, a as u64, b as u64, 0)),
+            ),
+            // the price of a quadruples, leaving the position all in b
+            tx(1_000, swap(SQRT_PRICE_ONE * 2)),
+            tx(
+                YEAR_MS / 2,
+                TxType::CollectFee(liquidity(0, 0, 0, 50_000_000)),
+            ),
+        ]
+    }
+
+    fn builder(prices: &StaticPrices) -> LpReportBuilder<'_> {
+        LpReportBuilder::new(prices)
+            .pool(POOL, COIN_A, COIN_B)
+            .decimals(COIN_A, 6)
+            .decimals(COIN_B, 6)
+    }
+
+    fn prices() -> StaticPrices {
+        StaticPrices::new(
+            [
+                (COIN_A.to_string(), BigDecimal::from(4)),
+                (COIN_B.to_string(), BigDecimal::from(1)),
+            ]
+            .into(),
+        )
+    }
+
+    #[test]
+    fn test_impermanent_loss() {
+        let prices = prices();
+        let report = builder(&prices)
+            .window(TimeWindow::between(0, YEAR_MS))
+            .build(&txs());
+        assert!(report.skipped.is_empty(), "{:?}", report.skipped);
+
+        let position = &report.positions[0];
+        assert!(position.open);
+        assert!(position.impermanent_loss_usd < BigDecimal::zero());
+        assert_eq!(
+            position.impermanent_loss_usd,
+            &position.lp_usd - &position.hodl_usd
+        );
+        assert_eq!(position.fees_usd, BigDecimal::from(50));
+        assert_eq!(position.active_ms, YEAR_MS);
+        // open for the whole year: the apr is the fees over the deposits
+        assert_eq!(
+            position.fee_apr_pct,
+            Some((BigDecimal::from(5_000) / &position.deposited_usd).round(4))
+        );
+        assert_eq!(
+            position.net_pnl_usd,
+            &position.lp_usd + BigDecimal::from(50) - &position.deposited_usd
+        );
+
+        let pool = &report.pools[0];
+        assert_eq!(pool.positions, 1);
+        assert_eq!(pool.fee_apr_pct, position.fee_apr_pct);
+    }
+
+    #[test]
+    fn test_window_excludes_earlier_fees() {
+        let prices = prices();
+        let report = builder(&prices)
+            .window(TimeWindow::between(YEAR_MS / 2 + 1, YEAR_MS))
+            .build(&txs());
+
+        let position = &report.positions[0];
+        assert!(position.fees_usd.is_zero());
+        assert_eq!(position.fee_apr_pct, Some(BigDecimal::zero()));
+        // the lifetime pnl still counts them
+        assert_eq!(
+            position.net_pnl_usd,
+            &position.lp_usd + BigDecimal::from(50) - &position.deposited_usd
+        );
+    }
+
+    #[test]
+    fn test_unknown_pool_is_skipped() {
+        let prices = prices();
+        let report = LpReportBuilder::new(&prices).build(&txs());
+        assert!(report.positions.is_empty());
+        assert_eq!(report.skipped.len(), 1);
+    }
+
+    #[test]
+    fn test_registered_pool_price() {
+        let prices = prices();
+        let txs = txs()
+            .into_iter()
+            .filter(|tx| !matches!(tx.tx_type, TxType::Swap(_)))
+            .collect::<Vec<_>>();
+        assert_eq!(builder(&prices).build(&txs).skipped.len(), 1);
+
+        let report = builder(&prices)
+            .sqrt_price(POOL, SQRT_PRICE_ONE * 2)
+            .build(&txs);
+        assert!(report.skipped.is_empty(), "{:?}", report.skipped);
+        assert_eq!(
+            report.positions[0].lp_usd,
+            builder(&prices).build(&txs()).positions[0].lp_usd
+        );
+    }
+
+    #[tokio::test]
+    async fn test_pool_sqrt_price() {
+        let pool: SuiObjectResponse = serde_json::from_value(json!({
+            "data": {
+                "objectId": POOL,
+                "version": "1",
+                "digest": "8RBsoeyoRwajj86MZfZE6gMDJQVYGYcdSfx1zxqxNHbr",
+                "content": {
+                    "dataType": "moveObject",
+                    "type": format!("0x1eabed72c53feb3805120a081dc15963c204dc8d091542592abaf7a35689b2fb::pool::Pool<{COIN_A}, {COIN_B}>"),
+                    "hasPublicTransfer": true,
+                    "fields": {"current_sqrt_price": SQRT_PRICE_ONE.to_string()}
+                }
+            }
+        }))
+        .unwrap();
+        let source = RecordedSource::from_txs(vec![])
+            .with_objects(vec![pool])
+            .unwrap();
+        assert_eq!(
+            pool_sqrt_price(&source, POOL).await.unwrap(),
+            SQRT_PRICE_ONE
+        );
+    }
+}
@@ -0,0 +1,501 @@
+use anyhow::{Context, Result, anyhow};
+use mini_macro::here as h;
+use reqwest::{
+    StatusCode, Url,
+    header::{HeaderMap, HeaderName, HeaderValue, RETRY_AFTER},
+};
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+use serde_json::{Value, json, value::RawValue};
+use std::{
+    collections::BTreeMap,
+    str::FromStr,
+    sync::{
+        Mutex,
+        atomic::{AtomicU64, AtomicUsize, Ordering},
+    },
+    time::{Duration, Instant},
+};
+use sui_sdk::rpc_types::{SuiTransactionBlockResponseQuery, TransactionBlocksPage};
+use sui_types::digests::TransactionDigest;
+
+use crate::errors::RpcError;
+
+pub const DEFAULT_REQUEST_TIMEOUT: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Deserialize)]
+struct JsonRpcResponse {
+    result: Option<Box<RawValue>>,
+    error: Option<JsonRpcError>,
+}
+
+#[derive(Debug, Deserialize)]
+struct JsonRpcError {
+    code: i64,
+    message: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct RetryPolicy {
+    pub max_retries: u32,
+    pub initial_backoff_ms: u64,
+    pub max_backoff_ms: u64,
+    pub multiplier: f64,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 5,
+            initial_backoff_ms: 200,
+            max_backoff_ms: 10_000,
+            multiplier: 2.0,
+        }
+    }
+}
+
+impl RetryPolicy {
+    pub fn none() -> Self {
+        Self {
+            max_retries: 0,
+            ..Default::default()
+        }
+    }
+
+    // A multiplier below 1 would shrink the backoff, a maximum below the
+    // initial backoff would cut it from the start.
+    pub fn is_valid(&self) -> bool {
+        self.multiplier.is_finite()
+            && self.multiplier >= 1.0
+            && self.initial_backoff_ms <= self.max_backoff_ms
+    }
+
+    fn backoff(&self, attempt: u32) -> Duration {
+        let ms = self.initial_backoff_ms as f64 * self.multiplier.powi(attempt as i32);
+        Duration::from_millis(ms.min(self.max_backoff_ms as f64) as u64)
+    }
+
+    // How long to wait before retrying after `error`. A node's `Retry-After`
+    // is followed up to the maximum backoff.
+    fn delay(&self, error: &RpcError, attempt: u32) -> Duration {
+        match error {
+            RpcError::RateLimited {
+                retry_after: Some(retry_after),
+            } => (*retry_after).min(Duration::from_millis(self.max_backoff_ms)),
+            _ => self.backoff(attempt),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct RateLimit {
+    pub requests_per_second: f64,
+    pub burst: u32,
+}
+
+impl RateLimit {
+    // A bucket that never refills would wait forever.
+    pub fn is_valid(&self) -> bool {
+        self.requests_per_second.is_finite() && self.requests_per_second > 0.0 && self.burst > 0
+    }
+}
+
+struct Bucket {
+    tokens: f64,
+    last_refill: Instant,
+}
+
+// Token bucket, one per endpoint.
+pub struct RateLimiter {
+    limit: RateLimit,
+    bucket: Mutex<Bucket>,
+}
+
+impl RateLimiter {
+    pub fn new(limit: RateLimit) -> Self {
+        Self {
+            bucket: Mutex::new(Bucket {
+                tokens: limit.burst as f64,
+                last_refill: Instant::now(),
+            }),
+            limit,
+        }
+    }
+
+    pub async fn acquire(&self) {
+        loop {
+            let wait = {
+                let mut bucket = self.bucket.lock().unwrap();
+                let now = Instant::now();
+                let elapsed = now.duration_since(bucket.last_refill).as_secs_f64();
+                bucket.tokens = (bucket.tokens + elapsed * self.limit.requests_per_second)
+                    .min(self.limit.burst.max(1) as f64);
+                bucket.last_refill = now;
+
+                if bucket.tokens >= 1.0 {
+                    bucket.tokens -= 1.0;
+                    return;
+                }
+                Duration::from_secs_f64((1.0 - bucket.tokens) / self.limit.requests_per_second)
+            };
+            tokio::time::sleep(wait).await;
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RpcConfig {
+    pub headers: BTreeMap<String, String>,
+    pub request_timeout: Duration,
+    pub retry: RetryPolicy,
+    pub rate_limit: Option<RateLimit>,
+}
+
+impl Default for RpcConfig {
+    fn default() -> Self {
+        Self {
+            headers: BTreeMap::new(),
+            request_timeout: DEFAULT_REQUEST_TIMEOUT,
+            retry: RetryPolicy::default(),
+            rate_limit: None,
+        }
+    }
+}
+
+struct Endpoint {
+    url: Url,
+    limiter: Option<RateLimiter>,
+}
+
+// Minimal JSON-RPC client for the read methods the fetcher needs. Unlike
+// `SuiClient` it lets us attach arbitrary headers, e.g. provider api keys,
+// and fails over across several endpoints.
+pub struct RpcClient {
+    http: reqwest::Client,
+    endpoints: Vec<Endpoint>,
+    active: AtomicUsize,
+    retry: RetryPolicy,
+    next_id: AtomicU64,
+}
+
+impl RpcClient {
+    pub fn new(urls: &[String], config: &RpcConfig) -> Result<Self> {
+        if urls.is_empty() {
+            return Err(anyhow!("no rpc urls"));
+        }
+        if let Some(rate_limit) = &config.rate_limit {
+            if !rate_limit.is_valid() {
+                return Err(anyhow!("invalid rate limit {rate_limit:?}"));
+            }
+        }
+        if !config.retry.is_valid() {
+            return Err(anyhow!("invalid retry policy {:?}", config.retry));
+        }
+        let endpoints = urls
+            .iter()
+            .map(|url| {
+                Ok(Endpoint {
+                    url: Url::parse(url).with_context(|| format!("invalid rpc url {url}"))?,
+                    limiter: config.rate_limit.clone().map(RateLimiter::new),
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let mut header_map = HeaderMap::new();
+        for (key, value) in &config.headers {
+            header_map.insert(
+                HeaderName::from_str(key).with_context(|| format!("invalid header {key}"))?,
+                HeaderValue::from_str(value).with_context(|| format!("invalid header {key}"))?,
+            );
+        }
+
+        let http = reqwest::Client::builder()
+            .default_headers(header_map)
+            .timeout(config.request_timeout)
+            .gzip(true)
+            .build()
+            .context(h!())?;
+
+        Ok(Self {
+            http,
+            endpoints,
+            active: AtomicUsize::new(0),
+            retry: config.retry.clone(),
+            next_id: AtomicU64::new(1),
+        })
+    }
+
+    pub fn url(&self) -> &Url {
+        &self.endpoints[self.active.load(Ordering::Relaxed)].url
+    }
+
+    pub async fn call<T: DeserializeOwned>(&self, method: &str, params: Value) -> Result<T> {
+        let result = self.call_raw(method, params).await?;
+        serde_json::from_str(result.get()).with_context(|| format!("decode {method} result"))
+    }
+
+    // The `result` of the call as the node sent it.
+    pub async fn call_raw(&self, method: &str, params: Value) -> Result<Box<RawValue>> {
+        let active = self.active.load(Ordering::Relaxed);
+        let mut last_err = None;
+
+        for i in 0..self.endpoints.len() {
+            let idx = (active + i) % self.endpoints.len();
+            let endpoint = &self.endpoints[idx];
+
+            match self.call_with_retry(endpoint, method, &params).await {
+                Ok(result) => {
+                    if idx != active {
+                        tracing::warn!(
+                            from = %self.endpoints[active].url,
+                            to = %endpoint.url,
+                            "rpc endpoint failover"
+                        );
+                        self.active.store(idx, Ordering::Relaxed);
+                    }
+                    return Ok(result);
+                }
+                Err(e) if e.is_endpoint_failure() => {
+                    tracing::error!(
+                        endpoint = %endpoint.url,
+                        method,
+                        error = %e,
+                        "rpc endpoint failed"
+                    );
+                    last_err = Some(e);
+                }
+                Err(e) => return Err(e).with_context(|| format!("{method} on {}", endpoint.url)),
+            }
+        }
+
+        Err(last_err.unwrap()).with_context(|| format!("{method}: all rpc endpoints failed"))
+    }
+
+    async fn call_with_retry(
+        &self,
+        endpoint: &Endpoint,
+        method: &str,
+        params: &Value,
+    ) -> Result<Box<RawValue>, RpcError> {
+        let mut attempt = 0;
+        loop {
+            if let Some(limiter) = &endpoint.limiter {
+                limiter.acquire().await;
+            }
+
+            match self.send(endpoint, method, params).await {
+                Ok(result) => return Ok(result),
+                Err(e) if e.is_retryable() && attempt < self.retry.max_retries => {
+                    let backoff = self.retry.delay(&e, attempt);
+                    tracing::warn!(
+                        endpoint = %endpoint.url,
+                        method,
+                        attempt,
+                        backoff_ms = backoff.as_millis() as u64,
+                        error = %e,
+                        "rpc request failed, retrying"
+                    );
+                    tokio::time::sleep(backoff).await;
+                    attempt += 1;
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    async fn send(
+        &self,
+        endpoint: &Endpoint,
+        method: &str,
+        params: &Value,
+    ) -> Result<Box<RawValue>, RpcError> {
+        let body = json!({
+            "jsonrpc": "2.0",
+            "id": self.next_id.fetch_add(1, Ordering::Relaxed),
+            "method": method,
+            "params": params,
+        });
+
+        let resp = self
+            .http
+            .post(endpoint.url.clone())
+            .json(&body)
+            .send()
+            .await?;
+
+        let status = resp.status();
+        if status == StatusCode::TOO_MANY_REQUESTS {
+            let retry_after = resp
+                .headers()
+                .get(RETRY_AFTER)
+                .and_then(|v| v.to_str().ok())
+                .and_then(|v| v.parse::<u64>().ok())
+                .map(Duration::from_secs);
+            return Err(RpcError::RateLimited { retry_after });
+        }
+        if !status.is_success() {
+            let body = resp.text().await.unwrap_or_default();
+            return Err(RpcError::Http {
+                status: status.as_u16(),
+                body,
+            });
+        }
+
+        let resp: JsonRpcResponse = resp.json().await?;
+        if let Some(e) = resp.error {
+            return Err(RpcError::JsonRpc {
+                code: e.code,
+                message: e.message,
+            });
+        }
+        resp.result.ok_or(RpcError::EmptyResponse)
+    }
+
+    pub async fn query_transaction_blocks(
+        &self,
+        query: &SuiTransactionBlockResponseQuery,
+        cursor: Option<TransactionDigest>,
+        limit: Option<usize>,
+        descending_order: bool,
+    ) -> Result<TransactionBlocksPage> {
+        self.call(
+            "suix_queryTransactionBlocks",
+            json!([query, cursor, limit, descending_order]),
+        )
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{Value, json};
+    use std::time::{Duration, Instant};
+
+    use super::{RateLimit, RetryPolicy, RpcClient, RpcConfig};
+    use crate::{
+        errors::RpcError,
+        fake_rpc::{FakeRpcServer, Fault},
+    };
+
+    fn config() -> RpcConfig {
+        RpcConfig {
+            retry: RetryPolicy {
+                initial_backoff_ms: 1,
+                max_backoff_ms: 5,
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+    }
+
+    async fn query(client: &RpcClient) -> anyhow::Result<Value> {
+        client
+            .call(
+                "suix_queryTransactionBlocks",
+                json!([{}, null, null, false]),
+            )
+            .await
+    }
+
+    #[tokio::test]
+    async fn test_retry_on_rate_limit() {
+        let server = FakeRpcServer::start(vec![]).await.unwrap();
+        server.inject(Fault::RateLimited);
+        server.inject(Fault::Status(502));
+
+        let client = RpcClient::new(&[server.url()], &config()).unwrap();
+        query(&client).await.unwrap();
+        assert_eq!(server.requests(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_give_up_after_max_retries() {
+        let server = FakeRpcServer::start(vec![]).await.unwrap();
+        server.set_down(true);
+
+        let mut config = config();
+        config.retry.max_retries = 2;
+        let client = RpcClient::new(&[server.url()], &config).unwrap();
+        assert!(query(&client).await.is_err());
+        assert_eq!(server.requests(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_failover() {
+        let down = FakeRpcServer::start(vec![]).await.unwrap();
+        down.set_down(true);
+        let up = FakeRpcServer::start(vec![]).await.unwrap();
+
+        let client = RpcClient::new(&[down.url(), up.url()], &config()).unwrap();
+        query(&client).await.unwrap();
+        assert_eq!(client.url().as_str(), format!("{}/", up.url()));
+
+        // sticks to the healthy endpoint
+        query(&client).await.unwrap();
+        assert_eq!(up.requests(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_json_rpc_error_is_not_retried() {
+        let server = FakeRpcServer::start(vec![]).await.unwrap();
+        server.inject(Fault::JsonRpc(-32602, "Invalid params".to_string()));
+
+        let client = RpcClient::new(&[server.url()], &config()).unwrap();
+        let err = query(&client).await.unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<RpcError>(),
+            Some(RpcError::JsonRpc { code: -32602, .. })
+        ));
+        assert_eq!(server.requests(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_rate_limit() {
+        let server = FakeRpcServer::start(vec![]).await.unwrap();
+        let mut config = config();
+        config.rate_limit = Some(RateLimit {
+            requests_per_second: 20.0,
+            burst: 2,
+        });
+        let client = RpcClient::new(&[server.url()], &config).unwrap();
+
+        // the burst goes through at once, the next two wait 50ms each
+        let start = Instant::now();
+        for _ in 0..4 {
+            query(&client).await.unwrap();
+        }
+        assert!(start.elapsed() >= Duration::from_millis(90));
+        assert_eq!(server.requests(), 4);
+    }
+
+    #[test]
+    fn test_invalid_rate_limit() {
+        let mut config = config();
+        config.rate_limit = Some(RateLimit {
+            requests_per_second: 0.0,
+            burst: 1,
+        });
+        assert!(RpcClient::new(&["http://localhost:9000".to_string()], &config).is_err());
+    }
+
+    #[test]
+    fn test_invalid_retry_policy() {
+        let mut config = config();
+        config.retry.multiplier = 0.5;
+        assert!(RpcClient::new(&["http://localhost:9000".to_string()], &config).is_err());
+    }
+
+    #[test]
+    fn test_retry_after_is_capped() {
+        let retry = RetryPolicy {
+            max_backoff_ms: 5_000,
+            ..Default::default()
+        };
+        let rate_limited = |secs| RpcError::RateLimited {
+            retry_after: Some(Duration::from_secs(secs)),
+        };
+        assert_eq!(retry.delay(&rate_limited(2), 0), Duration::from_secs(2));
+        assert_eq!(retry.delay(&rate_limited(3600), 0), Duration::from_secs(5));
+    }
+}
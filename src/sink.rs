@@ -0,0 +1,108 @@
+use anyhow::{Context, Result};
+use mini_macro::here as h;
+use std::{
+    fs::File,
+    io::{BufWriter, Write},
+    path::Path,
+};
+
+use crate::{TransactionKind, TxType, config::SinkConfig};
+
+// The variant name of `tx_type`, e.g. `Swap`.
+fn kind(tx_type: &TxType) -> String {
+    match serde_json::to_value(tx_type) {
+        Ok(serde_json::Value::Object(map)) => map.keys().next().cloned().unwrap_or_default(),
+        Ok(serde_json::Value::String(name)) => name,
+        _ => String::new(),
+    }
+}
+
+fn write_jsonl(out: impl Write, txs: &[TransactionKind]) -> Result<()> {
+    let mut out = BufWriter::new(out);
+    for tx in txs {
+        serde_json::to_writer(&mut out, tx).context(h!())?;
+        out.write_all(b"\n").context(h!())?;
+    }
+    out.flush().context(h!())
+}
+
+// One row per transaction, the decoded details as JSON in the last column.
+fn write_csv(path: &Path, txs: &[TransactionKind]) -> Result<()> {
+    let mut writer = csv::Writer::from_path(path).context(h!())?;
+    writer
+        .write_record(["tx_hash", "event_timestamp_ms", "kind", "details"])
+        .context(h!())?;
+    for tx in txs {
+        writer
+            .write_record([
+                tx.tx_hash.to_string(),
+                tx.event_timestamp_ms.to_string(),
+                kind(&tx.tx_type),
+                serde_json::to_string(&tx.tx_type).context(h!())?,
+            ])
+            .context(h!())?;
+    }
+    writer.flush().context(h!())
+}
+
+// Writes `txs` to every sink of the config. Files are overwritten.
+pub fn write(sinks: &[SinkConfig], txs: &[TransactionKind]) -> Result<()> {
+    for sink in sinks {
+        match sink {
+            SinkConfig::Stdout => write_jsonl(std::io::stdout().lock(), txs).context(h!())?,
+            SinkConfig::Jsonl { path } => {
+                let file = File::create(path)
+                    .with_context(|| format!("create sink {}", path.display()))?;
+                write_jsonl(file, txs).context(h!())?;
+            }
+            SinkConfig::Csv { path } => {
+                write_csv(path, txs).with_context(|| format!("write sink {}", path.display()))?
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+    use sui_types::digests::TransactionDigest;
+
+    use super::write;
+    use crate::{TransactionKind, TxType, config::SinkConfig};
+
+    #[test]
+    fn test_write_sinks() {
+        let dir = std::env::temp_dir().join(format!("sui_txs_sink_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let jsonl = dir.join("txs.jsonl");
+        let csv = dir.join("txs.csv");
+        let txs = vec![TransactionKind {
+            tx_type: TxType::Unknown,
+            tx_hash: TransactionDigest::from_str("CRNzhTtGj6R7JS1wWfjq5H6Xy84dQTVroDfg2dnp2nSu")
+                .unwrap(),
+            event_timestamp_ms: 1751968900000,
+        }];
+
+        write(
+            &[
+                SinkConfig::Jsonl {
+                    path: jsonl.clone(),
+                },
+                SinkConfig::Csv { path: csv.clone() },
+            ],
+            &txs,
+        )
+        .unwrap();
+
+        let lines = std::fs::read_to_string(&jsonl).unwrap();
+        let read: TransactionKind = serde_json::from_str(lines.trim_end()).unwrap();
+        assert_eq!(read.tx_hash, txs[0].tx_hash);
+        assert_eq!(
+            std::fs::read_to_string(&csv).unwrap(),
+            "tx_hash,event_timestamp_ms,kind,details\n\
+             CRNzhTtGj6R7JS1wWfjq5H6Xy84dQTVroDfg2dnp2nSu,1751968900000,Unknown,\"\"\"Unknown\"\"\"\n"
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
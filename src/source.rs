@@ -0,0 +1,525 @@
+use anyhow::{Context, Result};
+use futures::future::BoxFuture;
+use mini_macro::here as h;
+use serde_json::{Value, json};
+use std::{collections::HashMap, path::Path};
+use sui_sdk::{
+    SuiClient,
+    rpc_types::{
+        EventFilter, EventPage, Filter, ObjectsPage, Page, SuiEvent, SuiObjectDataOptions,
+        SuiObjectResponse, SuiObjectResponseQuery, SuiTransactionBlockEffectsAPI,
+        SuiTransactionBlockResponse, SuiTransactionBlockResponseOptions,
+        SuiTransactionBlockResponseQuery, TransactionBlocksPage, TransactionFilter,
+    },
+};
+use sui_types::{
+    base_types::{ObjectID, SuiAddress},
+    digests::TransactionDigest,
+    event::EventID,
+    object::Owner,
+};
+
+use crate::{ptb, rpc::RpcClient};
+
+const PAGE_SIZE: usize = 50;
+
+// Everything the fetcher reads from a node. Boxed futures keep the trait
+// object safe so the fetcher can hold any source behind `Box<dyn _>`.
+pub trait TransactionSource: Send + Sync {
+    fn query_transaction_blocks<'a>(
+        &'a self,
+        query: &'a SuiTransactionBlockResponseQuery,
+        cursor: Option<TransactionDigest>,
+        limit: Option<usize>,
+        descending_order: bool,
+    ) -> BoxFuture<'a, Result<TransactionBlocksPage>>;
+
+    fn get_transaction_block<'a>(
+        &'a self,
+        digest: TransactionDigest,
+        options: &'a SuiTransactionBlockResponseOptions,
+    ) -> BoxFuture<'a, Result<SuiTransactionBlockResponse>>;
+
+    fn get_object<'a>(
+        &'a self,
+        object_id: ObjectID,
+        options: &'a SuiObjectDataOptions,
+    ) -> BoxFuture<'a, Result<SuiObjectResponse>>;
+
+    fn query_events<'a>(
+        &'a self,
+        query: &'a EventFilter,
+        cursor: Option<EventID>,
+        limit: Option<usize>,
+        descending_order: bool,
+    ) -> BoxFuture<'a, Result<EventPage>>;
+
+    fn get_owned_objects<'a>(
+        &'a self,
+        address: SuiAddress,
+        query: &'a SuiObjectResponseQuery,
+        cursor: Option<ObjectID>,
+        limit: Option<usize>,
+    ) -> BoxFuture<'a, Result<ObjectsPage>>;
+
+    // The JSON-RPC result of `method` as the node sent it, for sources that
+    // talk to one. Recording archives it so a replay reads the same bytes.
+    fn call_raw<'a>(
+        &'a self,
+        _method: &'a str,
+        _params: Value,
+    ) -> Option<BoxFuture<'a, Result<String>>> {
+        None
+    }
+
+    // Writes out whatever the source still buffers, e.g. a recording archive.
+    fn finish(&self) -> Result<()> {
+        Ok(())
+    }
+}
+
+impl TransactionSource for SuiClient {
+    fn query_transaction_blocks<'a>(
+        &'a self,
+        query: &'a SuiTransactionBlockResponseQuery,
+        cursor: Option<TransactionDigest>,
+        limit: Option<usize>,
+        descending_order: bool,
+    ) -> BoxFuture<'a, Result<TransactionBlocksPage>> {
+        Box::pin(async move {
+            let page = self
+                .read_api()
+                .query_transaction_blocks(query.clone(), cursor, limit, descending_order)
+                .await
+                .context(h!())?;
+            Ok(page)
+        })
+    }
+
+    fn get_transaction_block<'a>(
+        &'a self,
+        digest: TransactionDigest,
+        options: &'a SuiTransactionBlockResponseOptions,
+    ) -> BoxFuture<'a, Result<SuiTransactionBlockResponse>> {
+        Box::pin(async move {
+            let tx = self
+                .read_api()
+                .get_transaction_with_options(digest, options.clone())
+                .await
+                .context(h!())?;
+            Ok(tx)
+        })
+    }
+
+    fn get_object<'a>(
+        &'a self,
+        object_id: ObjectID,
+        options: &'a SuiObjectDataOptions,
+    ) -> BoxFuture<'a, Result<SuiObjectResponse>> {
+        Box::pin(async move {
+            let object = self
+                .read_api()
+                .get_object_with_options(object_id, options.clone())
+                .await
+                .context(h!())?;
+            Ok(object)
+        })
+    }
+
+    fn query_events<'a>(
+        &'a self,
+        query: &'a EventFilter,
+        cursor: Option<EventID>,
+        limit: Option<usize>,
+        descending_order: bool,
+    ) -> BoxFuture<'a, Result<EventPage>> {
+        Box::pin(async move {
+            let page = self
+                .event_api()
+                .query_events(query.clone(), cursor, limit, descending_order)
+                .await
+                .context(h!())?;
+            Ok(page)
+        })
+    }
+
+    fn get_owned_objects<'a>(
+        &'a self,
+        address: SuiAddress,
+        query: &'a SuiObjectResponseQuery,
+        cursor: Option<ObjectID>,
+        limit: Option<usize>,
+    ) -> BoxFuture<'a, Result<ObjectsPage>> {
+        Box::pin(async move {
+            let page = self
+                .read_api()
+                .get_owned_objects(address, Some(query.clone()), cursor, limit)
+                .await
+                .context(h!())?;
+            Ok(page)
+        })
+    }
+}
+
+impl TransactionSource for RpcClient {
+    fn query_transaction_blocks<'a>(
+        &'a self,
+        query: &'a SuiTransactionBlockResponseQuery,
+        cursor: Option<TransactionDigest>,
+        limit: Option<usize>,
+        descending_order: bool,
+    ) -> BoxFuture<'a, Result<TransactionBlocksPage>> {
+        Box::pin(self.call(
+            "suix_queryTransactionBlocks",
+            json!([query, cursor, limit, descending_order]),
+        ))
+    }
+
+    fn get_transaction_block<'a>(
+        &'a self,
+        digest: TransactionDigest,
+        options: &'a SuiTransactionBlockResponseOptions,
+    ) -> BoxFuture<'a, Result<SuiTransactionBlockResponse>> {
+        Box::pin(self.call("sui_getTransactionBlock", json!([digest, options])))
+    }
+
+    fn get_object<'a>(
+        &'a self,
+        object_id: ObjectID,
+        options: &'a SuiObjectDataOptions,
+    ) -> BoxFuture<'a, Result<SuiObjectResponse>> {
+        Box::pin(self.call("sui_getObject", json!([object_id, options])))
+    }
+
+    fn query_events<'a>(
+        &'a self,
+        query: &'a EventFilter,
+        cursor: Option<EventID>,
+        limit: Option<usize>,
+        descending_order: bool,
+    ) -> BoxFuture<'a, Result<EventPage>> {
+        Box::pin(self.call(
+            "suix_queryEvents",
+            json!([query, cursor, limit, descending_order]),
+        ))
+    }
+
+    fn get_owned_objects<'a>(
+        &'a self,
+        address: SuiAddress,
+        query: &'a SuiObjectResponseQuery,
+        cursor: Option<ObjectID>,
+        limit: Option<usize>,
+    ) -> BoxFuture<'a, Result<ObjectsPage>> {
+        Box::pin(self.call(
+            "suix_getOwnedObjects",
+            json!([address, query, cursor, limit]),
+        ))
+    }
+
+    fn call_raw<'a>(
+        &'a self,
+        method: &'a str,
+        params: Value,
+    ) -> Option<BoxFuture<'a, Result<String>>> {
+        Some(Box::pin(async move {
+            let result = RpcClient::call_raw(self, method, params).await?;
+            Ok(result.get().to_string())
+        }))
+    }
+}
+
+// A directory of recorded responses: `*.json` files holding one
+// `SuiTransactionBlockResponse` each, and `objects/<id>.json` holding
+// `SuiObjectResponse`s. Of the transaction filters only the sender and
+// recipient ones are evaluated, the others and the owned object query
+// filters match everything. Events are served from the recorded
+// transactions.
+pub struct RecordedSource {
+    txs: Vec<SuiTransactionBlockResponse>,
+    objects: HashMap<ObjectID, SuiObjectResponse>,
+}
+
+impl RecordedSource {
+    pub fn load(dir: impl AsRef<Path>) -> Result<Self> {
+        let dir = dir.as_ref();
+        let mut txs = vec![];
+        for path in json_files(dir)? {
+            let content = std::fs::read_to_string(&path).context(h!())?;
+            let tx: SuiTransactionBlockResponse = serde_json::from_str(&content)
+                .with_context(|| format!("invalid transaction {}", path.display()))?;
+            txs.push(tx);
+        }
+        txs.sort_by_key(|tx| (tx.checkpoint, tx.timestamp_ms));
+
+        let mut objects = HashMap::new();
+        let objects_dir = dir.join("objects");
+        if objects_dir.is_dir() {
+            for path in json_files(&objects_dir)? {
+                let content = std::fs::read_to_string(&path).context(h!())?;
+                let object: SuiObjectResponse = serde_json::from_str(&content)
+                    .with_context(|| format!("invalid object {}", path.display()))?;
+                objects.insert(object.object_id().context(h!())?, object);
+            }
+        }
+
+        Ok(Self { txs, objects })
+    }
+
+    pub fn from_txs(mut txs: Vec<SuiTransactionBlockResponse>) -> Self {
+        txs.sort_by_key(|tx| (tx.checkpoint, tx.timestamp_ms));
+        Self {
+            txs,
+            objects: HashMap::new(),
+        }
+    }
+
+    pub fn with_objects(mut self, objects: Vec<SuiObjectResponse>) -> Result<Self> {
+        for object in objects {
+            self.objects
+                .insert(object.object_id().context(h!())?, object);
+        }
+        Ok(self)
+    }
+
+    fn page(
+        &self,
+        filter: Option<&TransactionFilter>,
+        cursor: Option<TransactionDigest>,
+        limit: Option<usize>,
+        descending_order: bool,
+    ) -> Result<TransactionBlocksPage> {
+        let mut txs = self
+            .txs
+            .iter()
+            .filter(|tx| filter.is_none_or(|filter| matches_filter(filter, tx)))
+            .collect::<Vec<_>>();
+        if descending_order {
+            txs.reverse();
+        }
+        page(txs, |tx| tx.digest, cursor, limit)
+    }
+
+    fn events(&self, query: &EventFilter) -> Vec<&SuiEvent> {
+        self.txs
+            .iter()
+            .filter_map(|tx| tx.events.as_ref())
+            .flat_map(|events| &events.data)
+            .filter(|event| query.matches(event))
+            .collect()
+    }
+
+    fn owned_objects(&self, address: SuiAddress) -> Vec<&SuiObjectResponse> {
+        let mut objects = self
+            .objects
+            .values()
+            .filter(|object| {
+                object
+                    .data
+                    .as_ref()
+                    .and_then(|data| data.owner.as_ref())
+                    .is_some_and(|owner| *owner == Owner::AddressOwner(address))
+            })
+            .collect::<Vec<_>>();
+        objects.sort_by_key(|object| object.object_id().ok());
+        objects
+    }
+}
+
+// Whether something at `timestamp_ms` happened before `since_ms`.
+pub(crate) fn before(since_ms: Option<u64>, timestamp_ms: Option<u64>) -> bool {
+    since_ms
+        .zip(timestamp_ms)
+        .is_some_and(|(since, at)| at < since)
+}
+
+// The events matching `filter` that `keep` accepts, newest first, as the time
+// and digest of their transaction. The search ends at events older than
+// `since_ms`.
+pub async fn search_events(
+    source: &dyn TransactionSource,
+    filter: &EventFilter,
+    since_ms: Option<u64>,
+    mut keep: impl FnMut(&SuiEvent) -> Result<bool>,
+) -> Result<Vec<(u64, TransactionDigest)>> {
+    let mut found = vec![];
+    let mut cursor = None;
+    loop {
+        let page = source
+            .query_events(filter, cursor, None, true)
+            .await
+            .context(h!())?;
+        for event in &page.data {
+            if before(since_ms, event.timestamp_ms) {
+                return Ok(found);
+            }
+            if keep(event)? {
+                found.push((event.timestamp_ms.unwrap_or_default(), event.id.tx_digest));
+            }
+        }
+        if !page.has_next_page {
+            return Ok(found);
+        }
+        cursor = page.next_cursor;
+    }
+}
+
+// The digests of `found`, newest first and each once.
+pub fn newest_first(mut found: Vec<(u64, TransactionDigest)>) -> Vec<TransactionDigest> {
+    found.sort_by(|a, b| b.0.cmp(&a.0));
+    let mut digests: Vec<TransactionDigest> = vec![];
+    for (_, digest) in found {
+        if !digests.contains(&digest) {
+            digests.push(digest);
+        }
+    }
+    digests
+}
+
+// The sender is the gas owner when the recording has no transaction input.
+// A recipient is whoever received coins or objects.
+pub(crate) fn matches_filter(filter: &TransactionFilter, tx: &SuiTransactionBlockResponse) -> bool {
+    match filter {
+        TransactionFilter::FromAddress(address) => {
+            let gas_owner = || {
+                let effects = tx.effects.as_ref()?;
+                effects.gas_object().owner.get_owner_address().ok()
+            };
+            ptb::sender(tx).or_else(gas_owner) == Some(*address)
+        }
+        TransactionFilter::ToAddress(address) => {
+            let owner = Owner::AddressOwner(*address);
+            let received_coins = tx
+                .balance_changes
+                .iter()
+                .flatten()
+                .any(|change| change.owner == owner && change.amount > 0);
+            let received_objects = tx.effects.as_ref().is_some_and(|effects| {
+                effects
+                    .created()
+                    .iter()
+                    .chain(effects.unwrapped())
+                    .any(|object| object.owner == owner)
+            });
+            received_coins || received_objects
+        }
+        _ => true,
+    }
+}
+
+// The page of `items` after `cursor`, ordered the way `items` are.
+pub(crate) fn page<T: Clone, C: PartialEq + std::fmt::Debug>(
+    items: Vec<&T>,
+    key: impl Fn(&T) -> C,
+    cursor: Option<C>,
+    limit: Option<usize>,
+) -> Result<Page<T, C>> {
+    let total = items.len();
+    let start = match cursor {
+        Some(cursor) => {
+            items
+                .iter()
+                .position(|item| key(item) == cursor)
+                .with_context(|| format!("unknown cursor {cursor:?}"))?
+                + 1
+        }
+        None => 0,
+    };
+    let data = items
+        .into_iter()
+        .skip(start)
+        .take(limit.unwrap_or(PAGE_SIZE))
+        .cloned()
+        .collect::<Vec<_>>();
+    let next_cursor = data.last().map(&key);
+    let has_next_page = start + data.len() < total;
+
+    Ok(Page {
+        data,
+        next_cursor,
+        has_next_page,
+    })
+}
+
+fn json_files(dir: &Path) -> Result<Vec<std::path::PathBuf>> {
+    let mut files = std::fs::read_dir(dir)
+        .with_context(|| format!("read dir {}", dir.display()))?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|p| p.is_file() && p.extension().is_some_and(|ext| ext == "json"))
+        .collect::<Vec<_>>();
+    files.sort();
+    Ok(files)
+}
+
+impl TransactionSource for RecordedSource {
+    fn query_transaction_blocks<'a>(
+        &'a self,
+        query: &'a SuiTransactionBlockResponseQuery,
+        cursor: Option<TransactionDigest>,
+        limit: Option<usize>,
+        descending_order: bool,
+    ) -> BoxFuture<'a, Result<TransactionBlocksPage>> {
+        Box::pin(async move { self.page(query.filter.as_ref(), cursor, limit, descending_order) })
+    }
+
+    fn get_transaction_block<'a>(
+        &'a self,
+        digest: TransactionDigest,
+        _options: &'a SuiTransactionBlockResponseOptions,
+    ) -> BoxFuture<'a, Result<SuiTransactionBlockResponse>> {
+        Box::pin(async move {
+            self.txs
+                .iter()
+                .find(|tx| tx.digest == digest)
+                .cloned()
+                .with_context(|| format!("transaction {digest} is not recorded"))
+        })
+    }
+
+    fn get_object<'a>(
+        &'a self,
+        object_id: ObjectID,
+        _options: &'a SuiObjectDataOptions,
+    ) -> BoxFuture<'a, Result<SuiObjectResponse>> {
+        Box::pin(async move {
+            self.objects
+                .get(&object_id)
+                .cloned()
+                .with_context(|| format!("object {object_id} is not recorded"))
+        })
+    }
+
+    fn query_events<'a>(
+        &'a self,
+        query: &'a EventFilter,
+        cursor: Option<EventID>,
+        limit: Option<usize>,
+        descending_order: bool,
+    ) -> BoxFuture<'a, Result<EventPage>> {
+        Box::pin(async move {
+            let mut events = self.events(query);
+            if descending_order {
+                events.reverse();
+            }
+            page(events, |event| event.id, cursor, limit)
+        })
+    }
+
+    fn get_owned_objects<'a>(
+        &'a self,
+        address: SuiAddress,
+        _query: &'a SuiObjectResponseQuery,
+        cursor: Option<ObjectID>,
+        limit: Option<usize>,
+    ) -> BoxFuture<'a, Result<ObjectsPage>> {
+        Box::pin(async move {
+            let objects = self.owned_objects(address);
+            page(
+                objects,
+                |object| object.object_id().unwrap_or(ObjectID::ZERO),
+                cursor,
+                limit,
+            )
+        })
+    }
+}
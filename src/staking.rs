@@ -0,0 +1,92 @@
+use std::str::FromStr;
+use sui_sdk::rpc_types::{ObjectChange, SuiTransactionBlockResponse};
+
+use crate::{
+    Stake, TxType, Unstake,
+    errors::EventDecodeError,
+    event_match::EventPattern,
+    events::{DecodeMode, StakingRequestEvent, UnstakingRequestEvent, parse},
+};
+
+const PROTOCOL: &str = "Sui staking";
+
+// Native staking with validators, from the events of the 0x3 system package.
+pub struct StakingDecoder {
+    stake: EventPattern,
+    unstake: EventPattern,
+    staked_sui: EventPattern,
+}
+
+impl Default for StakingDecoder {
+    fn default() -> Self {
+        let pattern = |s: &str| EventPattern::from_str(s).expect("valid system type");
+        Self {
+            stake: pattern("0x3::validator::StakingRequestEvent"),
+            unstake: pattern("0x3::validator::UnstakingRequestEvent"),
+            staked_sui: pattern("0x3::staking_pool::StakedSui"),
+        }
+    }
+}
+
+impl StakingDecoder {
+    // The `StakedSui` created by a stake or consumed by its withdrawal. Needs
+    // the response's object changes.
+    fn staked_sui(&self, tx_resp: &SuiTransactionBlockResponse, created: bool) -> Option<String> {
+        tx_resp
+            .object_changes
+            .as_ref()?
+            .iter()
+            .find_map(|change| match change {
+                ObjectChange::Created {
+                    object_type,
+                    object_id,
+                    ..
+                } if created => self.staked_sui.matches(object_type).map(|_| object_id),
+                ObjectChange::Deleted {
+                    object_type,
+                    object_id,
+                    ..
+                } if !created => self.staked_sui.matches(object_type).map(|_| object_id),
+                _ => None,
+            })
+            .map(|id| id.to_string())
+    }
+
+    pub fn decode(
+        &self,
+        tx_resp: &SuiTransactionBlockResponse,
+        mode: DecodeMode,
+    ) -> Result<Option<TxType>, EventDecodeError> {
+        let Some(events) = &tx_resp.events else {
+            return Ok(None);
+        };
+
+        for event in &events.data {
+            if self.stake.matches(&event.type_).is_some() {
+                let e: StakingRequestEvent = parse(PROTOCOL, event, mode)?;
+                return Ok(Some(TxType::Stake(Stake {
+                    validator: e.validator_address.to_string(),
+                    staker: e.staker_address.to_string(),
+                    pool_id: e.pool_id.to_string(),
+                    principal: e.amount,
+                    staked_sui: self.staked_sui(tx_resp, true),
+                    activation_epoch: e.epoch + 1,
+                })));
+            }
+            if self.unstake.matches(&event.type_).is_some() {
+                let e: UnstakingRequestEvent = parse(PROTOCOL, event, mode)?;
+                return Ok(Some(TxType::Unstake(Unstake {
+                    validator: e.validator_address.to_string(),
+                    staker: e.staker_address.to_string(),
+                    pool_id: e.pool_id.to_string(),
+                    principal: e.principal_amount,
+                    rewards: e.reward_amount,
+                    staked_sui: self.staked_sui(tx_resp, false),
+                    activation_epoch: e.stake_activation_epoch,
+                    unstaking_epoch: e.unstaking_epoch,
+                })));
+            }
+        }
+        Ok(None)
+    }
+}
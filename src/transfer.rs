@@ -22,7 +22,7 @@ fn transfer_amount(
 ) -> Result<i128> {
     let receiver_changes = balance_changes
         .iter()
-        .filter(|c| c.owner.get_owner_address().unwrap() == receiver && c.coin_type == token)
+        .filter(|c| c.owner.get_owner_address().ok() == Some(receiver) && c.coin_type == token)
         .collect::<Vec<_>>();
     if receiver_changes.is_empty() {
         bail!("NotFoundOfEmptyChanges")
@@ -97,18 +97,18 @@ fn transfer_to(balance_changes: &[BalanceChange], transfer_from: SuiAddress) ->
     }
     let c_except_from = balance_changes
         .iter()
-        .filter(|c| c.owner.get_owner_address().unwrap() != transfer_from)
+        .filter(|c| c.owner.get_owner_address().ok() != Some(transfer_from))
         .collect::<Vec<_>>();
     if c_except_from.is_empty() {
         return Ok(transfer_from);
     }
     if c_except_from.len() == 1 {
-        return Ok(c_except_from
+        return c_except_from
             .first()
             .unwrap()
             .owner
             .get_owner_address()
-            .unwrap());
+            .map_err(|e| anyhow!("Receiver is not an address: {e}"));
     }
 
     // let receiver_count: Vec<_> = balance_changes.iter().filter(|c| c.amount > 0).collect();
@@ -429,4 +429,185 @@ mod tests {
             }
         );
     }
+
+    mod props {
+        use bigdecimal::BigDecimal;
+        use proptest::prelude::*;
+        use std::str::FromStr;
+        use sui_sdk::rpc_types::BalanceChange;
+        use sui_types::{TypeTag, base_types::SuiAddress, object::Owner};
+
+        use super::decode_transfer;
+
+        const SUI: &str = "0x2::sui::SUI";
+        const COINS: [&str; 3] = [
+            SUI,
+            "0xdba34672e30cb065b1f93e3ab55318768fd6fef66c15942c9f7cb846e2f900e7::usdc::USDC",
+            "0xdeeb7a4662eec9f2f3def03fb937a663dddaa2e215b8078a284d026b7946c270::deep::DEEP",
+        ];
+
+        fn address(i: u64) -> SuiAddress {
+            SuiAddress::from_str(&format!("0x{i:064x}")).unwrap()
+        }
+
+        fn change(owner: SuiAddress, coin: &str, amount: i128) -> BalanceChange {
+            BalanceChange {
+                owner: Owner::AddressOwner(owner),
+                coin_type: TypeTag::from_str(coin).unwrap(),
+                amount,
+            }
+        }
+
+        // A transfer of one coin from `sender` to each recipient, plus the
+        // sender's net gas (negative when the storage rebate wins).
+        #[derive(Debug, Clone)]
+        struct Transfer {
+            sender: SuiAddress,
+            recipients: Vec<(SuiAddress, i128)>,
+            coin: &'static str,
+            gas: i128,
+        }
+
+        impl Transfer {
+            // aggregated per owner and coin, the way the node reports them
+            fn balance_changes(&self) -> Vec<BalanceChange> {
+                let total = self.recipients.iter().map(|(_, a)| a).sum::<i128>();
+                let mut changes = vec![];
+                if self.coin == SUI {
+                    changes.push(change(self.sender, SUI, -total - self.gas));
+                } else {
+                    changes.push(change(self.sender, SUI, -self.gas));
+                    changes.push(change(self.sender, self.coin, -total));
+                }
+                for (recipient, amount) in &self.recipients {
+                    changes.push(change(*recipient, self.coin, *amount));
+                }
+                changes
+            }
+        }
+
+        fn transfer(max_recipients: usize) -> impl Strategy<Value = Transfer> {
+            (
+                prop::collection::hash_set(1u64..u64::MAX, 2..=max_recipients + 1),
+                // large enough that the sender's SUI stays negative after a rebate
+                prop::collection::vec(10_000_000i128..1_000_000_000_000_000, max_recipients),
+                prop::sample::select(COINS.to_vec()),
+                // net gas, never zero or the node would drop the change
+                prop_oneof![1i128..100_000_000, -2_000_000i128..-1],
+            )
+                .prop_map(|(addresses, amounts, coin, gas)| {
+                    let mut addresses = addresses.into_iter().map(address);
+                    let sender = addresses.next().unwrap();
+                    Transfer {
+                        sender,
+                        recipients: addresses.zip(amounts).collect(),
+                        coin,
+                        gas,
+                    }
+                })
+        }
+
+        fn shuffled(t: Transfer) -> impl Strategy<Value = (Transfer, Vec<BalanceChange>)> {
+            let changes = t.balance_changes();
+            (Just(t), Just(changes).prop_shuffle())
+        }
+
+        proptest! {
+            #[test]
+            fn prop_recipient_sees_its_own_amount(
+                (t, changes) in transfer(4).prop_flat_map(shuffled)
+            ) {
+                for (recipient, amount) in &t.recipients {
+                    let decoded = decode_transfer(changes.clone(), Some(*recipient)).unwrap();
+                    prop_assert_eq!(decoded.sender, t.sender.to_string());
+                    prop_assert_eq!(decoded.receiver, recipient.to_string());
+                    prop_assert_eq!(decoded.token, TypeTag::from_str(t.coin).unwrap().to_string());
+                    prop_assert_eq!(decoded.amount, BigDecimal::from(*amount));
+                }
+            }
+
+            #[test]
+            fn prop_sender_sees_single_recipient(
+                (t, changes) in transfer(1).prop_flat_map(shuffled)
+            ) {
+                let (recipient, amount) = t.recipients[0];
+                let decoded = decode_transfer(changes, Some(t.sender)).unwrap();
+                prop_assert_eq!(decoded.sender, t.sender.to_string());
+                prop_assert_eq!(decoded.receiver, recipient.to_string());
+                prop_assert_eq!(decoded.amount, BigDecimal::from(amount));
+            }
+
+            #[test]
+            fn prop_sender_with_many_recipients_is_ambiguous(
+                (t, changes) in transfer(4)
+                    .prop_filter("many recipients", |t| t.recipients.len() > 1)
+                    .prop_flat_map(shuffled)
+            ) {
+                prop_assert!(decode_transfer(changes, Some(t.sender)).is_err());
+            }
+
+            #[test]
+            fn prop_gas_only_is_self_transfer(sender in 1u64..u64::MAX, gas in 1i128..100_000_000) {
+                let sender = address(sender);
+                let decoded = decode_transfer(vec![change(sender, SUI, -gas)], Some(sender)).unwrap();
+                prop_assert_eq!(&decoded.sender, &decoded.receiver);
+                prop_assert_eq!(decoded.amount, BigDecimal::from(gas));
+            }
+
+            #[test]
+            fn prop_conserves(
+                (t, changes) in transfer(1).prop_flat_map(shuffled),
+                from_sender in any::<bool>(),
+            ) {
+                let (recipient, _) = t.recipients[0];
+                let user = if from_sender { t.sender } else { recipient };
+                let decoded = decode_transfer(changes.clone(), Some(user)).unwrap();
+
+                // what left the sender is what the receiver got, plus gas when
+                // the coin is SUI
+                let sender = SuiAddress::from_str(&decoded.sender).unwrap();
+                let outflow = -changes
+                    .iter()
+                    .filter(|c| c.owner.get_owner_address().ok() == Some(sender))
+                    .filter(|c| c.coin_type.to_string() == decoded.token)
+                    .map(|c| c.amount)
+                    .sum::<i128>();
+                let gas = if t.coin == SUI { t.gas } else { 0 };
+                prop_assert_eq!(BigDecimal::from(outflow), decoded.amount + BigDecimal::from(gas));
+            }
+
+            #[test]
+            fn prop_never_panics(
+                changes in prop::collection::vec(
+                    (
+                        0u64..5,
+                        prop::sample::select(COINS.to_vec()),
+                        -1_000_000_000_000i128..1_000_000_000_000,
+                    ),
+                    0..8,
+                ),
+                user in 0u64..5,
+            ) {
+                let changes = changes
+                    .into_iter()
+                    .map(|(owner, coin, amount)| BalanceChange {
+                        // 0 stands for an owner without an address
+                        owner: if owner == 0 { Owner::Immutable } else { Owner::AddressOwner(address(owner)) },
+                        coin_type: TypeTag::from_str(coin).unwrap(),
+                        amount,
+                    })
+                    .collect::<Vec<_>>();
+
+                if let Ok(decoded) = decode_transfer(changes.clone(), Some(address(user.max(1)))) {
+                    // the decoded amount is one of the receiver's own changes
+                    let receiver = SuiAddress::from_str(&decoded.receiver).unwrap();
+                    prop_assert!(changes.iter().any(|c| {
+                        c.owner.get_owner_address().ok() == Some(receiver)
+                            && c.coin_type.to_string() == decoded.token
+                            && decoded.amount == BigDecimal::from(c.amount.abs())
+                    }));
+                }
+            }
+        }
+    }
 }